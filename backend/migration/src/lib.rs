@@ -9,6 +9,23 @@ mod m20250608_062748_medicines;
 mod m20250608_062839_medication_schedules;
 mod m20250608_062931_medication_logs;
 mod m20250608_063023_add_foreign_keys;
+mod m20250620_000001_add_snooze_to_medication_logs;
+mod m20250621_000001_schedule_time_of_day;
+mod m20250622_000001_scheduler_states;
+mod m20250623_000001_add_interval_to_medication_schedules;
+mod m20250624_000001_add_course_to_medication_schedules;
+mod m20250625_000001_add_rrule_to_medication_schedules;
+mod m20250626_000001_add_prn_to_medicines;
+mod m20250627_000001_add_stock_to_medicines;
+mod m20250628_000001_add_archived_at_to_medicines;
+mod m20250629_000001_add_indexes_to_medication_logs;
+mod m20250630_000001_medication_log_status_check;
+mod m20250701_000001_notification_logs;
+mod m20250702_000001_notification_channels;
+mod m20250703_000001_notification_dead_letters;
+mod m20250704_000001_add_paused_by_archive_to_medication_schedules;
+mod m20250705_000001_unique_medication_log_occurrence;
+mod m20250706_000001_add_frequency_index_to_medication_schedules;
 pub struct Migrator;
 
 #[async_trait::async_trait]
@@ -22,6 +39,23 @@ impl MigratorTrait for Migrator {
             Box::new(m20250608_062839_medication_schedules::Migration),
             Box::new(m20250608_062931_medication_logs::Migration),
             Box::new(m20250608_063023_add_foreign_keys::Migration),
+            Box::new(m20250620_000001_add_snooze_to_medication_logs::Migration),
+            Box::new(m20250621_000001_schedule_time_of_day::Migration),
+            Box::new(m20250622_000001_scheduler_states::Migration),
+            Box::new(m20250623_000001_add_interval_to_medication_schedules::Migration),
+            Box::new(m20250624_000001_add_course_to_medication_schedules::Migration),
+            Box::new(m20250625_000001_add_rrule_to_medication_schedules::Migration),
+            Box::new(m20250626_000001_add_prn_to_medicines::Migration),
+            Box::new(m20250627_000001_add_stock_to_medicines::Migration),
+            Box::new(m20250628_000001_add_archived_at_to_medicines::Migration),
+            Box::new(m20250629_000001_add_indexes_to_medication_logs::Migration),
+            Box::new(m20250630_000001_medication_log_status_check::Migration),
+            Box::new(m20250701_000001_notification_logs::Migration),
+            Box::new(m20250702_000001_notification_channels::Migration),
+            Box::new(m20250703_000001_notification_dead_letters::Migration),
+            Box::new(m20250704_000001_add_paused_by_archive_to_medication_schedules::Migration),
+            Box::new(m20250705_000001_unique_medication_log_occurrence::Migration),
+            Box::new(m20250706_000001_add_frequency_index_to_medication_schedules::Migration),
             // inject-above (do not remove this comment)
         ]
     }
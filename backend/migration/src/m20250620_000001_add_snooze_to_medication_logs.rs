@@ -0,0 +1,23 @@
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        // スヌーズ後の再通知時刻・再通知済み時刻・スヌーズ回数
+        add_column(m, "medication_logs", "snoozed_until", ColType::TimestampWithTimeZoneNull).await?;
+        add_column(m, "medication_logs", "renotified_at", ColType::TimestampWithTimeZoneNull).await?;
+        add_column(m, "medication_logs", "snooze_count", ColType::IntegerWithDefault(0)).await?;
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        remove_column(m, "medication_logs", "snooze_count").await?;
+        remove_column(m, "medication_logs", "renotified_at").await?;
+        remove_column(m, "medication_logs", "snoozed_until").await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,128 @@
+use loco_rs::schema::*;
+use sea_orm_migration::{prelude::*, sea_orm::DatabaseBackend};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// 全曜日（月曜=1 〜 日曜=64 のビットマスク）
+const ALL_WEEKDAYS: i16 = 0b111_1111;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        // 服薬時刻(TIME)と曜日ビットマスクの列を追加
+        add_column(
+            m,
+            "medication_schedules",
+            "time_of_day",
+            ColType::TimeWithDefault("00:00:00".to_string()),
+        )
+        .await?;
+        add_column(
+            m,
+            "medication_schedules",
+            "weekdays",
+            ColType::SmallIntegerWithDefault(ALL_WEEKDAYS),
+        )
+        .await?;
+
+        // 既存のスケジュールを移行
+        let time_of_day = match m.get_database_backend() {
+            DatabaseBackend::Postgres => "CAST(scheduled_time AS TIME)",
+            _ => "time(scheduled_time)",
+        };
+        let db = m.get_connection();
+        db.execute_unprepared(&format!(
+            "UPDATE medication_schedules SET time_of_day = {time_of_day}"
+        ))
+        .await?;
+
+        // 曜日は "1,3,5" のような文字列に含まれる番号を対応するビットに変換する
+        let weekdays = (1..=7)
+            .map(|day| {
+                format!(
+                    "CASE WHEN days_of_week LIKE '%{day}%' THEN {} ELSE 0 END",
+                    1 << (day - 1)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" + ");
+        db.execute_unprepared(&format!(
+            "UPDATE medication_schedules SET weekdays = {weekdays} \
+             WHERE frequency = 'weekly' AND days_of_week IS NOT NULL"
+        ))
+        .await?;
+
+        remove_column(m, "medication_schedules", "scheduled_time").await?;
+        remove_column(m, "medication_schedules", "days_of_week").await?;
+
+        // 時刻での検索用インデックス
+        m.create_index(
+            Index::create()
+                .name("idx_medication_schedules_time_of_day_active")
+                .table(MedicationSchedules::Table)
+                .col(MedicationSchedules::TimeOfDay)
+                .col(MedicationSchedules::Active)
+                .to_owned(),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        m.drop_index(
+            Index::drop()
+                .name("idx_medication_schedules_time_of_day_active")
+                .table(MedicationSchedules::Table)
+                .to_owned(),
+        )
+        .await?;
+
+        add_column(
+            m,
+            "medication_schedules",
+            "scheduled_time",
+            ColType::DateTimeWithDefault("2000-01-01 00:00:00".to_string()),
+        )
+        .await?;
+        add_column(m, "medication_schedules", "days_of_week", ColType::StringNull).await?;
+
+        // 日付部分は使われていなかったので固定の日付に戻す
+        let scheduled_time = match m.get_database_backend() {
+            DatabaseBackend::Postgres => "CAST('2000-01-01' AS DATE) + time_of_day",
+            _ => "'2000-01-01 ' || time_of_day",
+        };
+        let db = m.get_connection();
+        db.execute_unprepared(&format!(
+            "UPDATE medication_schedules SET scheduled_time = {scheduled_time}"
+        ))
+        .await?;
+
+        let days_of_week = (1..=7)
+            .map(|day| {
+                format!(
+                    "CASE WHEN weekdays & {} <> 0 THEN '{day}' ELSE '' END",
+                    1 << (day - 1)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" || ");
+        db.execute_unprepared(&format!(
+            "UPDATE medication_schedules SET days_of_week = {days_of_week} \
+             WHERE weekdays <> {ALL_WEEKDAYS}"
+        ))
+        .await?;
+
+        remove_column(m, "medication_schedules", "weekdays").await?;
+        remove_column(m, "medication_schedules", "time_of_day").await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum MedicationSchedules {
+    Table,
+    TimeOfDay,
+    Active,
+}
@@ -0,0 +1,27 @@
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        // スケジューラーごとの処理済み時刻（ウォーターマーク）
+        create_table(m, "scheduler_states",
+            &[
+            
+            ("id", ColType::PkAuto),
+            
+            ("name", ColType::StringUniq),
+            ("last_run_at", ColType::TimestampWithTimeZone),
+            ],
+            &[
+            ]
+        ).await
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        drop_table(m, "scheduler_states").await
+    }
+}
@@ -0,0 +1,35 @@
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        // frequency = "custom" の間隔（N 時間/日/週ごと）と起点の日付
+        add_column(
+            m,
+            "medication_schedules",
+            "interval_count",
+            ColType::IntegerNull,
+        )
+        .await?;
+        add_column(
+            m,
+            "medication_schedules",
+            "interval_unit",
+            ColType::StringNull,
+        )
+        .await?;
+        add_column(m, "medication_schedules", "start_date", ColType::DateNull).await?;
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        remove_column(m, "medication_schedules", "start_date").await?;
+        remove_column(m, "medication_schedules", "interval_unit").await?;
+        remove_column(m, "medication_schedules", "interval_count").await?;
+        Ok(())
+    }
+}
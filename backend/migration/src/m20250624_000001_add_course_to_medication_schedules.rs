@@ -0,0 +1,29 @@
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        // 服薬コースの終了日と総服薬回数、これまでに通知した回数
+        add_column(m, "medication_schedules", "end_date", ColType::DateNull).await?;
+        add_column(m, "medication_schedules", "max_doses", ColType::IntegerNull).await?;
+        add_column(
+            m,
+            "medication_schedules",
+            "dose_count",
+            ColType::IntegerWithDefault(0),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        remove_column(m, "medication_schedules", "dose_count").await?;
+        remove_column(m, "medication_schedules", "max_doses").await?;
+        remove_column(m, "medication_schedules", "end_date").await?;
+        Ok(())
+    }
+}
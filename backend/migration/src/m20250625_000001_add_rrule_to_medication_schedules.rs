@@ -0,0 +1,19 @@
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        // frequency = "rrule" の繰り返し規則（RFC 5545 の RRULE）
+        add_column(m, "medication_schedules", "rrule", ColType::TextNull).await?;
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        remove_column(m, "medication_schedules", "rrule").await?;
+        Ok(())
+    }
+}
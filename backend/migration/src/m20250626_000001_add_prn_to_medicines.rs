@@ -0,0 +1,29 @@
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        // 頓服薬（必要時に服用する薬）と、服用間隔・1日の上限回数
+        add_column(
+            m,
+            "medicines",
+            "as_needed",
+            ColType::BooleanWithDefault(false),
+        )
+        .await?;
+        add_column(m, "medicines", "min_interval_minutes", ColType::IntegerNull).await?;
+        add_column(m, "medicines", "max_daily_doses", ColType::IntegerNull).await?;
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        remove_column(m, "medicines", "max_daily_doses").await?;
+        remove_column(m, "medicines", "min_interval_minutes").await?;
+        remove_column(m, "medicines", "as_needed").await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,33 @@
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        // 在庫数（`dosage` と同じ単位、NULL は在庫を管理しない）と補充通知の送信時刻
+        add_column(
+            m,
+            "medicines",
+            "stock_quantity",
+            ColType::DecimalLenNull(10, 2),
+        )
+        .await?;
+        add_column(
+            m,
+            "medicines",
+            "refill_alerted_at",
+            ColType::TimestampWithTimeZoneNull,
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        remove_column(m, "medicines", "refill_alerted_at").await?;
+        remove_column(m, "medicines", "stock_quantity").await?;
+        Ok(())
+    }
+}
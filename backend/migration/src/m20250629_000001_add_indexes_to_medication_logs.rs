@@ -0,0 +1,55 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        // 服薬記録の一覧（薬・期間・状態での絞り込み）用インデックス
+        m.create_index(
+            Index::create()
+                .name("idx_medication_logs_medicine_id_scheduled_time")
+                .table(MedicationLogs::Table)
+                .col(MedicationLogs::MedicineId)
+                .col(MedicationLogs::ScheduledTime)
+                .to_owned(),
+        )
+        .await?;
+        m.create_index(
+            Index::create()
+                .name("idx_medication_logs_status")
+                .table(MedicationLogs::Table)
+                .col(MedicationLogs::Status)
+                .to_owned(),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        m.drop_index(
+            Index::drop()
+                .name("idx_medication_logs_status")
+                .table(MedicationLogs::Table)
+                .to_owned(),
+        )
+        .await?;
+        m.drop_index(
+            Index::drop()
+                .name("idx_medication_logs_medicine_id_scheduled_time")
+                .table(MedicationLogs::Table)
+                .to_owned(),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum MedicationLogs {
+    Table,
+    MedicineId,
+    ScheduledTime,
+    Status,
+}
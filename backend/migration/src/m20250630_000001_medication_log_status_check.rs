@@ -0,0 +1,45 @@
+use sea_orm_migration::{prelude::*, sea_orm::DatabaseBackend};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const STATUSES: &str = "'pending', 'completed', 'missed', 'skipped', 'snoozed'";
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        let db = m.get_connection();
+
+        // スヌーズ中は `pending` のまま再通知時刻を持っていた
+        db.execute_unprepared(
+            "UPDATE medication_logs SET status = 'snoozed' \
+             WHERE status = 'pending' AND snoozed_until IS NOT NULL",
+        )
+        .await?;
+
+        // SQLite は既存テーブルへの制約の追加に対応していない
+        if m.get_database_backend() == DatabaseBackend::Postgres {
+            db.execute_unprepared(&format!(
+                "ALTER TABLE medication_logs ADD CONSTRAINT chk_medication_logs_status \
+                 CHECK (status IN ({STATUSES}))"
+            ))
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        let db = m.get_connection();
+        if m.get_database_backend() == DatabaseBackend::Postgres {
+            db.execute_unprepared(
+                "ALTER TABLE medication_logs DROP CONSTRAINT chk_medication_logs_status",
+            )
+            .await?;
+        }
+        db.execute_unprepared(
+            "UPDATE medication_logs SET status = 'pending' WHERE status = 'snoozed'",
+        )
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,53 @@
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        // 通知の送信履歴（送信を試みるたびに1行）
+        create_table(m, "notification_logs",
+            &[
+            
+            ("id", ColType::PkAuto),
+            
+            ("user_id", ColType::IntegerNull),
+            ("recipient", ColType::String),
+            ("notification_type", ColType::String),
+            ("payload_hash", ColType::String),
+            ("medicine_id", ColType::IntegerNull),
+            ("log_id", ColType::IntegerNull),
+            ("status", ColType::String),
+            ("line_request_id", ColType::StringNull),
+            ("error", ColType::TextNull),
+            ("sent_at", ColType::TimestampWithTimeZoneNull),
+            ],
+            &[
+            ]
+        ).await?;
+
+        m.create_index(
+            Index::create()
+                .name("idx_notification_logs_user_id_created_at")
+                .table(NotificationLogs::Table)
+                .col(NotificationLogs::UserId)
+                .col(NotificationLogs::CreatedAt)
+                .to_owned(),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        drop_table(m, "notification_logs").await
+    }
+}
+
+#[derive(DeriveIden)]
+enum NotificationLogs {
+    Table,
+    UserId,
+    CreatedAt,
+}
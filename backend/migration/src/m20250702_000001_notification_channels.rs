@@ -0,0 +1,46 @@
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        // 通知に使う経路（優先順にカンマ区切り。例: "line,email"）
+        add_column(
+            m,
+            "users",
+            "notification_channels",
+            ColType::StringWithDefault("line".to_string()),
+        )
+        .await?;
+        // 優先順に試す（fallback）か、すべてに送る（fan_out）か
+        add_column(
+            m,
+            "users",
+            "notification_strategy",
+            ColType::StringWithDefault("fallback".to_string()),
+        )
+        .await?;
+        // 通知をPOSTする外部のWebhook URL
+        add_column(m, "users", "webhook_url", ColType::StringNull).await?;
+        // どの経路で送ったか
+        add_column(
+            m,
+            "notification_logs",
+            "channel",
+            ColType::StringWithDefault("line".to_string()),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        remove_column(m, "notification_logs", "channel").await?;
+        remove_column(m, "users", "webhook_url").await?;
+        remove_column(m, "users", "notification_strategy").await?;
+        remove_column(m, "users", "notification_channels").await?;
+        Ok(())
+    }
+}
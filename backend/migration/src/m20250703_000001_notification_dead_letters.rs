@@ -0,0 +1,42 @@
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        // 再送しても届かなかった通知（CLIタスクで確認・再送する）
+        create_table(m, "notification_dead_letters",
+            &[
+            
+            ("id", ColType::PkAuto),
+            
+            ("user_id", ColType::IntegerNull),
+            ("notification_type", ColType::String),
+            ("args", ColType::JsonBinary),
+            ("error", ColType::Text),
+            ("attempts", ColType::Integer),
+            ("replayed_at", ColType::TimestampWithTimeZoneNull),
+            ],
+            &[
+            ]
+        ).await?;
+
+        // 何回目の送信か（再送のたびに増える）
+        add_column(
+            m,
+            "notification_logs",
+            "attempt",
+            ColType::IntegerWithDefault(1),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        remove_column(m, "notification_logs", "attempt").await?;
+        drop_table(m, "notification_dead_letters").await
+    }
+}
@@ -0,0 +1,25 @@
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        // 薬のアーカイブで一時停止したか（元に戻すときはこれだけを再開する）
+        add_column(
+            m,
+            "medication_schedules",
+            "paused_by_archive",
+            ColType::BooleanWithDefault(false),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        remove_column(m, "medication_schedules", "paused_by_archive").await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,72 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        let db = m.get_connection();
+
+        // 重なったティックで作られた重複ログを1件にまとめる
+        // （記録済みの行を優先し、次に古い行を残す）
+        db.execute_unprepared(
+            "DELETE FROM medication_logs WHERE id IN ( \
+               SELECT id FROM ( \
+                 SELECT id, ROW_NUMBER() OVER ( \
+                   PARTITION BY medicine_id, scheduled_time \
+                   ORDER BY CASE WHEN status IN ('pending', 'snoozed') THEN 1 ELSE 0 END, id \
+                 ) AS rn FROM medication_logs \
+               ) ranked WHERE rn > 1 \
+             )",
+        )
+        .await?;
+
+        m.drop_index(
+            Index::drop()
+                .name("idx_medication_logs_medicine_id_scheduled_time")
+                .table(MedicationLogs::Table)
+                .to_owned(),
+        )
+        .await?;
+        // 1回の服薬予定につきログは1件
+        m.create_index(
+            Index::create()
+                .name("idx_medication_logs_medicine_id_scheduled_time")
+                .table(MedicationLogs::Table)
+                .col(MedicationLogs::MedicineId)
+                .col(MedicationLogs::ScheduledTime)
+                .unique()
+                .to_owned(),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        m.drop_index(
+            Index::drop()
+                .name("idx_medication_logs_medicine_id_scheduled_time")
+                .table(MedicationLogs::Table)
+                .to_owned(),
+        )
+        .await?;
+        m.create_index(
+            Index::create()
+                .name("idx_medication_logs_medicine_id_scheduled_time")
+                .table(MedicationLogs::Table)
+                .col(MedicationLogs::MedicineId)
+                .col(MedicationLogs::ScheduledTime)
+                .to_owned(),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum MedicationLogs {
+    Table,
+    MedicineId,
+    ScheduledTime,
+}
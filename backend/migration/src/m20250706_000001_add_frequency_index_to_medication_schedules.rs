@@ -0,0 +1,39 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        // 間隔指定・RRULE のスケジュールを時刻と別に引くためのインデックス
+        m.create_index(
+            Index::create()
+                .name("idx_medication_schedules_frequency_active")
+                .table(MedicationSchedules::Table)
+                .col(MedicationSchedules::Frequency)
+                .col(MedicationSchedules::Active)
+                .to_owned(),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        m.drop_index(
+            Index::drop()
+                .name("idx_medication_schedules_frequency_active")
+                .table(MedicationSchedules::Table)
+                .to_owned(),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum MedicationSchedules {
+    Table,
+    Frequency,
+    Active,
+}
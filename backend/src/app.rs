@@ -1,6 +1,6 @@
 use async_trait::async_trait;
 use loco_rs::{
-    app::{AppContext, Hooks},
+    app::{AppContext, Hooks, Initializer},
     boot::{create_app, BootResult, StartMode},
     controller::AppRoutes,
     environment::Environment,
@@ -14,7 +14,11 @@ use std::path::Path;
 
 use crate::{
     controllers, 
-    tasks::medication_reminder::MedicationReminderTask,
+    initializers::reminder_scheduler::ReminderSchedulerInitializer,
+    tasks::{
+        medication_reminder::MedicationReminderTask,
+        notification_dead_letters::NotificationDeadLettersTask,
+    },
     workers::{
         downloader::DownloadWorker,
         notification_worker::NotificationWorker,
@@ -45,12 +49,24 @@ impl Hooks for App {
         create_app::<Self, Migrator>(mode, environment, config).await
     }
 
+    async fn initializers(_ctx: &AppContext) -> Result<Vec<Box<dyn Initializer>>> {
+        // サーバー内で服薬リマインダーを毎分実行
+        Ok(vec![Box::new(ReminderSchedulerInitializer)])
+    }
+
     fn routes(_ctx: &AppContext) -> AppRoutes {
         AppRoutes::with_default_routes()
 			.add_route(
 				controllers::auth::routes()
 			)
 			.add_route(controllers::medicine::routes())
+			.add_route(controllers::medication_schedule::routes())
+			.add_route(controllers::medication_log::routes())
+			.add_route(controllers::reports::routes())
+			.add_route(controllers::dashboard::routes())
+			.add_route(controllers::api_users::routes())
+			.add_route(controllers::notifications::routes())
+			.add_route(controllers::webhook_line::routes())
 
             // Add more as needed
     }
@@ -63,8 +79,10 @@ impl Hooks for App {
     }
 
     fn register_tasks(tasks: &mut Tasks) {
-        // 服薬リマインダータスク（毎時実行）
+        // 服薬リマインダータスク（サーバー内のスケジューラーと同じ処理を手動で実行）
         tasks.register(MedicationReminderTask);
+        // 届かなかった通知の確認と再送
+        tasks.register(NotificationDeadLettersTask);
         
         // 将来的に追加できるタスク例:
         // tasks.register(DailyReportTask);   // 日次レポート
@@ -104,7 +122,8 @@ impl Hooks for App {
     async fn after_context(ctx: AppContext) -> Result<AppContext> {
         // アプリケーション起動後の初期化処理
         tracing::info!("🚀 Medication Reminder System initialized");
-        tracing::info!("📋 Registered tasks: medication_reminder");
+        tracing::info!("📋 Registered tasks: medication_reminder, notification_dead_letters");
+        tracing::info!("⏰ Registered initializers: reminder-scheduler");
         tracing::info!("👷 Registered workers: notification_worker, report_generator");
         
         Ok(ctx)
@@ -115,38 +134,36 @@ impl Hooks for App {
 impl App {
     /// 即座に通知を送信
     pub async fn send_immediate_notification(
-        _ctx: &AppContext,
+        ctx: &AppContext,
         line_user_id: String,
         message: String,
         notification_type: String,
     ) -> Result<()> {
-        // Fixed: Use the correct queue enqueue pattern for Loco 0.15
         use crate::workers::notification_worker::NotificationWorkerArgs;
-        
-        let _args = NotificationWorkerArgs {
-            line_user_id,
+
+        let args = NotificationWorkerArgs {
+            user_id: None,
+            line_user_id: Some(line_user_id),
             message,
             notification_type,
             medicine_id: None,
             log_id: None,
+            report: None,
+            dead_letter_id: None,
         };
 
-        // TODO: Fix queue access pattern for Loco 0.15
-        // The queue access needs to be implemented properly
-        // queue.enqueue(NotificationWorker::build(ctx), &args, None).await?;
-        Ok(())
+        NotificationWorker::perform_later(ctx, args).await
     }
 
     /// レポート生成を開始
     pub async fn generate_report(
-        _ctx: &AppContext,
+        ctx: &AppContext,
         user_id: i32,
         report_type: String,
     ) -> Result<()> {
-        // Fixed: Use the correct queue enqueue pattern for Loco 0.15
         use crate::workers::report_generator::ReportGeneratorArgs;
 
-        let _args = ReportGeneratorArgs {
+        let args = ReportGeneratorArgs {
             user_id,
             report_type,
             start_date: None,
@@ -154,9 +171,6 @@ impl App {
             send_notification: true,
         };
 
-        // TODO: Fix queue access pattern for Loco 0.15
-        // The queue access needs to be implemented properly
-        // queue.enqueue(ReportGeneratorWorker::build(ctx), &args, None).await?;
-        Ok(())
+        ReportGeneratorWorker::perform_later(ctx, args).await
     }
-}
\ No newline at end of file
+}
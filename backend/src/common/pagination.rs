@@ -0,0 +1,45 @@
+//! 一覧APIのページ分割
+
+use loco_rs::{
+    controller::views::pagination::{Pager, PagerMeta},
+    prelude::*,
+};
+
+/// 1ページに返す最大件数
+pub const MAX_PAGE_SIZE: u64 = 100;
+
+/// クエリパラメータの `page` / `page_size` を検証してページ指定にする
+///
+/// # Errors
+///
+/// `page_size` が1〜[`MAX_PAGE_SIZE`]の範囲外の場合は `400 Bad Request`
+#[allow(clippy::result_large_err)]
+pub fn pagination_query(page: Option<u64>, page_size: Option<u64>) -> Result<query::PaginationQuery> {
+    let mut pagination = query::PaginationQuery::default();
+    if let Some(page_size) = page_size {
+        if !(1..=MAX_PAGE_SIZE).contains(&page_size) {
+            return Err(Error::BadRequest(format!(
+                "page_size must be between 1 and {MAX_PAGE_SIZE}"
+            )));
+        }
+        pagination.page_size = page_size;
+    }
+    if let Some(page) = page {
+        pagination.page = page.max(1);
+    }
+    Ok(pagination)
+}
+
+/// 取得したページをレスポンスの形にする
+#[must_use]
+pub fn pager<T>(page: query::PageResponse<T>, pagination: &query::PaginationQuery) -> Pager<Vec<T>> {
+    Pager::new(
+        page.page,
+        PagerMeta {
+            page: pagination.page,
+            page_size: pagination.page_size,
+            total_pages: page.total_pages,
+            total_items: page.total_items,
+        },
+    )
+}
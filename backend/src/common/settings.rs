@@ -0,0 +1,221 @@
+use std::time::Duration;
+
+use loco_rs::config::Config;
+use serde::{Deserialize, Serialize};
+
+/// `config/*.yaml` の `settings:` に書くアプリケーション固有の設定
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Settings {
+    #[serde(default)]
+    pub reminder_scheduler: ReminderSchedulerSettings,
+    #[serde(default)]
+    pub refill: RefillSettings,
+    #[serde(default)]
+    pub notification_retry: NotificationRetrySettings,
+    #[serde(default)]
+    pub line: LineSettings,
+    #[serde(default)]
+    pub webhook: WebhookSettings,
+}
+
+/// サーバー内で動く服薬リマインダースケジューラーの設定
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReminderSchedulerSettings {
+    /// サーバー起動時にスケジューラーを動かすか
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// 実行間隔（秒）
+    #[serde(default = "default_tick_seconds")]
+    pub tick_seconds: u64,
+}
+
+impl Default for ReminderSchedulerSettings {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            tick_seconds: default_tick_seconds(),
+        }
+    }
+}
+
+/// 薬の補充通知の設定
+#[derive(Debug, Clone, Deserialize)]
+pub struct RefillSettings {
+    /// 在庫の残り日数がこれを下回ったら補充を通知する
+    #[serde(default = "default_alert_days")]
+    pub alert_days: i64,
+}
+
+impl Default for RefillSettings {
+    fn default() -> Self {
+        Self {
+            alert_days: default_alert_days(),
+        }
+    }
+}
+
+/// Webhookでの通知の設定
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WebhookSettings {
+    /// ループバック・プライベート・リンクローカルなど内部のアドレスへの送信を許可する（開発・テスト用）
+    #[serde(default)]
+    pub allow_private_hosts: bool,
+}
+
+/// LINE Messaging API の設定
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LineSettings {
+    /// APIのベースURL（テストではモックサーバーに向ける）
+    #[serde(default = "default_line_api_base_url")]
+    pub api_base_url: String,
+    /// Webhookの署名検証に使うチャネルシークレット
+    #[serde(default)]
+    pub channel_secret: Option<String>,
+    /// チャネルアクセストークン（[`LineSettings::PLACEHOLDER_TOKEN`] のままなら送信しない）
+    #[serde(default)]
+    pub channel_access_token: Option<String>,
+    /// 応答を待つ最大時間（ミリ秒）
+    #[serde(default = "default_line_timeout_ms")]
+    pub timeout_ms: u64,
+    /// 接続を待つ最大時間（ミリ秒）
+    #[serde(default = "default_line_connect_timeout_ms")]
+    pub connect_timeout_ms: u64,
+}
+
+impl Default for LineSettings {
+    fn default() -> Self {
+        Self {
+            api_base_url: default_line_api_base_url(),
+            channel_secret: None,
+            channel_access_token: None,
+            timeout_ms: default_line_timeout_ms(),
+            connect_timeout_ms: default_line_connect_timeout_ms(),
+        }
+    }
+}
+
+impl LineSettings {
+    /// 未設定を表すアクセストークンの仮の値
+    pub const PLACEHOLDER_TOKEN: &'static str = "YOUR_LINE_CHANNEL_ACCESS_TOKEN";
+
+    /// チャネルシークレット（空文字は未設定として扱う）
+    #[must_use]
+    pub fn channel_secret(&self) -> Option<&str> {
+        self.channel_secret.as_deref().filter(|secret| !secret.is_empty())
+    }
+
+    /// 送信に使うアクセストークン（仮の値の場合は `Ok(None)`）
+    ///
+    /// # Errors
+    ///
+    /// アクセストークンが設定されていない場合
+    pub fn access_token(&self) -> Result<Option<&str>, &'static str> {
+        match self.channel_access_token.as_deref() {
+            None | Some("") => Err("LINE channel access token is not configured (settings.line.channel_access_token)"),
+            Some(Self::PLACEHOLDER_TOKEN) => Ok(None),
+            Some(token) => Ok(Some(token)),
+        }
+    }
+
+    /// APIのURL（`path` は `/v2/bot/...`）
+    #[must_use]
+    pub fn endpoint(&self, path: &str) -> String {
+        format!("{}{}", self.api_base_url.trim_end_matches('/'), path)
+    }
+
+    /// タイムアウトを設定したHTTPクライアント
+    ///
+    /// # Errors
+    ///
+    /// クライアントを作成できない場合
+    pub fn http_client(&self) -> reqwest::Result<reqwest::Client> {
+        reqwest::Client::builder()
+            .timeout(Duration::from_millis(self.timeout_ms))
+            .connect_timeout(Duration::from_millis(self.connect_timeout_ms))
+            .build()
+    }
+}
+
+fn default_line_api_base_url() -> String {
+    "https://api.line.me".to_string()
+}
+
+const fn default_line_timeout_ms() -> u64 {
+    10_000
+}
+
+const fn default_line_connect_timeout_ms() -> u64 {
+    5_000
+}
+
+/// 通知の再送の設定
+#[derive(Debug, Clone, Deserialize)]
+pub struct NotificationRetrySettings {
+    /// 1つの経路に送る最大回数（初回を含む）
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    /// 初回の再送までの待ち時間（ミリ秒）。以降は倍々に増やす
+    #[serde(default = "default_base_delay_ms")]
+    pub base_delay_ms: u64,
+    /// 待ち時間の上限（ミリ秒）。`Retry-After` がこれを超える場合は再送しない
+    #[serde(default = "default_max_delay_ms")]
+    pub max_delay_ms: u64,
+    /// 1つの経路で待つ時間の合計の上限（ミリ秒）。ワーカーを長く止めないように数秒にする
+    #[serde(default = "default_max_total_delay_ms")]
+    pub max_total_delay_ms: u64,
+}
+
+impl Default for NotificationRetrySettings {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_max_attempts(),
+            base_delay_ms: default_base_delay_ms(),
+            max_delay_ms: default_max_delay_ms(),
+            max_total_delay_ms: default_max_total_delay_ms(),
+        }
+    }
+}
+
+const fn default_max_attempts() -> u32 {
+    5
+}
+
+const fn default_base_delay_ms() -> u64 {
+    1_000
+}
+
+const fn default_max_delay_ms() -> u64 {
+    5_000
+}
+
+const fn default_max_total_delay_ms() -> u64 {
+    5_000
+}
+
+const fn default_alert_days() -> i64 {
+    7
+}
+
+const fn default_enabled() -> bool {
+    true
+}
+
+const fn default_tick_seconds() -> u64 {
+    60
+}
+
+impl Settings {
+    /// 設定ファイルの `settings:` を読み込む（未設定の項目は既定値）
+    ///
+    /// # Errors
+    ///
+    /// `settings:` の形式が正しくない場合
+    pub fn from_config(config: &Config) -> serde_json::Result<Self> {
+        Ok(config
+            .settings
+            .clone()
+            .map(serde_json::from_value)
+            .transpose()?
+            .unwrap_or_default())
+    }
+}
@@ -1,16 +1,112 @@
 #![allow(clippy::missing_errors_doc)]
 #![allow(clippy::unnecessary_struct_initialization)]
 #![allow(clippy::unused_async)]
+#![allow(clippy::result_large_err)]
 use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
 use axum::debug_handler;
 
+use crate::{
+    common::settings::Settings,
+    models::users,
+    notifications::{
+        self, format_channels, NotificationChannelKind, NotificationStrategy, WebhookUrlError,
+    },
+    views::users::{NotificationSettingsResponse, UserResponse},
+};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NotificationSettingsParams {
+    /// 使う経路を優先順に並べたもの
+    pub channels: Vec<NotificationChannelKind>,
+    #[serde(default = "default_strategy")]
+    pub strategy: NotificationStrategy,
+    pub webhook_url: Option<String>,
+}
+
+const fn default_strategy() -> NotificationStrategy {
+    NotificationStrategy::Fallback
+}
+
+impl NotificationSettingsParams {
+    fn validate(&self) -> Result<()> {
+        if self.channels.is_empty() {
+            return Err(Error::BadRequest("channels must not be empty".to_string()));
+        }
+        for (i, channel) in self.channels.iter().enumerate() {
+            if self.channels[..i].contains(channel) {
+                return Err(Error::BadRequest(format!(
+                    "channel `{}` is listed more than once",
+                    channel.label()
+                )));
+            }
+        }
+        if self.channels.contains(&NotificationChannelKind::Webhook) && self.webhook_url.is_none() {
+            return Err(Error::BadRequest(
+                "webhook_url is required to use the webhook channel".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// WebhookのURLが内部のアドレスを指していないか確かめる
+    ///
+    /// 名前解決できないだけの場合は保存を許し、送信するときに改めて検証する。
+    async fn validate_webhook_url(&self, ctx: &AppContext) -> Result<()> {
+        let Some(url) = &self.webhook_url else {
+            return Ok(());
+        };
+        let allow_private_hosts = Settings::from_config(&ctx.config)?.webhook.allow_private_hosts;
+        match notifications::resolve_url(url, allow_private_hosts).await {
+            Ok(_) | Err(WebhookUrlError::Unresolvable(_)) => Ok(()),
+            Err(e) => Err(Error::BadRequest(e.to_string())),
+        }
+    }
+}
+
+/// ログイン中のユーザーのプロフィールを返す
 #[debug_handler]
-pub async fn index(State(_ctx): State<AppContext>) -> Result<Response> {
-    format::empty()
+pub async fn index(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    format::json(UserResponse::new(&user))
+}
+
+/// 通知の送信経路の設定を返す
+#[debug_handler]
+pub async fn notification_settings(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    format::json(NotificationSettingsResponse::new(&user))
+}
+
+/// 通知の送信経路とその優先順を変更する
+#[debug_handler]
+pub async fn update_notification_settings(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Json(params): Json<NotificationSettingsParams>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    params.validate()?;
+    params.validate_webhook_url(&ctx).await?;
+    if params.channels.contains(&NotificationChannelKind::Line) && user.line_user_id.is_none() {
+        return Err(Error::BadRequest("LINE is not linked to this account".to_string()));
+    }
+
+    let mut user = user.into_active_model();
+    user.notification_channels = Set(format_channels(&params.channels));
+    user.notification_strategy = Set(params.strategy);
+    user.webhook_url = Set(params.webhook_url);
+    let user = user.update(&ctx.db).await?;
+    format::json(NotificationSettingsResponse::new(&user))
 }
 
 pub fn routes() -> Routes {
     Routes::new()
-        .prefix("api/api_users/")
+        .prefix("api/users/")
         .add("/", get(index))
+        .add("notifications", get(notification_settings))
+        .add("notifications", put(update_notification_settings))
 }
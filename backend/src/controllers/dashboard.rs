@@ -1,9 +1,199 @@
 #![allow(clippy::missing_errors_doc)]
 #![allow(clippy::unnecessary_struct_initialization)]
 #![allow(clippy::unused_async)]
+use std::collections::{BTreeMap, HashMap};
+
 use loco_rs::prelude::*;
+use axum::debug_handler;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use chrono_tz::Tz;
+use sea_orm::{DatabaseConnection, QueryOrder, QuerySelect};
+
+use crate::{
+    models::{
+        _entities::{medication_logs, medication_schedules, medicines},
+        medication_logs::MedicationStatus,
+        users,
+    },
+    tasks::medication_reminder::MedicationReminderTask,
+    views::dashboard::{
+        adherence_rate, DailyStats, DashboardResponse, RecentLog, TodaySchedule,
+        UpcomingSchedule, WeeklyStats,
+    },
+};
+
+/// 服薬率を集計する日数（今日を含む）
+const WEEKLY_DAYS: i64 = 7;
+/// 「これからの予定」を探す期間（日）
+const UPCOMING_DAYS: i64 = 7;
+/// 「これからの予定」の最大件数
+const UPCOMING_LIMIT: usize = 5;
+/// 最近の服薬記録の件数
+const RECENT_LOGS_LIMIT: u64 = 10;
+
+/// 現地の日付の0時（UTC）
+fn start_of_day(tz: Tz, date: NaiveDate) -> DateTime<Utc> {
+    MedicationReminderTask::resolve_local_time(tz, date.and_time(chrono::NaiveTime::MIN))
+        .with_timezone(&Utc)
+}
+
+/// ユーザーのダッシュボードを `now` 時点で集計する
+///
+/// 日付の区切りや表示する時刻はすべてユーザーのタイムゾーンで扱う。
+pub async fn dashboard_for(
+    db: &DatabaseConnection,
+    user: &users::Model,
+    now: DateTime<Utc>,
+) -> Result<DashboardResponse> {
+    let tz = user.tz();
+    let today = now.with_timezone(&tz).date_naive();
+    let today_start = start_of_day(tz, today);
+    let tomorrow_start = start_of_day(tz, today + Duration::days(1));
+
+    let medicines: HashMap<i32, medicines::Model> = medicines::Entity::find_for_user(user.id)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|medicine| (medicine.id, medicine))
+        .collect();
+    // アーカイブや停止をしていない、定時に飲む薬のスケジュール
+    let scheduled_ids: Vec<i32> = medicines
+        .values()
+        .filter(|medicine| !medicine.as_needed && !medicine.is_archived())
+        .filter(|medicine| medicine.active != Some(false))
+        .map(|medicine| medicine.id)
+        .collect();
+    let schedules = medication_schedules::Entity::find()
+        .filter(medication_schedules::Column::MedicineId.is_in(scheduled_ids))
+        .filter(medication_schedules::Column::Active.eq(true))
+        .all(db)
+        .await?;
+
+    // 今日の予定と、その記録
+    let today_logs: HashMap<(i32, i64), medication_logs::Model> = medication_logs::Entity::find()
+        .filter(medication_logs::Column::MedicineId.is_in(medicines.keys().copied()))
+        .filter(medication_logs::Column::ScheduledTime.gte(today_start))
+        .filter(medication_logs::Column::ScheduledTime.lt(tomorrow_start))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|log| ((log.medicine_id, log.scheduled_time.timestamp()), log))
+        .collect();
+    let mut today_schedules = Vec::new();
+    for schedule in &schedules {
+        let medicine = &medicines[&schedule.medicine_id];
+        let occurrences =
+            MedicationReminderTask::occurrences_between(schedule, tz, today_start, tomorrow_start);
+        for time in occurrences.into_iter().filter(|time| *time < tomorrow_start) {
+            let log = today_logs.get(&(medicine.id, time.timestamp()));
+            today_schedules.push(TodaySchedule {
+                id: schedule.id,
+                medicine_id: medicine.id,
+                medicine_name: medicine.name.clone(),
+                dosage: medicine.dosage.clone(),
+                scheduled_time: time.fixed_offset(),
+                status: log.map_or(MedicationStatus::Pending, |log| log.status),
+                log_id: log.map(|log| log.id),
+            });
+        }
+    }
+    today_schedules.sort_by_key(|schedule| (schedule.scheduled_time, schedule.medicine_id));
+
+    // これからの予定
+    let mut upcoming_schedules = Vec::new();
+    for schedule in &schedules {
+        let medicine = &medicines[&schedule.medicine_id];
+        let occurrences = MedicationReminderTask::occurrences_between(
+            schedule,
+            tz,
+            now,
+            now + Duration::days(UPCOMING_DAYS),
+        );
+        for time in occurrences.into_iter().filter(|time| *time > now) {
+            upcoming_schedules.push(UpcomingSchedule {
+                id: schedule.id,
+                medicine_id: medicine.id,
+                medicine_name: medicine.name.clone(),
+                dosage: medicine.dosage.clone(),
+                scheduled_time: time.fixed_offset(),
+                days_until: (time.date_naive() - today).num_days(),
+            });
+        }
+    }
+    upcoming_schedules.sort_by_key(|schedule| (schedule.scheduled_time, schedule.medicine_id));
+    upcoming_schedules.truncate(UPCOMING_LIMIT);
+
+    // 直近7日間の服薬率（頓服薬は予定がないので除く）
+    let first_day = today - Duration::days(WEEKLY_DAYS - 1);
+    let mut days: BTreeMap<NaiveDate, (i32, i32)> = (0..WEEKLY_DAYS)
+        .map(|offset| (first_day + Duration::days(offset), (0, 0)))
+        .collect();
+    let weekly_logs = medication_logs::Entity::find()
+        .filter(
+            medication_logs::Column::MedicineId.is_in(
+                medicines
+                    .values()
+                    .filter(|medicine| !medicine.as_needed)
+                    .map(|medicine| medicine.id),
+            ),
+        )
+        .filter(medication_logs::Column::ScheduledTime.gte(start_of_day(tz, first_day)))
+        .filter(medication_logs::Column::ScheduledTime.lt(tomorrow_start))
+        .all(db)
+        .await?;
+    for log in weekly_logs {
+        let date = log.scheduled_time.with_timezone(&tz).date_naive();
+        if let Some((scheduled, completed)) = days.get_mut(&date) {
+            *scheduled += 1;
+            if log.status == MedicationStatus::Completed {
+                *completed += 1;
+            }
+        }
+    }
+    let mut weekly_stats = WeeklyStats::default();
+    for (date, (scheduled, completed)) in days {
+        weekly_stats.total_scheduled += scheduled;
+        weekly_stats.total_completed += completed;
+        weekly_stats.daily_stats.push(DailyStats {
+            date,
+            scheduled,
+            completed,
+            adherence_rate: adherence_rate(scheduled, completed),
+        });
+    }
+    weekly_stats.adherence_rate =
+        adherence_rate(weekly_stats.total_scheduled, weekly_stats.total_completed);
+
+    let recent_logs = medication_logs::Entity::find_for_user(user.id)
+        .filter(medication_logs::Column::ScheduledTime.lte(now))
+        .order_by_desc(medication_logs::Column::ScheduledTime)
+        .order_by_desc(medication_logs::Column::Id)
+        .limit(RECENT_LOGS_LIMIT)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|log| {
+            let medicine = medicines.get(&log.medicine_id);
+            RecentLog::new(log, medicine)
+        })
+        .collect();
+
+    Ok(DashboardResponse {
+        today_schedules,
+        upcoming_schedules,
+        weekly_stats,
+        recent_logs,
+    })
+}
+
+#[debug_handler]
+pub async fn index(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    format::json(dashboard_for(&ctx.db, &user, Utc::now()).await?)
+}
 
 pub fn routes() -> Routes {
     Routes::new()
-        .prefix("dashboards/")
+        .prefix("api/dashboard/")
+        .add("/", get(index))
 }
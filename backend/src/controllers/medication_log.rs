@@ -1,73 +1,223 @@
 #![allow(clippy::missing_errors_doc)]
 #![allow(clippy::unnecessary_struct_initialization)]
 #![allow(clippy::unused_async)]
+#![allow(clippy::result_large_err)]
 use loco_rs::prelude::*;
 use serde::{Deserialize, Serialize};
-use axum::debug_handler;
+use axum::{debug_handler, extract::Query};
+use sea_orm::{prelude::DateTimeWithTimeZone, sea_query::Order, Condition, QueryOrder, SqlErr};
 
-use crate::models::_entities::medication_logs::{ActiveModel, Entity, Model};
+use crate::{
+    common::pagination,
+    models::{
+        _entities::{
+            medication_logs::{self, ActiveModel, Entity, Model},
+            medicines,
+        },
+        medication_logs::MedicationStatus,
+        users,
+    },
+};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Params {
     pub medicine_id: i32,
     pub scheduled_time: DateTimeWithTimeZone,
     pub taken_time: Option<DateTimeWithTimeZone>,
+    /// `pending` | `completed` | `missed` | `skipped` | `snoozed`
     pub status: String,
     pub notes: Option<String>,
+    /// 記録が確定した（`completed` / `skipped`）ログの状態も変更する
+    #[serde(default)]
+    pub force: bool,
     }
 
 impl Params {
-    fn update(&self, item: &mut ActiveModel) {
+    fn status(&self) -> Result<MedicationStatus> {
+      self.status.parse().map_err(Error::BadRequest)
+      }
+
+    fn update(&self, item: &mut ActiveModel) -> Result<()> {
+      let status = self.status()?;
+      if let Some(current) = item.status.try_as_ref() {
+          if !self.force {
+              current.validate_transition(status).map_err(Error::BadRequest)?;
+          }
+      }
       item.medicine_id = Set(self.medicine_id.clone());
       item.scheduled_time = Set(self.scheduled_time.clone());
       item.taken_time = Set(self.taken_time.clone());
-      item.status = Set(self.status.clone());
+      item.status = Set(status);
       item.notes = Set(self.notes.clone());
+      Ok(())
       }
 }
 
-async fn load_item(ctx: &AppContext, id: i32) -> Result<Model> {
-    let item = Entity::find_by_id(id).one(&ctx.db).await?;
+/// 並び順（予定時刻）
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// 服薬記録の一覧の絞り込み
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct ListQuery {
+    pub medicine_id: Option<i32>,
+    /// カンマ区切りの状態（例: `missed,skipped`）
+    pub status: Option<String>,
+    /// 予定時刻の範囲（`date_from` 以上 `date_to` 未満）
+    pub date_from: Option<DateTimeWithTimeZone>,
+    pub date_to: Option<DateTimeWithTimeZone>,
+    #[serde(default)]
+    pub sort: SortOrder,
+    pub page: Option<u64>,
+    pub page_size: Option<u64>,
+}
+
+impl ListQuery {
+    fn statuses(&self) -> Result<Vec<MedicationStatus>> {
+        let Some(status) = self.status.as_deref() else {
+            return Ok(Vec::new());
+        };
+        status
+            .split(',')
+            .map(str::trim)
+            .filter(|status| !status.is_empty())
+            .map(|status| status.parse().map_err(Error::BadRequest))
+            .collect()
+    }
+
+    fn pagination(&self) -> Result<query::PaginationQuery> {
+        pagination::pagination_query(self.page, self.page_size)
+    }
+
+    fn condition(&self) -> Result<Condition> {
+        let mut condition = Condition::all();
+        if let Some(medicine_id) = self.medicine_id {
+            condition = condition.add(medication_logs::Column::MedicineId.eq(medicine_id));
+        }
+        let statuses = self.statuses()?;
+        if !statuses.is_empty() {
+            condition = condition.add(medication_logs::Column::Status.is_in(statuses));
+        }
+        if let (Some(from), Some(to)) = (self.date_from, self.date_to) {
+            if to < from {
+                return Err(Error::BadRequest("date_to must not be before date_from".to_string()));
+            }
+        }
+        if let Some(from) = self.date_from {
+            condition = condition.add(medication_logs::Column::ScheduledTime.gte(from));
+        }
+        if let Some(to) = self.date_to {
+            condition = condition.add(medication_logs::Column::ScheduledTime.lt(to));
+        }
+        Ok(condition)
+    }
+}
+
+/// ログイン中のユーザーの服薬記録を取得する（他のユーザーのものは見つからない扱い）
+async fn load_item(ctx: &AppContext, user: &users::Model, id: i32) -> Result<Model> {
+    let item = Entity::find_for_user(user.id)
+        .filter(medication_logs::Column::Id.eq(id))
+        .one(&ctx.db)
+        .await?;
     item.ok_or_else(|| Error::NotFound)
 }
 
+/// `medicine_id` がログイン中のユーザーの薬であることを確認する
+async fn ensure_own_medicine(ctx: &AppContext, user: &users::Model, medicine_id: i32) -> Result<()> {
+    medicines::Entity::find_for_user(user.id)
+        .filter(medicines::Column::Id.eq(medicine_id))
+        .one(&ctx.db)
+        .await?
+        .ok_or_else(|| Error::NotFound)?;
+    Ok(())
+}
+
+/// 服薬記録を絞り込み、ページ単位で返す
 #[debug_handler]
-pub async fn list(State(ctx): State<AppContext>) -> Result<Response> {
-    format::json(Entity::find().all(&ctx.db).await?)
+pub async fn list(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Query(params): Query<ListQuery>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let pagination = params.pagination()?;
+    let order = match params.sort {
+        SortOrder::Asc => Order::Asc,
+        SortOrder::Desc => Order::Desc,
+    };
+    let logs = Entity::find_for_user(user.id)
+        .order_by(medication_logs::Column::ScheduledTime, order.clone())
+        .order_by(medication_logs::Column::Id, order);
+    let page = query::paginate(&ctx.db, logs, Some(params.condition()?), &pagination).await?;
+    format::json(pagination::pager(page, &pagination))
+}
+
+/// 同じ薬・同じ予定時刻のログが既にある場合は 400 にする
+fn duplicate_occurrence(err: DbErr) -> Error {
+    match err.sql_err() {
+        Some(SqlErr::UniqueConstraintViolation(_)) => Error::BadRequest(
+            "a log for this medicine at this scheduled_time already exists".to_string(),
+        ),
+        _ => err.into(),
+    }
 }
 
 #[debug_handler]
-pub async fn add(State(ctx): State<AppContext>, Json(params): Json<Params>) -> Result<Response> {
+pub async fn add(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Json(params): Json<Params>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    ensure_own_medicine(&ctx, &user, params.medicine_id).await?;
     let mut item = ActiveModel {
         ..Default::default()
     };
-    params.update(&mut item);
-    let item = item.insert(&ctx.db).await?;
+    params.update(&mut item)?;
+    let item = item.insert(&ctx.db).await.map_err(duplicate_occurrence)?;
     format::json(item)
 }
 
 #[debug_handler]
 pub async fn update(
+    auth: auth::JWT,
     Path(id): Path<i32>,
     State(ctx): State<AppContext>,
     Json(params): Json<Params>,
 ) -> Result<Response> {
-    let item = load_item(&ctx, id).await?;
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let item = load_item(&ctx, &user, id).await?;
+    ensure_own_medicine(&ctx, &user, params.medicine_id).await?;
     let mut item = item.into_active_model();
-    params.update(&mut item);
-    let item = item.update(&ctx.db).await?;
+    params.update(&mut item)?;
+    let item = item.update(&ctx.db).await.map_err(duplicate_occurrence)?;
     format::json(item)
 }
 
 #[debug_handler]
-pub async fn remove(Path(id): Path<i32>, State(ctx): State<AppContext>) -> Result<Response> {
-    load_item(&ctx, id).await?.delete(&ctx.db).await?;
+pub async fn remove(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    load_item(&ctx, &user, id).await?.delete(&ctx.db).await?;
     format::empty()
 }
 
 #[debug_handler]
-pub async fn get_one(Path(id): Path<i32>, State(ctx): State<AppContext>) -> Result<Response> {
-    format::json(load_item(&ctx, id).await?)
+pub async fn get_one(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    format::json(load_item(&ctx, &user, id).await?)
 }
 
 pub fn routes() -> Routes {
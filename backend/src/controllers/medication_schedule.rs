@@ -1,73 +1,252 @@
 #![allow(clippy::missing_errors_doc)]
 #![allow(clippy::unnecessary_struct_initialization)]
 #![allow(clippy::unused_async)]
+#![allow(clippy::result_large_err)]
 use loco_rs::prelude::*;
 use serde::{Deserialize, Serialize};
-use axum::debug_handler;
+use axum::{debug_handler, extract::Query};
+use chrono::{DateTime, Duration, FixedOffset, Timelike, Utc};
+use sea_orm::{prelude::{Date, Time}, TryIntoModel};
 
-use crate::models::_entities::medication_schedules::{ActiveModel, Entity, Model};
+use crate::{
+    models::{
+        _entities::{
+            medication_schedules::{self, ActiveModel, Entity, Model},
+            medicines,
+        },
+        medication_schedules::{validate_course, validate_recurrence, weekdays_mask, ALL_WEEKDAYS},
+        users,
+    },
+    tasks::medication_reminder::MedicationReminderTask,
+};
+
+/// 服薬時刻のプレビューで一度に指定できる最長の期間（日）
+const MAX_PREVIEW_DAYS: i64 = 366;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Params {
     pub medicine_id: i32,
-    pub scheduled_time: DateTime,
+    pub time_of_day: Time,
     pub frequency: String,
     pub active: Option<bool>,
-    pub days_of_week: Option<String>,
+    /// ISOの曜日番号（月曜=1〜日曜=7）。省略時は毎日
+    pub days_of_week: Option<Vec<u8>>,
+    /// `frequency` が `custom` のときの間隔（N 時間/日/週ごと）
+    pub interval_count: Option<i32>,
+    /// `hours` | `days` | `weeks`
+    pub interval_unit: Option<String>,
+    /// `frequency` が `rrule` のときの繰り返し規則（例: `FREQ=MONTHLY;BYDAY=1MO`）
+    pub rrule: Option<String>,
+    /// 服薬コースの開始日（`custom` では間隔を数える起点）
+    pub start_date: Option<Date>,
+    /// 服薬コースの終了日（当日を含む）
+    pub end_date: Option<Date>,
+    /// 服薬コースの総服薬回数
+    pub max_doses: Option<i32>,
     }
 
 impl Params {
-    fn update(&self, item: &mut ActiveModel) {
-      item.medicine_id = Set(self.medicine_id.clone());
-      item.scheduled_time = Set(self.scheduled_time.clone());
+    fn update(&self, item: &mut ActiveModel) -> Result<()> {
+      let weekdays = match &self.days_of_week {
+          Some(days) => weekdays_mask(days)
+              .ok_or_else(|| Error::BadRequest("days_of_week must be between 1 and 7".to_string()))?,
+          None => ALL_WEEKDAYS,
+      };
+      validate_recurrence(
+          &self.frequency,
+          self.interval_count,
+          self.interval_unit.as_deref(),
+          self.rrule.as_deref(),
+          self.start_date,
+      )
+      .map_err(Error::BadRequest)?;
+      validate_course(self.start_date, self.end_date, self.max_doses).map_err(Error::BadRequest)?;
+      item.medicine_id = Set(self.medicine_id);
+      // リマインダーは分単位で照合するので、秒以下は切り捨てる
+      item.time_of_day = Set(
+          self.time_of_day
+              .with_second(0)
+              .and_then(|time| time.with_nanosecond(0))
+              .unwrap_or(self.time_of_day),
+      );
       item.frequency = Set(self.frequency.clone());
-      item.active = Set(self.active.clone());
-      item.days_of_week = Set(self.days_of_week.clone());
+      item.active = Set(self.active);
+      item.weekdays = Set(weekdays);
+      item.interval_count = Set(self.interval_count);
+      item.interval_unit = Set(self.interval_unit.clone());
+      item.rrule = Set(self.rrule.clone());
+      item.start_date = Set(self.start_date);
+      item.end_date = Set(self.end_date);
+      item.max_doses = Set(self.max_doses);
+      Ok(())
       }
 }
 
-async fn load_item(ctx: &AppContext, id: i32) -> Result<Model> {
-    let item = Entity::find_by_id(id).one(&ctx.db).await?;
+/// 服薬時刻のプレビューの期間（RFC 3339）。省略時は現在から7日間
+#[derive(Debug, Deserialize)]
+pub struct OccurrencesQuery {
+    pub from: Option<DateTime<FixedOffset>>,
+    pub to: Option<DateTime<FixedOffset>>,
+}
+
+impl OccurrencesQuery {
+    fn range(&self) -> Result<(DateTime<Utc>, DateTime<Utc>)> {
+        let from = self.from.map_or_else(Utc::now, |from| from.with_timezone(&Utc));
+        let to = self.to.map_or_else(|| from + Duration::days(7), |to| to.with_timezone(&Utc));
+        if to < from {
+            return Err(Error::BadRequest("to must not be before from".to_string()));
+        }
+        if to - from > Duration::days(MAX_PREVIEW_DAYS) {
+            return Err(Error::BadRequest(format!(
+                "range must not be longer than {MAX_PREVIEW_DAYS} days"
+            )));
+        }
+        Ok((from, to))
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct OccurrencesResponse {
+    /// 服薬時刻を表すタイムゾーン（ユーザーのタイムゾーン）
+    pub timezone: String,
+    pub occurrences: Vec<DateTime<FixedOffset>>,
+}
+
+/// ログイン中のユーザーのスケジュールを取得する（他のユーザーのものは見つからない扱い）
+async fn load_item(ctx: &AppContext, user: &users::Model, id: i32) -> Result<Model> {
+    let item = Entity::find_for_user(user.id)
+        .filter(medication_schedules::Column::Id.eq(id))
+        .one(&ctx.db)
+        .await?;
     item.ok_or_else(|| Error::NotFound)
 }
 
+/// `medicine_id` がログイン中のユーザーの、アーカイブしていない薬であることを確認する
+async fn ensure_own_medicine(ctx: &AppContext, user: &users::Model, medicine_id: i32) -> Result<()> {
+    let medicine = medicines::Entity::find_for_user(user.id)
+        .filter(medicines::Column::Id.eq(medicine_id))
+        .one(&ctx.db)
+        .await?
+        .ok_or_else(|| Error::NotFound)?;
+    if medicine.is_archived() {
+        return Err(Error::BadRequest(
+            "medicine is archived; restore it before changing its schedules".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// スケジュールの服薬時刻を、ユーザーのタイムゾーンで展開する
+fn occurrences_response(
+    user: &users::Model,
+    schedule: &Model,
+    query: &OccurrencesQuery,
+) -> Result<Response> {
+    let (from, to) = query.range()?;
+    let tz = user.tz();
+
+    format::json(OccurrencesResponse {
+        timezone: tz.name().to_string(),
+        occurrences: MedicationReminderTask::occurrences_between(schedule, tz, from, to)
+            .into_iter()
+            .map(|time| time.fixed_offset())
+            .collect(),
+    })
+}
+
 #[debug_handler]
-pub async fn list(State(ctx): State<AppContext>) -> Result<Response> {
-    format::json(Entity::find().all(&ctx.db).await?)
+pub async fn list(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    format::json(Entity::find_for_user(user.id).all(&ctx.db).await?)
 }
 
 #[debug_handler]
-pub async fn add(State(ctx): State<AppContext>, Json(params): Json<Params>) -> Result<Response> {
+pub async fn add(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Json(params): Json<Params>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    ensure_own_medicine(&ctx, &user, params.medicine_id).await?;
     let mut item = ActiveModel {
         ..Default::default()
     };
-    params.update(&mut item);
+    params.update(&mut item)?;
     let item = item.insert(&ctx.db).await?;
     format::json(item)
 }
 
 #[debug_handler]
 pub async fn update(
+    auth: auth::JWT,
     Path(id): Path<i32>,
     State(ctx): State<AppContext>,
     Json(params): Json<Params>,
 ) -> Result<Response> {
-    let item = load_item(&ctx, id).await?;
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let item = load_item(&ctx, &user, id).await?;
+    ensure_own_medicine(&ctx, &user, params.medicine_id).await?;
     let mut item = item.into_active_model();
-    params.update(&mut item);
+    params.update(&mut item)?;
     let item = item.update(&ctx.db).await?;
     format::json(item)
 }
 
 #[debug_handler]
-pub async fn remove(Path(id): Path<i32>, State(ctx): State<AppContext>) -> Result<Response> {
-    load_item(&ctx, id).await?.delete(&ctx.db).await?;
+pub async fn remove(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    load_item(&ctx, &user, id).await?.delete(&ctx.db).await?;
     format::empty()
 }
 
 #[debug_handler]
-pub async fn get_one(Path(id): Path<i32>, State(ctx): State<AppContext>) -> Result<Response> {
-    format::json(load_item(&ctx, id).await?)
+pub async fn occurrences(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    Query(query): Query<OccurrencesQuery>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let item = load_item(&ctx, &user, id).await?;
+    occurrences_response(&user, &item, &query)
+}
+
+/// 保存前のスケジュールの服薬時刻をプレビューする
+#[debug_handler]
+pub async fn preview_occurrences(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Query(query): Query<OccurrencesQuery>,
+    Json(params): Json<Params>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    ensure_own_medicine(&ctx, &user, params.medicine_id).await?;
+    let now = Utc::now();
+    let mut item = ActiveModel {
+        id: Set(0),
+        created_at: Set(now.into()),
+        updated_at: Set(now.into()),
+        dose_count: Set(0),
+        paused_by_archive: Set(false),
+        ..Default::default()
+    };
+    params.update(&mut item)?;
+    let item = item.try_into_model()?;
+    occurrences_response(&user, &item, &query)
+}
+
+#[debug_handler]
+pub async fn get_one(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    format::json(load_item(&ctx, &user, id).await?)
 }
 
 pub fn routes() -> Routes {
@@ -75,6 +254,8 @@ pub fn routes() -> Routes {
         .prefix("api/medication_schedules/")
         .add("/", get(list))
         .add("/", post(add))
+        .add("occurrences", post(preview_occurrences))
+        .add("{id}/occurrences", get(occurrences))
         .add("{id}", get(get_one))
         .add("{id}", delete(remove))
         .add("{id}", put(update))
@@ -1,11 +1,25 @@
 #![allow(clippy::missing_errors_doc)]
 #![allow(clippy::unnecessary_struct_initialization)]
 #![allow(clippy::unused_async)]
+#![allow(clippy::result_large_err)]
 use loco_rs::prelude::*;
 use serde::{Deserialize, Serialize};
-use axum::debug_handler;
+use axum::{debug_handler, extract::Query};
+use chrono::{Duration, Utc};
+use sea_orm::{prelude::Decimal, QueryOrder};
 
-use crate::models::_entities::medicines::{ActiveModel, Entity, Model};
+use crate::{
+    models::{
+        _entities::{
+            medication_logs, medication_schedules,
+            medicines::{self, ActiveModel, Entity, Model},
+        },
+        medication_logs::MedicationStatus,
+        medicines::{validate_prn, PrnWarning},
+        users,
+    },
+    workers::notification_worker::{NotificationWorker, NotificationWorkerArgs},
+};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Params {
@@ -13,63 +27,309 @@ pub struct Params {
     pub description: Option<String>,
     pub dosage: Option<String>,
     pub unit: Option<String>,
-    pub user_id: i32,
     pub active: Option<bool>,
+    /// 頓服薬（必要時に服用する薬）か
+    #[serde(default)]
+    pub as_needed: bool,
+    /// 頓服薬の最小服用間隔（分）
+    pub min_interval_minutes: Option<i32>,
+    /// 頓服薬の1日の上限回数
+    pub max_daily_doses: Option<i32>,
+    /// 在庫数（`dosage` と同じ単位）。省略時は在庫を管理しない
+    pub stock_quantity: Option<Decimal>,
     }
 
 impl Params {
-    fn update(&self, item: &mut ActiveModel) {
+    fn update(&self, item: &mut ActiveModel) -> Result<()> {
+      validate_prn(self.min_interval_minutes, self.max_daily_doses).map_err(Error::BadRequest)?;
       item.name = Set(self.name.clone());
       item.description = Set(self.description.clone());
       item.dosage = Set(self.dosage.clone());
       item.unit = Set(self.unit.clone());
-      item.user_id = Set(self.user_id.clone());
       item.active = Set(self.active.clone());
+      item.as_needed = Set(self.as_needed);
+      item.min_interval_minutes = Set(self.min_interval_minutes);
+      item.max_daily_doses = Set(self.max_daily_doses);
+      // 在庫が増えたら補充済みとして、次の補充通知を送れるようにする
+      let current_stock = item.stock_quantity.try_as_ref().copied().flatten();
+      if self.stock_quantity > current_stock {
+          item.refill_alerted_at = Set(None);
       }
+      item.stock_quantity = Set(self.stock_quantity);
+      Ok(())
+      }
+}
+
+/// 頓服薬の服用記録
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct DoseParams {
+    pub notes: Option<String>,
+    /// 警告がある場合に通知も送るか（ユーザーが選んだ経路で送る）
+    #[serde(default)]
+    pub line_alert: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DoseWarning {
+    #[serde(flatten)]
+    pub warning: PrnWarning,
+    pub message: String,
+}
+
+/// 薬の一覧の絞り込み
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct ListQuery {
+    /// アーカイブした薬も含めるか
+    #[serde(default)]
+    pub include_archived: bool,
+}
+
+/// アーカイブした薬の復元
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct RestoreParams {
+    /// 薬のスケジュールも再開するか
+    #[serde(default)]
+    pub reactivate_schedules: bool,
+}
+
+/// 薬の補充
+#[derive(Clone, Debug, Deserialize)]
+pub struct RefillParams {
+    /// 補充した数量（`dosage` と同じ単位）
+    pub quantity: Decimal,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DoseResponse {
+    pub log: medication_logs::Model,
+    pub warnings: Vec<DoseWarning>,
 }
 
-async fn load_item(ctx: &AppContext, id: i32) -> Result<Model> {
-    let item = Entity::find_by_id(id).one(&ctx.db).await?;
+/// ログイン中のユーザーの薬を取得する（他のユーザーの薬は見つからない扱い）
+async fn load_item(ctx: &AppContext, user: &users::Model, id: i32) -> Result<Model> {
+    let item = Entity::find_for_user(user.id)
+        .filter(medicines::Column::Id.eq(id))
+        .one(&ctx.db)
+        .await?;
     item.ok_or_else(|| Error::NotFound)
 }
 
 #[debug_handler]
-pub async fn list(State(ctx): State<AppContext>) -> Result<Response> {
-    format::json(Entity::find().all(&ctx.db).await?)
+pub async fn list(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Query(query): Query<ListQuery>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let items = if query.include_archived {
+        Entity::find_for_user(user.id)
+    } else {
+        Entity::find_unarchived_for_user(user.id)
+    };
+    format::json(items.all(&ctx.db).await?)
 }
 
 #[debug_handler]
-pub async fn add(State(ctx): State<AppContext>, Json(params): Json<Params>) -> Result<Response> {
+pub async fn add(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Json(params): Json<Params>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
     let mut item = ActiveModel {
+        user_id: Set(user.id),
         ..Default::default()
     };
-    params.update(&mut item);
+    params.update(&mut item)?;
     let item = item.insert(&ctx.db).await?;
     format::json(item)
 }
 
 #[debug_handler]
 pub async fn update(
+    auth: auth::JWT,
     Path(id): Path<i32>,
     State(ctx): State<AppContext>,
     Json(params): Json<Params>,
 ) -> Result<Response> {
-    let item = load_item(&ctx, id).await?;
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let item = load_item(&ctx, &user, id).await?;
+    // 更新で `active` が戻ると、アーカイブ中の薬が通知対象になってしまう
+    if item.is_archived() {
+        return Err(Error::BadRequest("medicine is archived; restore it first".to_string()));
+    }
     let mut item = item.into_active_model();
-    params.update(&mut item);
+    params.update(&mut item)?;
     let item = item.update(&ctx.db).await?;
     format::json(item)
 }
 
+/// 薬をアーカイブする（服薬記録を残すため削除はしない）
 #[debug_handler]
-pub async fn remove(Path(id): Path<i32>, State(ctx): State<AppContext>) -> Result<Response> {
-    load_item(&ctx, id).await?.delete(&ctx.db).await?;
+pub async fn remove(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let item = load_item(&ctx, &user, id).await?;
+    if !item.is_archived() {
+        item.into_active_model().archive(&ctx.db).await?;
+    }
     format::empty()
 }
 
+/// アーカイブした薬を元に戻す
+#[debug_handler]
+pub async fn restore(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    Json(params): Json<RestoreParams>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let item = load_item(&ctx, &user, id).await?;
+    if !item.is_archived() {
+        return Err(Error::BadRequest("medicine is not archived".to_string()));
+    }
+    let item = item
+        .into_active_model()
+        .restore(&ctx.db, params.reactivate_schedules)
+        .await?;
+    format::json(item)
+}
+
 #[debug_handler]
-pub async fn get_one(Path(id): Path<i32>, State(ctx): State<AppContext>) -> Result<Response> {
-    format::json(load_item(&ctx, id).await?)
+pub async fn get_one(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    format::json(load_item(&ctx, &user, id).await?)
+}
+
+/// 頓服薬を今服用したことを記録する
+///
+/// 最小間隔より早い場合や1日の上限回数を超える場合も記録し、警告を返す。
+#[debug_handler]
+pub async fn take_dose(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    Json(params): Json<DoseParams>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let medicine = load_item(&ctx, &user, id).await?;
+    if medicine.is_archived() {
+        return Err(Error::BadRequest("medicine is archived".to_string()));
+    }
+    if !medicine.as_needed {
+        return Err(Error::BadRequest("medicine is not as-needed".to_string()));
+    }
+    let now = Utc::now().with_timezone(&user.tz());
+
+    // 最小間隔と、今日（現地の日付）の回数の判定に必要な分だけ取得する
+    let lookback = Duration::hours(25).max(Duration::minutes(i64::from(
+        medicine.min_interval_minutes.unwrap_or(0),
+    )));
+    let previous_doses: Vec<_> = medication_logs::Entity::find()
+        .filter(medication_logs::Column::MedicineId.eq(medicine.id))
+        .filter(medication_logs::Column::Status.eq(MedicationStatus::Completed))
+        .filter(medication_logs::Column::TakenTime.gte(now.fixed_offset() - lookback))
+        .order_by_asc(medication_logs::Column::TakenTime)
+        .all(&ctx.db)
+        .await?
+        .into_iter()
+        .filter_map(|log| log.taken_time)
+        .collect();
+    let warnings = medicine.prn_warnings(&previous_doses, &now);
+
+    let log = medication_logs::ActiveModel {
+        medicine_id: Set(medicine.id),
+        scheduled_time: Set(now.fixed_offset()),
+        taken_time: Set(Some(now.fixed_offset())),
+        status: Set(MedicationStatus::Completed),
+        notes: Set(params.notes.clone()),
+        ..Default::default()
+    }
+    .insert(&ctx.db)
+    .await?;
+
+    if params.line_alert && !warnings.is_empty() {
+        let message = format!(
+            "⚠️ {}の服用についての注意\n\n{}",
+            medicine.name,
+            warnings.iter().map(PrnWarning::message).collect::<Vec<_>>().join("\n")
+        );
+        let notification_args = NotificationWorkerArgs {
+            user_id: Some(user.id),
+            line_user_id: None,
+            message,
+            notification_type: "prn_warning".to_string(),
+            medicine_id: Some(medicine.id),
+            log_id: Some(log.id),
+            report: None,
+            dead_letter_id: None,
+        };
+        // 服用の記録は済んでいるので、通知の失敗はログに残すだけにする
+        if let Err(e) = NotificationWorker::perform_later(&ctx, notification_args).await {
+            tracing::error!("Failed to send PRN warning for medicine {}: {}", medicine.id, e);
+        }
+    }
+
+    format::json(DoseResponse {
+        log,
+        warnings: warnings
+            .into_iter()
+            .map(|warning| DoseWarning {
+                message: warning.message(),
+                warning,
+            })
+            .collect(),
+    })
+}
+
+/// 在庫と、アクティブなスケジュールから見た残り日数
+#[debug_handler]
+pub async fn inventory(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let medicine = load_item(&ctx, &user, id).await?;
+    let schedules = medication_schedules::Entity::find()
+        .filter(medication_schedules::Column::MedicineId.eq(medicine.id))
+        .filter(medication_schedules::Column::Active.eq(true))
+        .all(&ctx.db)
+        .await?;
+
+    let now = Utc::now().with_timezone(&user.tz()).naive_local();
+    let forecast = medicine
+        .stock_forecast(&schedules, now)
+        .ok_or_else(|| Error::BadRequest("stock is not tracked for this medicine".to_string()))?;
+    format::json(forecast)
+}
+
+/// 在庫を補充する
+#[debug_handler]
+pub async fn refill(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    Json(params): Json<RefillParams>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    if params.quantity <= Decimal::ZERO {
+        return Err(Error::BadRequest("quantity must be positive".to_string()));
+    }
+    let medicine = load_item(&ctx, &user, id).await?;
+    let stock_quantity = medicine.stock_quantity.unwrap_or(Decimal::ZERO) + params.quantity;
+    let mut item = medicine.into_active_model();
+    item.stock_quantity = Set(Some(stock_quantity));
+    item.refill_alerted_at = Set(None);
+    format::json(item.update(&ctx.db).await?)
 }
 
 pub fn routes() -> Routes {
@@ -81,4 +341,8 @@ pub fn routes() -> Routes {
         .add("{id}", delete(remove))
         .add("{id}", put(update))
         .add("{id}", patch(update))
+        .add("{id}/doses", post(take_dose))
+        .add("{id}/inventory", get(inventory))
+        .add("{id}/refill", post(refill))
+        .add("{id}/restore", post(restore))
 }
@@ -6,4 +6,5 @@ pub mod reports;
 pub mod api_users;
 pub mod medicine;
 pub mod medication_schedule;
-pub mod medication_log;
\ No newline at end of file
+pub mod medication_log;
+pub mod notifications;
\ No newline at end of file
@@ -0,0 +1,60 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::unused_async)]
+#![allow(clippy::result_large_err)]
+use loco_rs::prelude::*;
+use serde::Deserialize;
+use axum::{debug_handler, extract::Query};
+use sea_orm::{Condition, QueryOrder};
+
+use crate::{
+    common::pagination,
+    models::{
+        notification_logs::{Column, Entity, NotificationStatus},
+        users,
+    },
+};
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ListQuery {
+    /// `sent` | `failed` | `skipped`
+    pub status: Option<NotificationStatus>,
+    pub page: Option<u64>,
+    pub page_size: Option<u64>,
+}
+
+impl ListQuery {
+    fn pagination(&self) -> Result<query::PaginationQuery> {
+        pagination::pagination_query(self.page, self.page_size)
+    }
+
+    fn condition(&self) -> Condition {
+        let mut condition = Condition::all();
+        if let Some(status) = self.status {
+            condition = condition.add(Column::Status.eq(status));
+        }
+        condition
+    }
+}
+
+/// ログイン中のユーザーへの通知の送信履歴を新しい順に返す
+#[debug_handler]
+pub async fn list(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Query(params): Query<ListQuery>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let pagination = params.pagination()?;
+    let notifications = Entity::find_for_user(user.id)
+        .order_by_desc(Column::CreatedAt)
+        .order_by_desc(Column::Id);
+    let page =
+        query::paginate(&ctx.db, notifications, Some(params.condition()), &pagination).await?;
+    format::json(pagination::pager(page, &pagination))
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("api/notifications/")
+        .add("/", get(list))
+}
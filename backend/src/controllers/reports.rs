@@ -1,12 +1,72 @@
 #![allow(clippy::missing_errors_doc)]
 #![allow(clippy::unnecessary_struct_initialization)]
 #![allow(clippy::unused_async)]
+#![allow(clippy::result_large_err)]
 use loco_rs::prelude::*;
-use axum::debug_handler;
+use serde::Deserialize;
+use axum::{debug_handler, extract::Query};
+use chrono::NaiveDate;
 
+use crate::{
+    models::users,
+    workers::report_generator::{ReportGeneratorArgs, ReportGeneratorWorker},
+};
+
+/// レポートの種類と期間
+#[derive(Debug, Deserialize)]
+pub struct ReportQuery {
+    /// `daily` | `weekly` | `monthly`（省略時は `weekly`）
+    #[serde(default = "default_report_type")]
+    pub report_type: String,
+    /// 期間（当日を含む）。省略時は種類に応じて今日までの期間
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+}
+
+fn default_report_type() -> String {
+    "weekly".to_string()
+}
+
+impl ReportQuery {
+    fn validate(&self) -> Result<()> {
+        if !matches!(self.report_type.as_str(), "daily" | "weekly" | "monthly") {
+            return Err(Error::BadRequest(
+                "report_type must be daily, weekly or monthly".to_string(),
+            ));
+        }
+        if let (Some(start), Some(end)) = (self.start_date, self.end_date) {
+            if end < start {
+                return Err(Error::BadRequest(
+                    "end_date must not be before start_date".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// ログイン中のユーザーの服薬レポートを作成して返す（通知は送らない）
 #[debug_handler]
-pub async fn index(State(_ctx): State<AppContext>) -> Result<Response> {
-    format::empty()
+pub async fn index(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Query(query): Query<ReportQuery>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    query.validate()?;
+
+    let worker = ReportGeneratorWorker::build(&ctx);
+    let (start_date, end_date) = worker.determine_report_period(&ReportGeneratorArgs {
+        user_id: user.id,
+        report_type: query.report_type.clone(),
+        start_date: query.start_date,
+        end_date: query.end_date,
+        send_notification: false,
+    })?;
+    let report = worker
+        .generate_medication_report(&ctx.db, &user, &query.report_type, start_date, end_date)
+        .await?;
+    format::json(report)
 }
 
 pub fn routes() -> Routes {
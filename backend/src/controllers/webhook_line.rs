@@ -1,16 +1,58 @@
 #![allow(clippy::missing_errors_doc)]
 #![allow(clippy::unnecessary_struct_initialization)]
 #![allow(clippy::unused_async)]
+use axum::{body::Bytes, debug_handler, http::HeaderMap};
 use loco_rs::prelude::*;
-use axum::debug_handler;
 
+use crate::{
+    common::settings::Settings,
+    line::{handler, signature, webhook::WebhookRequest},
+};
+
+/// LINE Messaging APIからのWebhookを受け付ける
+///
+/// `X-Line-Signature` を検証したうえでイベントを1件ずつハンドラーに渡す。
+/// 個々のイベントの処理に失敗してもLINE側の再送を招かないよう200を返す。
 #[debug_handler]
-pub async fn index(State(_ctx): State<AppContext>) -> Result<Response> {
+pub async fn webhook(
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response> {
+    let line = Settings::from_config(&ctx.config)?.line;
+    let Some(channel_secret) = line.channel_secret() else {
+        tracing::error!("LINE channel secret is not configured (settings.line.channel_secret)");
+        return unauthorized("webhook is not configured");
+    };
+
+    let Some(line_signature) = headers
+        .get(signature::SIGNATURE_HEADER)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return unauthorized("missing signature");
+    };
+
+    if !signature::verify(channel_secret, &body, line_signature) {
+        tracing::warn!("Rejected LINE webhook with invalid signature");
+        return unauthorized("invalid signature");
+    }
+
+    let request: WebhookRequest = serde_json::from_slice(&body)
+        .map_err(|e| Error::BadRequest(format!("invalid webhook payload: {e}")))?;
+
+    tracing::info!("📨 Received {} LINE webhook event(s)", request.events.len());
+
+    for event in request.events {
+        if let Err(e) = handler::dispatch(&ctx, event).await {
+            tracing::error!("Failed to handle LINE webhook event: {}", e);
+        }
+    }
+
     format::empty()
 }
 
 pub fn routes() -> Routes {
     Routes::new()
-        .prefix("api/webhook_lines/")
-        .add("/", get(index))
+        .prefix("api/webhook/")
+        .add("line", post(webhook))
 }
@@ -1 +1 @@
-
+pub mod reminder_scheduler;
@@ -0,0 +1,52 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use axum::Router as AxumRouter;
+use chrono::Utc;
+use loco_rs::{
+    app::{AppContext, Initializer},
+    Result,
+};
+use tokio::time::MissedTickBehavior;
+
+use crate::{common::settings::Settings, tasks::medication_reminder::MedicationReminderTask};
+
+/// サーバープロセス内で服薬リマインダーを定期的に実行する
+///
+/// ルーターの構築後（サーバー起動時）にバックグラウンドで開始する。処理済みの
+/// 時刻はDBに保存されるので、実行が遅れたり再起動したりしても取りこぼさない。
+pub struct ReminderSchedulerInitializer;
+
+#[async_trait]
+impl Initializer for ReminderSchedulerInitializer {
+    fn name(&self) -> String {
+        "reminder-scheduler".to_string()
+    }
+
+    async fn after_routes(&self, router: AxumRouter, ctx: &AppContext) -> Result<AxumRouter> {
+        let settings = Settings::from_config(&ctx.config)?.reminder_scheduler;
+        if !settings.enabled {
+            tracing::info!("⏸️ Reminder scheduler is disabled");
+            return Ok(router);
+        }
+
+        let period = Duration::from_secs(settings.tick_seconds.max(1));
+        tokio::spawn(run(ctx.clone(), period));
+        tracing::info!("⏰ Reminder scheduler started (every {}s)", period.as_secs());
+
+        Ok(router)
+    }
+}
+
+/// 一定間隔でリマインダーを処理し続ける
+async fn run(ctx: AppContext, period: Duration) {
+    let mut interval = tokio::time::interval(period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        interval.tick().await;
+        if let Err(e) = MedicationReminderTask.tick(&ctx, Utc::now()).await {
+            tracing::error!("Reminder scheduler tick failed: {}", e);
+        }
+    }
+}
@@ -1,9 +1,12 @@
 pub mod app;
+pub mod common;
 pub mod controllers;
 pub mod data;
 pub mod initializers;
+pub mod line;
 pub mod mailers;
 pub mod models;
+pub mod notifications;
 pub mod tasks;
 pub mod views;
 pub mod workers;
@@ -0,0 +1,59 @@
+use loco_rs::app::AppContext;
+use serde_json::{json, Value};
+
+use crate::common::settings::Settings;
+
+type ClientResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+const REPLY_PATH: &str = "/v2/bot/message/reply";
+
+/// Reply APIでメッセージを返信する
+///
+/// 返信トークンは受信から一定時間のみ有効なので、Webhookの処理中に呼び出すこと。
+///
+/// # Errors
+///
+/// アクセストークンが未設定、またはLINE APIがエラーを返した場合
+pub async fn reply(ctx: &AppContext, reply_token: &str, messages: Vec<Value>) -> ClientResult<()> {
+    let line = Settings::from_config(&ctx.config)?.line;
+    let Some(channel_access_token) = line.access_token()? else {
+        tracing::warn!("LINE channel access token is not properly configured. Skipping reply.");
+        return Ok(());
+    };
+
+    let body = json!({
+        "replyToken": reply_token,
+        "messages": messages,
+    });
+
+    let response = line
+        .http_client()?
+        .post(line.endpoint(REPLY_PATH))
+        .header("Authorization", format!("Bearer {channel_access_token}"))
+        .header("Content-Type", "application/json")
+        .json(&body)
+        .send()
+        .await?;
+
+    if response.status().is_success() {
+        tracing::debug!("LINE reply API response: {}", response.status());
+        Ok(())
+    } else {
+        let status = response.status();
+        let error_text = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unknown error".to_string());
+        tracing::error!("LINE reply API error: {} - {}", status, error_text);
+        Err(format!("LINE reply API error: {status} - {error_text}").into())
+    }
+}
+
+/// テキストメッセージ1件で返信する
+///
+/// # Errors
+///
+/// [`reply`] を参照
+pub async fn reply_text(ctx: &AppContext, reply_token: &str, text: &str) -> ClientResult<()> {
+    reply(ctx, reply_token, vec![json!({ "type": "text", "text": text })]).await
+}
@@ -0,0 +1,87 @@
+/// LINEのテキストメッセージから解釈した服薬コマンド
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// 服薬完了（「服薬完了」「飲んだ」など）
+    Complete,
+    /// 後で通知（「後で通知」「あとで」など）
+    Snooze,
+    /// 飲み忘れとして記録（「飲み忘れ」）
+    Missed,
+    /// 今回は服用しない（「スキップ」）
+    Skip,
+}
+
+const COMPLETE_PHRASES: &[&str] = &[
+    "服薬完了",
+    "完了",
+    "飲んだ",
+    "飲みました",
+    "のんだ",
+    "のみました",
+    "服用した",
+    "服用しました",
+];
+const SNOOZE_PHRASES: &[&str] = &[
+    "後で通知",
+    "後で",
+    "あとで",
+    "あとで通知",
+    "後でね",
+    "あとでね",
+];
+const MISSED_PHRASES: &[&str] = &[
+    "飲み忘れ",
+    "飲み忘れた",
+    "のみわすれ",
+    "飲み忘れ記録",
+    "忘れた",
+];
+const SKIP_PHRASES: &[&str] = &["スキップ", "すきっぷ", "skip"];
+
+impl Command {
+    /// メッセージ本文をコマンドとして解釈する
+    ///
+    /// 前後の空白や末尾の「！」「。」などは無視する。該当しない場合は `None`。
+    #[must_use]
+    pub fn parse(text: &str) -> Option<Self> {
+        let normalized = text
+            .trim()
+            .trim_end_matches(['!', '！', '。', '.', '~', '〜', 'ー', ' ', '　'])
+            .to_lowercase();
+
+        if MISSED_PHRASES.contains(&normalized.as_str()) {
+            Some(Self::Missed)
+        } else if COMPLETE_PHRASES.contains(&normalized.as_str()) {
+            Some(Self::Complete)
+        } else if SNOOZE_PHRASES.contains(&normalized.as_str()) {
+            Some(Self::Snooze)
+        } else if SKIP_PHRASES.contains(&normalized.as_str()) {
+            Some(Self::Skip)
+        } else {
+            None
+        }
+    }
+
+    /// ポストバックデータで使う識別子
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Complete => "complete",
+            Self::Snooze => "snooze",
+            Self::Missed => "missed",
+            Self::Skip => "skip",
+        }
+    }
+
+    /// [`Command::as_str`] の識別子からコマンドを復元する
+    #[must_use]
+    pub fn from_action(action: &str) -> Option<Self> {
+        match action {
+            "complete" => Some(Self::Complete),
+            "snooze" => Some(Self::Snooze),
+            "missed" => Some(Self::Missed),
+            "skip" => Some(Self::Skip),
+            _ => None,
+        }
+    }
+}
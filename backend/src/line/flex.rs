@@ -0,0 +1,354 @@
+//! Flexメッセージの型とビルダー
+//!
+//! LINEの [Flex Message](https://developers.line.biz/ja/docs/messaging-api/flex-message-elements/)
+//! のうち、通知で使う要素だけを型にしたもの。`serde_json::to_value` で
+//! Messaging APIにそのまま送れるJSONになる。
+
+use serde::{Deserialize, Serialize};
+
+/// Flexメッセージ
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type", rename = "flex", rename_all = "camelCase")]
+pub struct FlexMessage {
+    /// 通知やトーク一覧に表示される代替テキスト
+    pub alt_text: String,
+    pub contents: Container,
+}
+
+impl FlexMessage {
+    #[must_use]
+    pub fn new(alt_text: impl Into<String>, contents: impl Into<Container>) -> Self {
+        Self {
+            alt_text: alt_text.into(),
+            contents: contents.into(),
+        }
+    }
+}
+
+/// メッセージの最上位の要素
+///
+/// 各要素の型が自身の `type` を持つので、ここではタグを付けない。
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Container {
+    Bubble(Bubble),
+    Carousel(Carousel),
+}
+
+impl From<Bubble> for Container {
+    fn from(bubble: Bubble) -> Self {
+        Self::Bubble(bubble)
+    }
+}
+
+impl From<Carousel> for Container {
+    fn from(carousel: Carousel) -> Self {
+        Self::Carousel(carousel)
+    }
+}
+
+/// 1枚のカード（ヘッダー・本文・フッター）
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type", rename = "bubble")]
+pub struct Bubble {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header: Option<FlexBox>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<FlexBox>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub footer: Option<FlexBox>,
+}
+
+impl Bubble {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn header(mut self, header: FlexBox) -> Self {
+        self.header = Some(header);
+        self
+    }
+
+    #[must_use]
+    pub fn body(mut self, body: FlexBox) -> Self {
+        self.body = Some(body);
+        self
+    }
+
+    #[must_use]
+    pub fn footer(mut self, footer: FlexBox) -> Self {
+        self.footer = Some(footer);
+        self
+    }
+}
+
+/// 横に並べた複数のカード
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type", rename = "carousel")]
+pub struct Carousel {
+    pub contents: Vec<Bubble>,
+}
+
+impl Carousel {
+    #[must_use]
+    pub const fn new(contents: Vec<Bubble>) -> Self {
+        Self { contents }
+    }
+}
+
+/// 要素を並べるボックス
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type", rename = "box", rename_all = "camelCase")]
+pub struct FlexBox {
+    pub layout: Layout,
+    pub contents: Vec<Component>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spacing: Option<Size>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background_color: Option<String>,
+}
+
+impl FlexBox {
+    #[must_use]
+    pub fn new(layout: Layout, contents: Vec<Component>) -> Self {
+        Self {
+            layout,
+            contents,
+            spacing: None,
+            background_color: None,
+        }
+    }
+
+    /// 縦に並べるボックス
+    #[must_use]
+    pub fn vertical(contents: Vec<Component>) -> Self {
+        Self::new(Layout::Vertical, contents)
+    }
+
+    /// 横に並べるボックス
+    #[must_use]
+    pub fn horizontal(contents: Vec<Component>) -> Self {
+        Self::new(Layout::Horizontal, contents)
+    }
+
+    #[must_use]
+    pub const fn spacing(mut self, spacing: Size) -> Self {
+        self.spacing = Some(spacing);
+        self
+    }
+
+    #[must_use]
+    pub fn background_color(mut self, color: impl Into<String>) -> Self {
+        self.background_color = Some(color.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    Vertical,
+    Horizontal,
+    Baseline,
+}
+
+/// 文字の大きさ・要素の間隔
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Size {
+    Xxs,
+    Xs,
+    Sm,
+    Md,
+    Lg,
+    Xl,
+    Xxl,
+}
+
+/// ボックスに置く要素
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Component {
+    Box(FlexBox),
+    Text(Text),
+    Button(Button),
+    Separator(Separator),
+}
+
+impl From<FlexBox> for Component {
+    fn from(flex_box: FlexBox) -> Self {
+        Self::Box(flex_box)
+    }
+}
+
+impl From<Text> for Component {
+    fn from(text: Text) -> Self {
+        Self::Text(text)
+    }
+}
+
+impl From<Button> for Component {
+    fn from(button: Button) -> Self {
+        Self::Button(button)
+    }
+}
+
+impl From<Separator> for Component {
+    fn from(separator: Separator) -> Self {
+        Self::Separator(separator)
+    }
+}
+
+/// テキスト
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type", rename = "text")]
+pub struct Text {
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weight: Option<Weight>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<Size>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub align: Option<Align>,
+    /// 折り返して全文を表示する
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub wrap: bool,
+}
+
+impl Text {
+    #[must_use]
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            weight: None,
+            size: None,
+            color: None,
+            align: None,
+            wrap: false,
+        }
+    }
+
+    #[must_use]
+    pub const fn bold(mut self) -> Self {
+        self.weight = Some(Weight::Bold);
+        self
+    }
+
+    #[must_use]
+    pub const fn size(mut self, size: Size) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    #[must_use]
+    pub fn color(mut self, color: impl Into<String>) -> Self {
+        self.color = Some(color.into());
+        self
+    }
+
+    #[must_use]
+    pub const fn align(mut self, align: Align) -> Self {
+        self.align = Some(align);
+        self
+    }
+
+    #[must_use]
+    pub const fn wrap(mut self) -> Self {
+        self.wrap = true;
+        self
+    }
+}
+
+/// 区切り線
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type", rename = "separator")]
+pub struct Separator {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+}
+
+impl Separator {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Weight {
+    Regular,
+    Bold,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Align {
+    Start,
+    End,
+    Center,
+}
+
+/// ボタン
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type", rename = "button")]
+pub struct Button {
+    pub action: Action,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub style: Option<ButtonStyle>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+}
+
+impl Button {
+    #[must_use]
+    pub const fn new(action: Action) -> Self {
+        Self {
+            action,
+            style: None,
+            color: None,
+        }
+    }
+
+    #[must_use]
+    pub const fn style(mut self, style: ButtonStyle) -> Self {
+        self.style = Some(style);
+        self
+    }
+
+    #[must_use]
+    pub fn color(mut self, color: impl Into<String>) -> Self {
+        self.color = Some(color.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ButtonStyle {
+    Primary,
+    Secondary,
+    Link,
+}
+
+/// ボタンを押したときの動作
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Action {
+    /// Webhookにポストバックイベントを送る（`display_text` はトークに表示される）
+    #[serde(rename_all = "camelCase")]
+    Postback {
+        label: String,
+        data: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        display_text: Option<String>,
+    },
+    /// ユーザーの発言としてテキストを送る
+    Message { label: String, text: String },
+    /// URLを開く
+    Uri { label: String, uri: String },
+}
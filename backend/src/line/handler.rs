@@ -0,0 +1,259 @@
+use chrono::{Duration, Local};
+use loco_rs::prelude::*;
+
+use super::{
+    client,
+    command::Command,
+    postback::PostbackData,
+    webhook::{Event, FollowEvent, Message, MessageEvent, PostbackEvent, UnfollowEvent},
+};
+use crate::models::{
+    _entities::medicines,
+    medication_logs::{self, MedicationStatus},
+    users,
+};
+
+const HELP_MESSAGE: &str = "💊 服薬リマインダーです。\n\n次のように返信すると記録できます。\n• 服薬完了（飲んだ）\n• 後で通知（あとで）\n• 飲み忘れ\n• スキップ";
+const NOT_LINKED_MESSAGE: &str =
+    "このLINEアカウントはまだ連携されていません。Webアプリの設定画面から連携してください。";
+const NO_OPEN_LOG_MESSAGE: &str = "記録待ちの服薬はありません。";
+const LOG_NOT_FOUND_MESSAGE: &str = "対象の服薬記録が見つかりませんでした。";
+
+/// Webhookイベントを種類ごとのハンドラーに振り分ける
+///
+/// # Errors
+///
+/// ハンドラー内でDB操作などに失敗した場合
+pub async fn dispatch(ctx: &AppContext, event: Event) -> Result<()> {
+    match event {
+        Event::Message(event) => handle_message(ctx, event).await,
+        Event::Postback(event) => handle_postback(ctx, event).await,
+        Event::Follow(event) => handle_follow(ctx, event).await,
+        Event::Unfollow(event) => handle_unfollow(ctx, event).await,
+        Event::Unsupported => {
+            tracing::debug!("Ignoring unsupported LINE webhook event");
+            Ok(())
+        }
+    }
+}
+
+/// メッセージイベントを処理
+///
+/// テキストを服薬コマンドとして解釈し、最新の未記録ログに反映して結果を返信する。
+async fn handle_message(ctx: &AppContext, event: MessageEvent) -> Result<()> {
+    let Message::Text(text) = event.message else {
+        tracing::debug!(
+            "Ignoring non-text LINE message from {:?}",
+            event.source.user_id
+        );
+        return Ok(());
+    };
+    let Some(line_user_id) = event.source.user_id.as_deref() else {
+        return Ok(());
+    };
+
+    tracing::info!("💬 LINE message from {}: {}", line_user_id, text.text);
+
+    let reply = match Command::parse(&text.text) {
+        Some(command) => apply_command(ctx, line_user_id, command).await?,
+        None => HELP_MESSAGE.to_string(),
+    };
+    send_reply(ctx, &event.reply_token, &reply).await;
+    Ok(())
+}
+
+/// コマンドを最新の `pending` / `missed` ログに適用し、返信メッセージを返す
+async fn apply_command(ctx: &AppContext, line_user_id: &str, command: Command) -> Result<String> {
+    let Some(user) = find_linked_user(ctx, line_user_id).await? else {
+        return Ok(NOT_LINKED_MESSAGE.to_string());
+    };
+
+    let Some(log) = medication_logs::Model::find_latest_open_for_user(&ctx.db, user.id).await?
+    else {
+        return Ok(NO_OPEN_LOG_MESSAGE.to_string());
+    };
+
+    apply_to_log(ctx, &user, log, command).await
+}
+
+/// 指定された服薬ログにコマンドを適用し、返信メッセージを返す
+///
+/// 時刻はユーザーのタイムゾーンで表示する。
+async fn apply_to_log(
+    ctx: &AppContext,
+    user: &users::Model,
+    log: medication_logs::Model,
+    command: Command,
+) -> Result<String> {
+    let tz = user.tz();
+    let medicine_name = medicines::Entity::find_by_id(log.medicine_id)
+        .one(&ctx.db)
+        .await?
+        .map_or_else(|| "お薬".to_string(), |medicine| medicine.name);
+    let scheduled_time = log
+        .scheduled_time
+        .with_timezone(&tz)
+        .format("%H:%M")
+        .to_string();
+
+    let reply = match command {
+        Command::Complete => {
+            log.into_active_model()
+                .mark_completed(&ctx.db, Local::now().into())
+                .await?;
+            format!("✅ 服薬を記録しました！\n\n💊 {medicine_name}\n⏰ {scheduled_time}")
+        }
+        Command::Snooze => {
+            if !log.can_snooze() {
+                return Ok(format!(
+                    "⚠️ {medicine_name}（{scheduled_time}）はこれ以上延期できません。\n飲んだら「服薬完了」、今回飲まない場合は「スキップ」と返信してください。"
+                ));
+            }
+            let snoozed_until = (Local::now()
+                + Duration::minutes(medication_logs::SNOOZE_MINUTES))
+            .with_timezone(&tz);
+            let log = log
+                .into_active_model()
+                .snooze(&ctx.db, snoozed_until.fixed_offset())
+                .await?;
+            let remaining = medication_logs::MAX_SNOOZE_COUNT - log.snooze_count;
+            format!(
+                "⏰ 了解しました。{}ごろにもう一度お知らせします。\n\n💊 {medicine_name}（{scheduled_time}）\n（あと{remaining}回延期できます）",
+                snoozed_until.format("%H:%M")
+            )
+        }
+        Command::Missed => {
+            log.into_active_model().mark_missed(&ctx.db).await?;
+            format!("📝 飲み忘れとして記録しました。\n\n💊 {medicine_name}\n⏰ {scheduled_time}")
+        }
+        Command::Skip => {
+            log.into_active_model().mark_skipped(&ctx.db).await?;
+            format!("⏭️ 今回の服薬をスキップしました。\n\n💊 {medicine_name}\n⏰ {scheduled_time}")
+        }
+    };
+    Ok(reply)
+}
+
+/// 返信を送信（失敗してもWebhookの処理は継続する）
+async fn send_reply(ctx: &AppContext, reply_token: &str, text: &str) {
+    if let Err(e) = client::reply_text(ctx, reply_token, text).await {
+        tracing::error!("Failed to send LINE reply: {}", e);
+    }
+}
+
+/// ポストバックイベントを処理
+///
+/// データに埋め込まれた `log_id` のログだけを更新する。記録済みのログへの
+/// 再タップは何も変更せず、記録済みである旨を返信する。
+async fn handle_postback(ctx: &AppContext, event: PostbackEvent) -> Result<()> {
+    let Some(line_user_id) = event.source.user_id.as_deref() else {
+        return Ok(());
+    };
+
+    tracing::info!(
+        "📮 LINE postback from {}: {}",
+        line_user_id,
+        event.postback.data
+    );
+
+    let Some(data) = PostbackData::parse(&event.postback.data) else {
+        tracing::warn!(
+            "Ignoring malformed LINE postback data: {}",
+            event.postback.data
+        );
+        return Ok(());
+    };
+
+    let reply = apply_postback(ctx, line_user_id, data).await?;
+    send_reply(ctx, &event.reply_token, &reply).await;
+    Ok(())
+}
+
+/// ポストバックのコマンドを対象ログに適用し、返信メッセージを返す
+async fn apply_postback(
+    ctx: &AppContext,
+    line_user_id: &str,
+    data: PostbackData,
+) -> Result<String> {
+    let Some(user) = find_linked_user(ctx, line_user_id).await? else {
+        return Ok(NOT_LINKED_MESSAGE.to_string());
+    };
+
+    let Some(log) = medication_logs::Model::find_for_user(&ctx.db, data.log_id, user.id).await?
+    else {
+        tracing::warn!(
+            "LINE user {} sent postback for unknown log {}",
+            line_user_id,
+            data.log_id
+        );
+        return Ok(LOG_NOT_FOUND_MESSAGE.to_string());
+    };
+
+    let already_recorded = !log.is_open()
+        || (data.command == Command::Missed && log.status == MedicationStatus::Missed);
+    if already_recorded {
+        return Ok(format!(
+            "この服薬は既に「{}」として記録されています。",
+            log.status.label()
+        ));
+    }
+
+    // 再通知前に「後で通知」がもう一度届いても（二度押しや再送）、延期し直さない
+    if data.command == Command::Snooze
+        && log.status == MedicationStatus::Snoozed
+        && log.renotified_at.is_none()
+    {
+        if let Some(snoozed_until) = log.snoozed_until {
+            return Ok(format!(
+                "⏰ 既に延期しています。{}ごろにもう一度お知らせします。",
+                snoozed_until.with_timezone(&user.tz()).format("%H:%M")
+            ));
+        }
+    }
+
+    apply_to_log(ctx, &user, log, data.command).await
+}
+
+/// LINE user IDに連携済みのユーザーを取得
+async fn find_linked_user(ctx: &AppContext, line_user_id: &str) -> Result<Option<users::Model>> {
+    match users::Model::find_by_line_user_id(&ctx.db, line_user_id).await {
+        Ok(user) => Ok(Some(user)),
+        Err(ModelError::EntityNotFound) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// 友だち追加（ブロック解除）時は通知を再開する
+async fn handle_follow(ctx: &AppContext, event: FollowEvent) -> Result<()> {
+    let Some(line_user_id) = event.source.user_id.as_deref() else {
+        return Ok(());
+    };
+    tracing::info!("👋 LINE user followed: {}", line_user_id);
+    set_notification_enabled(ctx, line_user_id, true).await
+}
+
+/// ブロック時は通知を停止する
+async fn handle_unfollow(ctx: &AppContext, event: UnfollowEvent) -> Result<()> {
+    let Some(line_user_id) = event.source.user_id.as_deref() else {
+        return Ok(());
+    };
+    tracing::info!("🚫 LINE user unfollowed: {}", line_user_id);
+    set_notification_enabled(ctx, line_user_id, false).await
+}
+
+/// 連携済みユーザーの通知設定を更新（未連携のLINEユーザーは無視）
+async fn set_notification_enabled(
+    ctx: &AppContext,
+    line_user_id: &str,
+    enabled: bool,
+) -> Result<()> {
+    let Some(user) = find_linked_user(ctx, line_user_id).await? else {
+        tracing::debug!("No user linked to LINE user {}", line_user_id);
+        return Ok(());
+    };
+
+    let mut user = user.into_active_model();
+    user.notification_enabled = ActiveValue::Set(Some(enabled));
+    user.update(&ctx.db).await?;
+    Ok(())
+}
@@ -0,0 +1,12 @@
+//! LINE Messaging API 連携
+//!
+//! Webhookイベントの型定義、署名検証、イベントごとのハンドラー、
+//! 返信用のAPIクライアント、Flexメッセージの型をまとめたモジュール。
+
+pub mod client;
+pub mod command;
+pub mod flex;
+pub mod handler;
+pub mod postback;
+pub mod signature;
+pub mod webhook;
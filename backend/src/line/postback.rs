@@ -0,0 +1,50 @@
+use super::command::Command;
+
+/// Flexメッセージのボタンに埋め込むポストバックデータ
+///
+/// `action=complete&log_id=12&medicine_id=3` のようなクエリ文字列形式で、
+/// どの服薬ログに対する操作かを特定できるようにする。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PostbackData {
+    pub command: Command,
+    pub log_id: i32,
+    pub medicine_id: Option<i32>,
+}
+
+impl PostbackData {
+    /// ポストバックの `data` フィールドに設定する文字列を生成
+    #[must_use]
+    pub fn encode(&self) -> String {
+        let mut data = format!("action={}&log_id={}", self.command.as_str(), self.log_id);
+        if let Some(medicine_id) = self.medicine_id {
+            data.push_str(&format!("&medicine_id={medicine_id}"));
+        }
+        data
+    }
+
+    /// ポストバックの `data` フィールドを解析する
+    ///
+    /// `action` と `log_id` が揃っていない場合は `None`。
+    #[must_use]
+    pub fn parse(data: &str) -> Option<Self> {
+        let mut command = None;
+        let mut log_id = None;
+        let mut medicine_id = None;
+
+        for pair in data.split('&') {
+            let (key, value) = pair.split_once('=')?;
+            match key {
+                "action" => command = Command::from_action(value),
+                "log_id" => log_id = value.parse().ok(),
+                "medicine_id" => medicine_id = value.parse().ok(),
+                _ => {}
+            }
+        }
+
+        Some(Self {
+            command: command?,
+            log_id: log_id?,
+            medicine_id,
+        })
+    }
+}
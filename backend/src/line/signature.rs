@@ -0,0 +1,33 @@
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Webhookリクエストの署名ヘッダー名
+pub const SIGNATURE_HEADER: &str = "x-line-signature";
+
+/// `X-Line-Signature` を検証する
+///
+/// リクエストボディをチャネルシークレットでHMAC-SHA256し、Base64エンコードした値と
+/// ヘッダーの値を定数時間で比較する。
+#[must_use]
+pub fn verify(channel_secret: &str, body: &[u8], signature: &str) -> bool {
+    let Ok(expected) = STANDARD.decode(signature.trim()) else {
+        return false;
+    };
+    let Ok(mut mac) = HmacSha256::new_from_slice(channel_secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(body);
+    mac.verify_slice(&expected).is_ok()
+}
+
+/// リクエストボディに対する署名を生成する
+#[must_use]
+pub fn sign(channel_secret: &str, body: &[u8]) -> String {
+    let mut mac = HmacSha256::new_from_slice(channel_secret.as_bytes())
+        .expect("HMAC can take a key of any size");
+    mac.update(body);
+    STANDARD.encode(mac.finalize().into_bytes())
+}
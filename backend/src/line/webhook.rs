@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+
+/// Webhookで送られてくるイベントのバッチ
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WebhookRequest {
+    /// イベントを受信したボットのユーザーID
+    pub destination: Option<String>,
+    /// 受信したイベント（Webhook URLの検証時は空）
+    #[serde(default)]
+    pub events: Vec<Event>,
+}
+
+/// Webhookイベント
+///
+/// 処理対象外のイベント（join, beacon など）は `Unsupported` として読み捨てる。
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Event {
+    Message(MessageEvent),
+    Postback(PostbackEvent),
+    Follow(FollowEvent),
+    Unfollow(UnfollowEvent),
+    #[serde(other)]
+    Unsupported,
+}
+
+/// イベントの送信元
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Source {
+    /// "user", "group", "room" のいずれか
+    #[serde(rename = "type")]
+    pub source_type: String,
+    pub user_id: Option<String>,
+    pub group_id: Option<String>,
+    pub room_id: Option<String>,
+}
+
+/// メッセージイベント
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageEvent {
+    pub reply_token: String,
+    pub source: Source,
+    pub timestamp: i64,
+    pub webhook_event_id: Option<String>,
+    pub message: Message,
+}
+
+/// メッセージ本文（テキスト以外は内容を扱わない）
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Message {
+    Text(TextMessage),
+    #[serde(other)]
+    Unsupported,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TextMessage {
+    pub id: String,
+    pub text: String,
+}
+
+/// ポストバックイベント（Flexメッセージのボタン押下など）
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostbackEvent {
+    pub reply_token: String,
+    pub source: Source,
+    pub timestamp: i64,
+    pub webhook_event_id: Option<String>,
+    pub postback: Postback,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Postback {
+    pub data: String,
+}
+
+/// 友だち追加・ブロック解除イベント
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FollowEvent {
+    pub reply_token: String,
+    pub source: Source,
+    pub timestamp: i64,
+    pub webhook_event_id: Option<String>,
+}
+
+/// ブロックイベント（返信トークンは付与されない）
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnfollowEvent {
+    pub source: Source,
+    pub timestamp: i64,
+    pub webhook_event_id: Option<String>,
+}
@@ -0,0 +1,37 @@
+// notification mailer
+#![allow(non_upper_case_globals)]
+
+use loco_rs::prelude::*;
+use serde_json::Value;
+
+static notify: Dir<'_> = include_dir!("src/mailers/notification/notify");
+
+/// LINEを使わないユーザーにメールで通知するためのメーラー
+#[allow(clippy::module_name_repetitions)]
+pub struct NotificationMailer {}
+impl Mailer for NotificationMailer {}
+impl NotificationMailer {
+    /// 通知メールを送信する
+    ///
+    /// `locals` には `to`・`name`・`subject`・`message` を含める。
+    ///
+    /// # Errors
+    ///
+    /// When email sending is failed
+    pub async fn send_notification(ctx: &AppContext, to: &str, locals: &Value) -> Result<()> {
+        let mut locals = locals.clone();
+        locals["domain"] = Value::String(ctx.config.server.full_url());
+        Self::mail_template(
+            ctx,
+            &notify,
+            mailer::Args {
+                to: to.to_string(),
+                locals,
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+}
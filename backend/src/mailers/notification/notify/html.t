@@ -0,0 +1,9 @@
+;<html>
+
+<body>
+  <p>{{name}}さん</p>
+  <p style="white-space: pre-wrap">{{message}}</p>
+  <p><a href="{{domain}}">{{domain}}</a></p>
+</body>
+
+</html>
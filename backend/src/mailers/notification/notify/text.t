@@ -0,0 +1,5 @@
+{{name}}さん
+
+{{message}}
+
+{{domain}}
@@ -1,5 +1,6 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.12
 
+use super::sea_orm_active_enums::MedicationStatus;
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
@@ -13,9 +14,12 @@ pub struct Model {
     pub medicine_id: i32,
     pub scheduled_time: DateTimeWithTimeZone,
     pub taken_time: Option<DateTimeWithTimeZone>,
-    pub status: String,
+    pub status: MedicationStatus,
     #[sea_orm(column_type = "Text", nullable)]
     pub notes: Option<String>,
+    pub snoozed_until: Option<DateTimeWithTimeZone>,
+    pub renotified_at: Option<DateTimeWithTimeZone>,
+    pub snooze_count: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -11,10 +11,19 @@ pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i32,
     pub medicine_id: i32,
-    pub scheduled_time: DateTime,
     pub frequency: String,
     pub active: Option<bool>,
-    pub days_of_week: Option<String>,
+    pub time_of_day: Time,
+    pub weekdays: i16,
+    pub interval_count: Option<i32>,
+    pub interval_unit: Option<String>,
+    pub start_date: Option<Date>,
+    pub end_date: Option<Date>,
+    pub max_doses: Option<i32>,
+    pub dose_count: i32,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub rrule: Option<String>,
+    pub paused_by_archive: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -17,6 +17,13 @@ pub struct Model {
     pub unit: Option<String>,
     pub user_id: i32,
     pub active: Option<bool>,
+    pub as_needed: bool,
+    pub min_interval_minutes: Option<i32>,
+    pub max_daily_doses: Option<i32>,
+    #[sea_orm(column_type = "Decimal(Some((10, 2)))", nullable)]
+    pub stock_quantity: Option<Decimal>,
+    pub refill_alerted_at: Option<DateTimeWithTimeZone>,
+    pub archived_at: Option<DateTimeWithTimeZone>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -5,4 +5,8 @@ pub mod prelude;
 pub mod medication_logs;
 pub mod medication_schedules;
 pub mod medicines;
+pub mod notification_dead_letters;
+pub mod notification_logs;
+pub mod scheduler_states;
+pub mod sea_orm_active_enums;
 pub mod users;
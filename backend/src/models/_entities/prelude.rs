@@ -3,4 +3,7 @@
 pub use super::medication_logs::Entity as MedicationLogs;
 pub use super::medication_schedules::Entity as MedicationSchedules;
 pub use super::medicines::Entity as Medicines;
+pub use super::notification_dead_letters::Entity as NotificationDeadLetters;
+pub use super::notification_logs::Entity as NotificationLogs;
+pub use super::scheduler_states::Entity as SchedulerStates;
 pub use super::users::Entity as Users;
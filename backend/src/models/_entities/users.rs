@@ -1,5 +1,6 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.12
 
+use super::sea_orm_active_enums::NotificationStrategy;
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
@@ -24,6 +25,9 @@ pub struct Model {
     pub timezone: Option<String>,
     pub notification_enabled: Option<bool>,
     pub last_login_at: Option<DateTime>,
+    pub notification_channels: String,
+    pub notification_strategy: NotificationStrategy,
+    pub webhook_url: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
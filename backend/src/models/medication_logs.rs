@@ -1,13 +1,118 @@
-use sea_orm::entity::prelude::*;
-pub use super::_entities::medication_logs::{ActiveModel, Model, Entity};
+use std::{fmt, str::FromStr};
+
+use sea_orm::{entity::prelude::*, Iterable, QueryOrder, Select, Set};
+pub use super::_entities::medication_logs::{ActiveModel, Column, Model, Entity};
+pub use super::_entities::sea_orm_active_enums::MedicationStatus;
+use super::_entities::medicines;
 pub type MedicationLogs = Entity;
 
+/// スヌーズから再通知までの時間（分）
+pub const SNOOZE_MINUTES: i64 = 30;
+/// 1回の服薬あたりのスヌーズ上限回数
+pub const MAX_SNOOZE_COUNT: i32 = 3;
+
+impl MedicationStatus {
+    /// まだ記録が確定していない（`pending` / `snoozed` / `missed`）状態か
+    #[must_use]
+    pub const fn is_open(self) -> bool {
+        matches!(self, Self::Pending | Self::Snoozed | Self::Missed)
+    }
+
+    /// `next` に変更できるか
+    ///
+    /// 記録が確定した状態（`completed` / `skipped`）からは、同じ状態への更新を除き
+    /// 変更できない。変更するには明示的な上書きが必要。
+    #[must_use]
+    pub fn can_transition_to(self, next: Self) -> bool {
+        self == next || self.is_open()
+    }
+
+    /// 状態の変更を検証する
+    ///
+    /// # Errors
+    ///
+    /// 変更できない場合、その理由
+    pub fn validate_transition(self, next: Self) -> Result<(), String> {
+        if self.can_transition_to(next) {
+            Ok(())
+        } else {
+            Err(format!(
+                "cannot change status from `{self}` to `{next}` without `force`"
+            ))
+        }
+    }
+
+    /// 表示名
+    #[must_use]
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Pending => "未服薬",
+            Self::Completed => "服薬完了",
+            Self::Missed => "飲み忘れ",
+            Self::Skipped => "スキップ",
+            Self::Snoozed => "スヌーズ中",
+        }
+    }
+
+    /// 指定できる値の一覧（エラーメッセージ用）
+    #[must_use]
+    pub fn allowed_values() -> String {
+        Self::iter()
+            .map(|status| status.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl fmt::Display for MedicationStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_value())
+    }
+}
+
+impl FromStr for MedicationStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::iter().find(|status| status.to_value() == s).ok_or_else(|| {
+            format!(
+                "unknown status `{s}`; allowed values: {}",
+                Self::allowed_values()
+            )
+        })
+    }
+}
+
 #[async_trait::async_trait]
 impl ActiveModelBehavior for ActiveModel {
-    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    async fn before_save<C>(self, db: &C, insert: bool) -> std::result::Result<Self, DbErr>
     where
         C: ConnectionTrait,
     {
+        // 服薬完了になったら在庫を減らし、完了が取り消されたら戻す
+        if self.status.is_set() {
+            let previous = match self.id.try_as_ref() {
+                Some(id) if !insert => Entity::find_by_id(*id).one(db).await?,
+                _ => None,
+            };
+            let was_completed = previous
+                .as_ref()
+                .filter(|log| log.status == MedicationStatus::Completed);
+            let completed = self
+                .status
+                .try_as_ref()
+                .is_some_and(|status| *status == MedicationStatus::Completed);
+            match (was_completed, self.medicine_id.try_as_ref()) {
+                (None, Some(medicine_id)) if completed => {
+                    medicines::Entity::consume_stock(db, *medicine_id, 1).await?;
+                }
+                (Some(previous), _) if !completed => {
+                    medicines::Entity::consume_stock(db, previous.medicine_id, -1).await?;
+                }
+                _ => {}
+            }
+        }
+
         if !insert && self.updated_at.is_unchanged() {
             let mut this = self;
             this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
@@ -16,13 +121,152 @@ impl ActiveModelBehavior for ActiveModel {
             Ok(self)
         }
     }
+
+    async fn before_delete<C>(self, db: &C) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        // 服薬完了のログを削除したら在庫を戻す
+        if let Some(id) = self.id.try_as_ref() {
+            if let Some(log) = Entity::find_by_id(*id).one(db).await? {
+                if log.status == MedicationStatus::Completed {
+                    medicines::Entity::consume_stock(db, log.medicine_id, -1).await?;
+                }
+            }
+        }
+        Ok(self)
+    }
 }
 
 // implement your read-oriented logic here
-impl Model {}
+impl Model {
+    /// まだ記録が確定していない（`pending` / `snoozed` / `missed`）ログかどうか
+    #[must_use]
+    pub const fn is_open(&self) -> bool {
+        self.status.is_open()
+    }
+
+    /// スヌーズ上限に達していないか
+    #[must_use]
+    pub fn can_snooze(&self) -> bool {
+        self.snooze_count < MAX_SNOOZE_COUNT
+    }
+
+    /// ユーザーの薬に紐づくログをIDで取得する（他ユーザーのログは `None`）
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn find_for_user<C: ConnectionTrait>(
+        db: &C,
+        id: i32,
+        user_id: i32,
+    ) -> Result<Option<Self>, DbErr> {
+        Entity::find_by_id(id)
+            .filter(Column::MedicineId.is_in(user_medicine_ids(db, user_id).await?))
+            .one(db)
+            .await
+    }
+
+    /// ユーザーの最新の未記録ログ（`pending` / `snoozed` / `missed`）を取得する
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn find_latest_open_for_user<C: ConnectionTrait>(
+        db: &C,
+        user_id: i32,
+    ) -> Result<Option<Self>, DbErr> {
+        let medicine_ids = user_medicine_ids(db, user_id).await?;
+
+        Entity::find()
+            .filter(Column::MedicineId.is_in(medicine_ids))
+            .filter(Column::Status.is_in(
+                MedicationStatus::iter().filter(|status| status.is_open()),
+            ))
+            .order_by_desc(Column::ScheduledTime)
+            .one(db)
+            .await
+    }
+}
+
+/// ユーザーが登録した薬のID一覧
+async fn user_medicine_ids<C: ConnectionTrait>(db: &C, user_id: i32) -> Result<Vec<i32>, DbErr> {
+    Ok(medicines::Entity::find()
+        .filter(medicines::Column::UserId.eq(user_id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|m| m.id)
+        .collect())
+}
 
 // implement your write-oriented logic here
-impl ActiveModel {}
+impl ActiveModel {
+    /// 服薬完了として記録する
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn mark_completed<C: ConnectionTrait>(
+        mut self,
+        db: &C,
+        taken_time: DateTimeWithTimeZone,
+    ) -> Result<Model, DbErr> {
+        self.status = Set(MedicationStatus::Completed);
+        self.taken_time = Set(Some(taken_time));
+        self.update(db).await
+    }
+
+    /// スヌーズする
+    ///
+    /// ステータスを `snoozed` にして `snoozed_until` に再通知時刻を設定し、
+    /// スヌーズ回数を1つ増やす。
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn snooze<C: ConnectionTrait>(
+        mut self,
+        db: &C,
+        snoozed_until: DateTimeWithTimeZone,
+    ) -> Result<Model, DbErr> {
+        let snooze_count = *self.snooze_count.as_ref();
+        self.status = Set(MedicationStatus::Snoozed);
+        self.snoozed_until = Set(Some(snoozed_until));
+        self.renotified_at = Set(None);
+        self.snooze_count = Set(snooze_count + 1);
+        self.update(db).await
+    }
+
+    /// 飲み忘れとして記録する
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn mark_missed<C: ConnectionTrait>(mut self, db: &C) -> Result<Model, DbErr> {
+        self.status = Set(MedicationStatus::Missed);
+        self.taken_time = Set(None);
+        self.update(db).await
+    }
+
+    /// 今回の服用をスキップとして記録する
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn mark_skipped<C: ConnectionTrait>(mut self, db: &C) -> Result<Model, DbErr> {
+        self.status = Set(MedicationStatus::Skipped);
+        self.taken_time = Set(None);
+        self.update(db).await
+    }
+}
 
 // implement your custom finders, selectors oriented logic here
-impl Entity {}
+impl Entity {
+    /// ユーザーの薬の服薬ログ
+    #[must_use]
+    pub fn find_for_user(user_id: i32) -> Select<Self> {
+        Self::find().filter(Column::MedicineId.in_subquery(medicines::Entity::ids_for_user(user_id)))
+    }
+}
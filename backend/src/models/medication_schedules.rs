@@ -1,7 +1,130 @@
-use sea_orm::entity::prelude::*;
-pub use super::_entities::medication_schedules::{ActiveModel, Model, Entity};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Weekday};
+use rrule::{Frequency, RRule, RRuleError, RRuleSet, Unvalidated};
+use sea_orm::{entity::prelude::*, Select};
+pub use super::_entities::medication_schedules::{ActiveModel, Model, Entity, Column};
+use super::_entities::medicines;
 pub type MedicationSchedules = Entity;
 
+/// 全曜日を表す曜日ビットマスク（月曜が最下位ビット）
+pub const ALL_WEEKDAYS: i16 = 0b111_1111;
+
+/// 曜日に対応する `weekdays` のビット
+#[must_use]
+pub fn weekday_bit(weekday: Weekday) -> i16 {
+    1 << weekday.num_days_from_monday()
+}
+
+/// ISOの曜日番号（月曜=1〜日曜=7）の一覧から曜日ビットマスクを作る
+///
+/// 範囲外の番号が含まれる場合は `None`。
+#[must_use]
+pub fn weekdays_mask(days: &[u8]) -> Option<i16> {
+    days.iter().try_fold(0, |mask, day| match day {
+        1..=7 => Some(mask | 1 << (day - 1)),
+        _ => None,
+    })
+}
+
+/// 指定できる頻度
+pub const FREQUENCIES: &[&str] = &["daily", "weekly", "custom", "rrule"];
+/// `custom` の間隔の単位
+pub const INTERVAL_UNITS: &[&str] = &["hours", "days", "weeks"];
+
+/// 一度に展開する RRULE の服薬時刻の上限
+const MAX_RRULE_OCCURRENCES: u16 = 10_000;
+
+/// RRULE を `dt_start`（現地の日時）を起点とした繰り返しとして解釈する
+///
+/// 先頭の `RRULE:` は省略できる。日時は現地の時計の上で展開するため、
+/// タイムゾーンを持たない UTC の日時として扱う。
+///
+/// # Errors
+///
+/// RRULE として正しくない場合
+pub fn parse_rrule(rule: &str, dt_start: NaiveDateTime) -> Result<RRuleSet, RRuleError> {
+    let rule = rule.trim();
+    let rule = rule.strip_prefix("RRULE:").unwrap_or(rule);
+    rule.parse::<RRule<Unvalidated>>()?
+        .build(rrule::Tz::UTC.from_utc_datetime(&dt_start))
+}
+
+/// 頻度と間隔の組み合わせを検証する
+///
+/// `custom` の場合は1以上の間隔、単位、起点の日付がすべて必要。
+/// `rrule` の場合は正しい RRULE と起点の日付が必要で、分単位より細かい
+/// 繰り返しは指定できない。
+///
+/// # Errors
+///
+/// 組み合わせが正しくない場合、その理由
+pub fn validate_recurrence(
+    frequency: &str,
+    interval_count: Option<i32>,
+    interval_unit: Option<&str>,
+    rrule: Option<&str>,
+    start_date: Option<NaiveDate>,
+) -> Result<(), String> {
+    if !FREQUENCIES.contains(&frequency) {
+        return Err(format!("frequency must be one of {}", FREQUENCIES.join(", ")));
+    }
+    if frequency == "rrule" {
+        let rule = rrule.ok_or_else(|| "rrule is required for rrule frequency".to_string())?;
+        let start_date =
+            start_date.ok_or_else(|| "start_date is required for rrule frequency".to_string())?;
+        let rule_set = parse_rrule(rule, start_date.and_time(NaiveTime::MIN))
+            .map_err(|e| format!("invalid rrule: {e}"))?;
+        if rule_set
+            .get_rrule()
+            .iter()
+            .any(|rule| rule.get_freq() == Frequency::Secondly)
+        {
+            return Err("rrule must not repeat more often than every minute".to_string());
+        }
+        return Ok(());
+    }
+    if frequency != "custom" {
+        return Ok(());
+    }
+
+    match interval_count {
+        Some(count) if count >= 1 => {}
+        Some(_) => return Err("interval_count must be at least 1".to_string()),
+        None => return Err("interval_count is required for custom frequency".to_string()),
+    }
+    match interval_unit {
+        Some(unit) if INTERVAL_UNITS.contains(&unit) => {}
+        Some(_) => {
+            return Err(format!("interval_unit must be one of {}", INTERVAL_UNITS.join(", ")))
+        }
+        None => return Err("interval_unit is required for custom frequency".to_string()),
+    }
+    if start_date.is_none() {
+        return Err("start_date is required for custom frequency".to_string());
+    }
+    Ok(())
+}
+
+/// 服薬コースの期間と総服薬回数を検証する
+///
+/// # Errors
+///
+/// 終了日が開始日より前の場合や、総服薬回数が1未満の場合、その理由
+pub fn validate_course(
+    start_date: Option<NaiveDate>,
+    end_date: Option<NaiveDate>,
+    max_doses: Option<i32>,
+) -> Result<(), String> {
+    if let (Some(start_date), Some(end_date)) = (start_date, end_date) {
+        if end_date < start_date {
+            return Err("end_date must not be before start_date".to_string());
+        }
+    }
+    if max_doses.is_some_and(|max_doses| max_doses < 1) {
+        return Err("max_doses must be at least 1".to_string());
+    }
+    Ok(())
+}
+
 #[async_trait::async_trait]
 impl ActiveModelBehavior for ActiveModel {
     async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
@@ -19,10 +142,172 @@ impl ActiveModelBehavior for ActiveModel {
 }
 
 // implement your read-oriented logic here
-impl Model {}
+impl Model {
+    /// 指定した曜日が服薬日か
+    #[must_use]
+    pub fn runs_on(&self, weekday: Weekday) -> bool {
+        self.frequency == "daily" || self.weekdays & weekday_bit(weekday) != 0
+    }
+
+    /// 総服薬回数に達したか
+    #[must_use]
+    pub fn doses_exhausted(&self) -> bool {
+        self.max_doses.is_some_and(|max_doses| self.dose_count >= max_doses)
+    }
+
+    /// 現地の日付 `date` が服薬コースの期間内か
+    ///
+    /// 開始日・終了日を当日も含めて判定し、総服薬回数に達していれば期間外とする。
+    #[must_use]
+    pub fn in_course(&self, date: NaiveDate) -> bool {
+        self.start_date.is_none_or(|start_date| start_date <= date)
+            && self.end_date.is_none_or(|end_date| date <= end_date)
+            && !self.doses_exhausted()
+    }
+
+    /// 現地の日付 `today` の時点で服薬コースが終わっているか
+    #[must_use]
+    pub fn course_finished(&self, today: NaiveDate) -> bool {
+        self.end_date.is_some_and(|end_date| end_date < today) || self.doses_exhausted()
+    }
+
+    /// `from` 以降 `to` 以前（どちらも現地の日時）の服薬時刻
+    ///
+    /// リマインダーの送信と服薬時刻のプレビューは、どちらもこの展開に従う。
+    /// 総服薬回数が決まっている場合は残りの回数までに限る。
+    #[must_use]
+    pub fn occurrences(&self, from: NaiveDateTime, to: NaiveDateTime) -> Vec<NaiveDateTime> {
+        let remaining = self.max_doses.map_or(usize::MAX, |max_doses| {
+            usize::try_from(max_doses - self.dose_count).unwrap_or(0)
+        });
+
+        if self.frequency == "rrule" {
+            return self.rrule_occurrences(from, to).into_iter().take(remaining).collect();
+        }
+
+        // 候補の日時を間隔ごとにたどり、服薬時刻のものを集める
+        let (mut at, step) = match (self.frequency.as_str(), self.interval_unit.as_deref(), self.interval_count, self.start_date) {
+            ("custom", Some("hours"), Some(count), Some(start_date)) if count >= 1 => {
+                let anchor = start_date.and_time(self.time_of_day);
+                let step = Duration::hours(i64::from(count));
+                let steps = if from > anchor {
+                    ((from - anchor).num_minutes() + step.num_minutes() - 1) / step.num_minutes()
+                } else {
+                    0
+                };
+                (anchor + step * i32::try_from(steps).unwrap_or(i32::MAX), step)
+            }
+            _ => {
+                let first = from.date().and_time(self.time_of_day);
+                (if first < from { first + Duration::days(1) } else { first }, Duration::days(1))
+            }
+        };
+
+        let mut occurrences = Vec::new();
+        while at <= to && occurrences.len() < remaining {
+            if self.occurs_at(at) {
+                occurrences.push(at);
+            }
+            at += step;
+        }
+        occurrences
+    }
+
+    /// RRULE を `from` 以降 `to` 以前に展開する（服薬コースの期間内に限る）
+    fn rrule_occurrences(&self, from: NaiveDateTime, to: NaiveDateTime) -> Vec<NaiveDateTime> {
+        let (Some(rule), Some(start_date)) = (self.rrule.as_deref(), self.start_date) else {
+            return Vec::new();
+        };
+        let rule_set = match parse_rrule(rule, start_date.and_time(self.time_of_day)) {
+            Ok(rule_set) => rule_set,
+            Err(e) => {
+                tracing::warn!("Invalid rrule on schedule {}: {}", self.id, e);
+                return Vec::new();
+            }
+        };
+
+        rule_set
+            .after(rrule::Tz::UTC.from_utc_datetime(&from))
+            .before(rrule::Tz::UTC.from_utc_datetime(&to))
+            .all(MAX_RRULE_OCCURRENCES)
+            .dates
+            .into_iter()
+            .map(|at| at.naive_utc())
+            .filter(|at| self.in_course(at.date()))
+            .collect()
+    }
+
+    /// 現地日時 `at`（分単位）が服薬時刻か
+    ///
+    /// `custom` は `start_date` の `time_of_day` を起点に、間隔ごとに服薬する。
+    /// 時間単位の間隔は現地の時計の上で数える。`rrule` は `start_date` の
+    /// `time_of_day` を DTSTART として RRULE を展開する。服薬コースの期間外は
+    /// 常に `false`。
+    #[must_use]
+    pub fn occurs_at(&self, at: NaiveDateTime) -> bool {
+        if !self.in_course(at.date()) {
+            return false;
+        }
+        if self.frequency == "rrule" {
+            return !self.rrule_occurrences(at, at).is_empty();
+        }
+        if self.frequency != "custom" {
+            return at.time() == self.time_of_day && self.runs_on(at.weekday());
+        }
+
+        let (Some(count), Some(unit), Some(start_date)) =
+            (self.interval_count, self.interval_unit.as_deref(), self.start_date)
+        else {
+            return false;
+        };
+        let anchor = start_date.and_time(self.time_of_day);
+        if count < 1 || at < anchor {
+            return false;
+        }
+
+        let count = i64::from(count);
+        match unit {
+            "hours" => (at - anchor).num_minutes() % (count * 60) == 0,
+            "days" => at.time() == self.time_of_day && (at.date() - start_date).num_days() % count == 0,
+            "weeks" => {
+                at.time() == self.time_of_day && (at.date() - start_date).num_days() % (count * 7) == 0
+            }
+            _ => false,
+        }
+    }
+
+    /// 服薬日のISO曜日番号（月曜=1〜日曜=7）
+    #[must_use]
+    pub fn days_of_week(&self) -> Vec<u8> {
+        (1..=7).filter(|day| self.weekdays & 1 << (day - 1) != 0).collect()
+    }
+}
 
 // implement your write-oriented logic here
-impl ActiveModel {}
+impl ActiveModel {
+    /// 服薬回数を1増やし、更新後のスケジュールを返す
+    ///
+    /// # Errors
+    ///
+    /// データベースの更新に失敗した場合
+    pub async fn record_dose(db: &DatabaseConnection, id: i32) -> Result<Model, DbErr> {
+        Entity::update_many()
+            .col_expr(Column::DoseCount, Expr::col(Column::DoseCount).add(1))
+            .filter(Column::Id.eq(id))
+            .exec(db)
+            .await?;
+        Entity::find_by_id(id)
+            .one(db)
+            .await?
+            .ok_or_else(|| DbErr::RecordNotFound(format!("medication_schedule {id}")))
+    }
+}
 
 // implement your custom finders, selectors oriented logic here
-impl Entity {}
+impl Entity {
+    /// ユーザーの薬のスケジュール
+    #[must_use]
+    pub fn find_for_user(user_id: i32) -> Select<Self> {
+        Self::find().filter(Column::MedicineId.in_subquery(medicines::Entity::ids_for_user(user_id)))
+    }
+}
@@ -1,7 +1,76 @@
-use sea_orm::entity::prelude::*;
-pub use super::_entities::medicines::{ActiveModel, Model, Entity};
+use std::str::FromStr;
+
+use chrono::{DateTime, Duration, FixedOffset, NaiveDateTime, TimeZone};
+use sea_orm::{
+    entity::prelude::*,
+    sea_query::{Expr, Query, SelectStatement},
+    Select, TransactionTrait,
+};
+use serde::Serialize;
+pub use super::_entities::medicines::{ActiveModel, Column, Model, Entity};
+use super::_entities::medication_schedules;
+use super::medication_schedules::Model as MedicationSchedule;
 pub type Medicines = Entity;
 
+/// 在庫の見通しで服薬回数を数える期間（日）
+const FORECAST_DAYS: i64 = 28;
+
+/// 在庫の見通し
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct StockForecast {
+    pub stock_quantity: Decimal,
+    /// 1回の服用量（`dosage`）
+    pub dose_amount: Decimal,
+    /// アクティブなスケジュールによる1日あたりの服用回数
+    pub doses_per_day: Decimal,
+    /// 在庫がなくなるまでの日数（服用予定がない場合は `None`）
+    pub days_remaining: Option<i64>,
+}
+
+/// 頓服薬の服用に対する警告
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "code", rename_all = "snake_case")]
+pub enum PrnWarning {
+    /// 前回の服用から最小間隔が経っていない
+    TooSoon {
+        last_taken_at: DateTime<FixedOffset>,
+        next_allowed_at: DateTime<FixedOffset>,
+    },
+    /// 1日の上限回数を超えた
+    DailyLimitExceeded { taken_today: i32, max_daily_doses: i32 },
+}
+
+impl PrnWarning {
+    /// ユーザー向けのメッセージ
+    #[must_use]
+    pub fn message(&self) -> String {
+        match self {
+            Self::TooSoon { next_allowed_at, .. } => format!(
+                "前回の服用から間隔が空いていません。次に服用できるのは{}以降です。",
+                next_allowed_at.format("%H:%M")
+            ),
+            Self::DailyLimitExceeded { taken_today, max_daily_doses } => format!(
+                "本日{taken_today}回目の服用です。1日の上限（{max_daily_doses}回）を超えています。"
+            ),
+        }
+    }
+}
+
+/// 頓服薬の服用間隔と1日の上限回数を検証する
+///
+/// # Errors
+///
+/// 1未満の値が指定された場合、その理由
+pub fn validate_prn(min_interval_minutes: Option<i32>, max_daily_doses: Option<i32>) -> Result<(), String> {
+    if min_interval_minutes.is_some_and(|minutes| minutes < 1) {
+        return Err("min_interval_minutes must be at least 1".to_string());
+    }
+    if max_daily_doses.is_some_and(|doses| doses < 1) {
+        return Err("max_daily_doses must be at least 1".to_string());
+    }
+    Ok(())
+}
+
 #[async_trait::async_trait]
 impl ActiveModelBehavior for ActiveModel {
     async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
@@ -19,10 +88,208 @@ impl ActiveModelBehavior for ActiveModel {
 }
 
 // implement your read-oriented logic here
-impl Model {}
+impl Model {
+    /// アーカイブ（削除）済みか
+    #[must_use]
+    pub const fn is_archived(&self) -> bool {
+        self.archived_at.is_some()
+    }
+
+    /// 1回の服用量
+    ///
+    /// `dosage` の先頭の数値（例: `"1.5"`、`"2錠"`）。数値でない場合は1とする。
+    #[must_use]
+    pub fn dose_amount(&self) -> Decimal {
+        self.dosage
+            .as_deref()
+            .map(|dosage| {
+                dosage
+                    .trim()
+                    .chars()
+                    .take_while(|c| c.is_ascii_digit() || *c == '.')
+                    .collect::<String>()
+            })
+            .and_then(|amount| Decimal::from_str(&amount).ok())
+            .filter(|amount| *amount > Decimal::ZERO)
+            .unwrap_or(Decimal::ONE)
+    }
+
+    /// 在庫の見通し（在庫を管理していない場合は `None`）
+    ///
+    /// `schedules` はこの薬のアクティブなスケジュール。`from`（現地の日時）から
+    /// 一定期間の服薬時刻を展開し、1日あたりの服用回数を求める。
+    #[must_use]
+    pub fn stock_forecast(&self, schedules: &[MedicationSchedule], from: NaiveDateTime) -> Option<StockForecast> {
+        let stock_quantity = self.stock_quantity?;
+        let dose_amount = self.dose_amount();
+        let doses: usize = schedules
+            .iter()
+            .map(|schedule| schedule.occurrences(from, from + Duration::days(FORECAST_DAYS)).len())
+            .sum();
+        let doses = Decimal::from(doses);
+
+        let days_remaining = (doses > Decimal::ZERO).then(|| {
+            let days = (stock_quantity.max(Decimal::ZERO) * Decimal::from(FORECAST_DAYS)
+                / (dose_amount * doses))
+                .floor();
+            i64::try_from(days).unwrap_or(i64::MAX)
+        });
+
+        Some(StockForecast {
+            stock_quantity,
+            dose_amount,
+            doses_per_day: (doses / Decimal::from(FORECAST_DAYS)).round_dp(2),
+            days_remaining,
+        })
+    }
+
+    /// 頓服薬を `now` に服用する場合の警告
+    ///
+    /// `previous_doses` はこれまでに服用した時刻。1日の回数は `now` の
+    /// タイムゾーン（ユーザーのタイムゾーン）の日付で数える。
+    #[must_use]
+    pub fn prn_warnings<Tz: TimeZone>(
+        &self,
+        previous_doses: &[DateTime<FixedOffset>],
+        now: &DateTime<Tz>,
+    ) -> Vec<PrnWarning> {
+        let mut warnings = Vec::new();
+
+        let last_taken_at = previous_doses.iter().filter(|taken| **taken <= *now).max();
+        if let (Some(minutes), Some(last_taken_at)) = (self.min_interval_minutes, last_taken_at) {
+            let next_allowed_at = *last_taken_at + Duration::minutes(i64::from(minutes));
+            if *now < next_allowed_at {
+                warnings.push(PrnWarning::TooSoon {
+                    last_taken_at: last_taken_at.with_timezone(&now.timezone()).fixed_offset(),
+                    next_allowed_at: next_allowed_at.with_timezone(&now.timezone()).fixed_offset(),
+                });
+            }
+        }
+
+        if let Some(max_daily_doses) = self.max_daily_doses {
+            let today = now.date_naive();
+            let taken_today = previous_doses
+                .iter()
+                .filter(|taken| taken.with_timezone(&now.timezone()).date_naive() == today)
+                .count();
+            let taken_today = i32::try_from(taken_today).unwrap_or(i32::MAX).saturating_add(1);
+            if taken_today > max_daily_doses {
+                warnings.push(PrnWarning::DailyLimitExceeded { taken_today, max_daily_doses });
+            }
+        }
+
+        warnings
+    }
+}
 
 // implement your write-oriented logic here
-impl ActiveModel {}
+impl ActiveModel {
+    /// 薬をアーカイブし、スケジュールを一時停止する
+    ///
+    /// 服薬記録を残すため、薬は削除せずに `active=false` にする。
+    /// 一時停止したスケジュールには `paused_by_archive` を付け、元に戻すときに
+    /// それ以前から止まっていたもの（終了したコースなど）と区別する。
+    ///
+    /// # Errors
+    ///
+    /// データベースの更新に失敗した場合
+    pub async fn archive(mut self, db: &DatabaseConnection) -> Result<Model, DbErr> {
+        let txn = db.begin().await?;
+        self.active = sea_orm::ActiveValue::Set(Some(false));
+        self.archived_at = sea_orm::ActiveValue::Set(Some(chrono::Utc::now().into()));
+        let medicine = self.update(&txn).await?;
+        medication_schedules::Entity::update_many()
+            .col_expr(medication_schedules::Column::Active, Expr::value(false))
+            .col_expr(medication_schedules::Column::PausedByArchive, Expr::value(true))
+            .col_expr(
+                medication_schedules::Column::UpdatedAt,
+                Expr::value(chrono::Utc::now().fixed_offset()),
+            )
+            .filter(medication_schedules::Column::MedicineId.eq(medicine.id))
+            .filter(medication_schedules::Column::Active.eq(true))
+            .exec(&txn)
+            .await?;
+        txn.commit().await?;
+        Ok(medicine)
+    }
+
+    /// アーカイブした薬を元に戻す
+    ///
+    /// `reactivate_schedules` が `true` の場合は、アーカイブで一時停止した
+    /// スケジュールを再開する。
+    ///
+    /// # Errors
+    ///
+    /// データベースの更新に失敗した場合
+    pub async fn restore(
+        mut self,
+        db: &DatabaseConnection,
+        reactivate_schedules: bool,
+    ) -> Result<Model, DbErr> {
+        let txn = db.begin().await?;
+        self.active = sea_orm::ActiveValue::Set(Some(true));
+        self.archived_at = sea_orm::ActiveValue::Set(None);
+        let medicine = self.update(&txn).await?;
+        let mut paused = medication_schedules::Entity::update_many()
+            .col_expr(medication_schedules::Column::PausedByArchive, Expr::value(false))
+            .col_expr(
+                medication_schedules::Column::UpdatedAt,
+                Expr::value(chrono::Utc::now().fixed_offset()),
+            );
+        if reactivate_schedules {
+            paused = paused.col_expr(medication_schedules::Column::Active, Expr::value(true));
+        }
+        paused
+            .filter(medication_schedules::Column::MedicineId.eq(medicine.id))
+            .filter(medication_schedules::Column::PausedByArchive.eq(true))
+            .exec(&txn)
+            .await?;
+        txn.commit().await?;
+        Ok(medicine)
+    }
+}
 
 // implement your custom finders, selectors oriented logic here
-impl Entity {}
+impl Entity {
+    /// ユーザーの薬
+    #[must_use]
+    pub fn find_for_user(user_id: i32) -> Select<Self> {
+        Self::find().filter(Column::UserId.eq(user_id))
+    }
+
+    /// ユーザーのアーカイブされていない薬
+    #[must_use]
+    pub fn find_unarchived_for_user(user_id: i32) -> Select<Self> {
+        Self::find_for_user(user_id).filter(Column::ArchivedAt.is_null())
+    }
+
+    /// ユーザーの薬のIDを選ぶサブクエリ
+    #[must_use]
+    pub fn ids_for_user(user_id: i32) -> SelectStatement {
+        Query::select()
+            .column(Column::Id)
+            .from(Self)
+            .and_where(Column::UserId.eq(user_id))
+            .to_owned()
+    }
+
+    /// 服用 `doses` 回分だけ在庫を減らす（負の値なら戻す）
+    ///
+    /// 在庫を管理していない（`stock_quantity` が `NULL`）薬は変わらない。
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn consume_stock<C: ConnectionTrait>(db: &C, id: i32, doses: i32) -> Result<(), DbErr> {
+        let Some(medicine) = Self::find_by_id(id).one(db).await? else {
+            return Ok(());
+        };
+        let amount = medicine.dose_amount() * Decimal::from(doses);
+        Self::update_many()
+            .col_expr(Column::StockQuantity, Expr::col(Column::StockQuantity).sub(amount))
+            .filter(Column::Id.eq(id))
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+}
@@ -3,3 +3,6 @@ pub mod users;
 pub mod medicines;
 pub mod medication_schedules;
 pub mod medication_logs;
+pub mod notification_dead_letters;
+pub mod notification_logs;
+pub mod scheduler_states;
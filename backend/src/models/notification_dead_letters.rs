@@ -0,0 +1,98 @@
+use sea_orm::{entity::prelude::*, ActiveValue, QueryOrder, Select};
+pub use super::_entities::notification_dead_letters::{ActiveModel, Model, Entity, Column};
+pub type NotificationDeadLetters = Entity;
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+// implement your read-oriented logic here
+impl Model {
+    #[must_use]
+    pub const fn is_replayed(&self) -> bool {
+        self.replayed_at.is_some()
+    }
+}
+
+// implement your write-oriented logic here
+impl ActiveModel {
+    /// 届かなかった通知のジョブを保存する
+    ///
+    /// # Errors
+    ///
+    /// DBへの保存に失敗した場合
+    pub async fn park<C: ConnectionTrait>(
+        db: &C,
+        user_id: Option<i32>,
+        notification_type: &str,
+        args: Json,
+        error: &str,
+        attempts: i32,
+    ) -> Result<Model, DbErr> {
+        Self {
+            user_id: ActiveValue::Set(user_id),
+            notification_type: ActiveValue::Set(notification_type.to_string()),
+            args: ActiveValue::Set(args),
+            error: ActiveValue::Set(error.to_string()),
+            attempts: ActiveValue::Set(attempts),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+    }
+
+    /// 再送したことを記録する
+    ///
+    /// # Errors
+    ///
+    /// DBへの保存に失敗した場合
+    pub async fn mark_replayed<C: ConnectionTrait>(mut self, db: &C) -> Result<Model, DbErr> {
+        self.replayed_at = ActiveValue::Set(Some(chrono::Utc::now().into()));
+        self.update(db).await
+    }
+
+    /// 再送しても届かなかった通知を、最後のエラーで未再送に戻す
+    ///
+    /// # Errors
+    ///
+    /// 通知が見つからない場合、またはDBへの保存に失敗した場合
+    pub async fn repark<C: ConnectionTrait>(
+        db: &C,
+        id: i32,
+        error: &str,
+        attempts: i32,
+    ) -> Result<Model, DbErr> {
+        let dead_letter = Entity::find_by_id(id)
+            .one(db)
+            .await?
+            .ok_or_else(|| DbErr::RecordNotFound(format!("Dead letter {id} not found")))?;
+        let mut dead_letter: Self = dead_letter.into();
+        dead_letter.error = ActiveValue::Set(error.to_string());
+        dead_letter.attempts = ActiveValue::Set(attempts);
+        dead_letter.replayed_at = ActiveValue::Set(None);
+        dead_letter.update(db).await
+    }
+}
+
+// implement your custom finders, selectors oriented logic here
+impl Entity {
+    /// まだ再送していないもの（古い順）
+    #[must_use]
+    pub fn find_pending() -> Select<Self> {
+        Self::find()
+            .filter(Column::ReplayedAt.is_null())
+            .order_by_asc(Column::Id)
+    }
+}
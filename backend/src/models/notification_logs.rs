@@ -0,0 +1,47 @@
+use sea_orm::{entity::prelude::*, Select};
+use sha2::{Digest, Sha256};
+pub use super::_entities::notification_logs::{ActiveModel, Model, Entity, Column};
+pub use super::_entities::sea_orm_active_enums::NotificationStatus;
+pub type NotificationLogs = Entity;
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+/// 送信内容のハッシュ（SHA-256 の16進表記）
+///
+/// 本文を保存せずに、同じ内容が送られたかを照合するために使う。
+#[must_use]
+pub fn payload_hash(payload: &[u8]) -> String {
+    Sha256::digest(payload)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+// implement your read-oriented logic here
+impl Model {}
+
+// implement your write-oriented logic here
+impl ActiveModel {}
+
+// implement your custom finders, selectors oriented logic here
+impl Entity {
+    /// ユーザーへの通知履歴
+    #[must_use]
+    pub fn find_for_user(user_id: i32) -> Select<Self> {
+        Self::find().filter(Column::UserId.eq(user_id))
+    }
+}
@@ -0,0 +1,72 @@
+use sea_orm::{entity::prelude::*, IntoActiveModel, Set};
+pub use super::_entities::scheduler_states::{ActiveModel, Model, Entity, Column};
+pub type SchedulerStates = Entity;
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+// implement your read-oriented logic here
+impl Model {
+    /// スケジューラーが最後に処理を終えた時刻（未実行なら `None`）
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn last_run_at<C: ConnectionTrait>(
+        db: &C,
+        name: &str,
+    ) -> Result<Option<DateTimeWithTimeZone>, DbErr> {
+        Ok(Entity::find()
+            .filter(Column::Name.eq(name))
+            .one(db)
+            .await?
+            .map(|state| state.last_run_at))
+    }
+}
+
+// implement your write-oriented logic here
+impl ActiveModel {
+    /// スケジューラーの処理済み時刻を記録する
+    ///
+    /// # Errors
+    ///
+    /// When DB query error
+    pub async fn advance<C: ConnectionTrait>(
+        db: &C,
+        name: &str,
+        last_run_at: DateTimeWithTimeZone,
+    ) -> Result<Model, DbErr> {
+        match Entity::find().filter(Column::Name.eq(name)).one(db).await? {
+            Some(state) => {
+                let mut state = state.into_active_model();
+                state.last_run_at = Set(last_run_at);
+                state.update(db).await
+            }
+            None => {
+                Self {
+                    name: Set(name.to_string()),
+                    last_run_at: Set(last_run_at),
+                    ..Default::default()
+                }
+                .insert(db)
+                .await
+            }
+        }
+    }
+}
+
+// implement your custom finders, selectors oriented logic here
+impl Entity {}
@@ -1,5 +1,6 @@
 use async_trait::async_trait;
 use chrono::offset::Local;
+use chrono_tz::Tz;
 use loco_rs::{auth::jwt, hash, prelude::*};
 use serde::{Deserialize, Serialize};
 use serde_json::Map;
@@ -7,6 +8,21 @@ use uuid::Uuid;
 
 pub use super::_entities::users::{self, ActiveModel, Entity, Model};
 
+/// Timezone used when a user has none set (or an unknown one)
+pub const DEFAULT_TIMEZONE: Tz = chrono_tz::Asia::Tokyo;
+
+/// Parses a stored IANA timezone name, falling back to [`DEFAULT_TIMEZONE`]
+#[must_use]
+pub fn parse_timezone(name: Option<&str>) -> Tz {
+    match name {
+        Some(name) => name.parse().unwrap_or_else(|_| {
+            tracing::warn!("Unknown timezone {:?}, using {}", name, DEFAULT_TIMEZONE);
+            DEFAULT_TIMEZONE
+        }),
+        None => DEFAULT_TIMEZONE,
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct LoginParams {
     pub email: String,
@@ -92,8 +108,32 @@ impl Model {
         user.ok_or_else(|| ModelError::EntityNotFound)
     }
 
+    /// the user's IANA timezone, used to evaluate medication schedules
+    #[must_use]
+    pub fn tz(&self) -> Tz {
+        parse_timezone(self.timezone.as_deref())
+    }
+
+    /// finds a user by the linked LINE user id
+    ///
+    /// # Errors
+    ///
+    /// When could not find user by the given LINE user id or DB query error
+    pub async fn find_by_line_user_id(
+        db: &DatabaseConnection,
+        line_user_id: &str,
+    ) -> ModelResult<Self> {
+        let user = users::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(users::Column::LineUserId, line_user_id)
+                    .build(),
+            )
+            .one(db)
+            .await?;
+        user.ok_or_else(|| ModelError::EntityNotFound)
+    }
 
-    
     /// finds a user by the provided reset token
     ///
     /// # Errors
@@ -0,0 +1,54 @@
+use async_trait::async_trait;
+use loco_rs::prelude::*;
+use serde_json::{json, Value};
+
+use super::{ChannelError, Delivery, NotificationChannel, NotificationChannelKind, SendResult};
+use crate::{mailers::notification::NotificationMailer, workers::notification_worker::NotificationWorkerArgs};
+
+/// 登録メールアドレスに送る経路
+pub struct EmailChannel {
+    pub email: String,
+    pub name: String,
+}
+
+/// 通知の種類ごとの件名
+fn subject(notification_type: &str) -> &'static str {
+    match notification_type {
+        "medication_reminder" => "🔔 服薬時間です",
+        "missed_medication" => "⚠️ 服薬忘れ",
+        "course_finished" => "🎉 服薬コースが終了しました",
+        "prn_warning" => "⚠️ 頓服薬の服用についての注意",
+        "refill_reminder" => "📦 お薬の残りが少なくなっています",
+        "medication_report" => "📊 服薬レポート",
+        _ => "お薬のお知らせ",
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for EmailChannel {
+    fn kind(&self) -> NotificationChannelKind {
+        NotificationChannelKind::Email
+    }
+
+    fn recipient(&self) -> &str {
+        &self.email
+    }
+
+    /// メールテンプレートに渡す値
+    fn payload(&self, args: &NotificationWorkerArgs) -> Value {
+        json!({
+            "to": self.email,
+            "name": self.name,
+            "subject": subject(&args.notification_type),
+            "message": args.message,
+        })
+    }
+
+    async fn send(&self, ctx: &AppContext, payload: &Value) -> SendResult<Delivery> {
+        // キューやメールサーバーの一時的な失敗として再送の対象にする
+        NotificationMailer::send_notification(ctx, &self.email, payload)
+            .await
+            .map_err(|e| ChannelError::retryable(e.to_string()))?;
+        Ok(Delivery::Sent { request_id: None })
+    }
+}
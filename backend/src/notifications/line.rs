@@ -0,0 +1,250 @@
+use async_trait::async_trait;
+use loco_rs::prelude::*;
+use serde_json::{json, Value};
+
+use reqwest::header::RETRY_AFTER;
+
+use super::{ChannelError, Delivery, NotificationChannel, NotificationChannelKind, SendResult};
+use crate::{
+    common::settings::Settings,
+    line::{
+        command::Command,
+        flex::{Action, Align, Bubble, Button, ButtonStyle, Component, FlexBox, FlexMessage, Separator, Size, Text},
+        postback::PostbackData,
+    },
+    workers::{notification_worker::NotificationWorkerArgs, report_generator::ReportDigest},
+};
+
+const PUSH_PATH: &str = "/v2/bot/message/push";
+
+/// LINEのPush APIで送る経路
+pub struct LineChannel {
+    pub line_user_id: String,
+}
+
+#[async_trait]
+impl NotificationChannel for LineChannel {
+    fn kind(&self) -> NotificationChannelKind {
+        NotificationChannelKind::Line
+    }
+
+    fn recipient(&self) -> &str {
+        &self.line_user_id
+    }
+
+    /// Push APIに送るリクエストボディを作成
+    fn payload(&self, args: &NotificationWorkerArgs) -> Value {
+        // メッセージタイプに応じてLINE Flexメッセージまたは通常テキストを使い分け
+        let flex = match (args.notification_type.as_str(), &args.report) {
+            ("medication_reminder", _) => Some(self.medication_reminder_message(args)),
+            ("missed_medication", _) => Some(self.missed_medication_message(args)),
+            ("medication_report", Some(report)) => Some(self.medication_report_message(report)),
+            _ => None,
+        };
+        let message_payload = match flex {
+            Some(flex) => json!(flex),
+            None => json!({
+                "type": "text",
+                "text": args.message
+            }),
+        };
+
+        json!({
+            "to": self.line_user_id,
+            "messages": [message_payload]
+        })
+    }
+
+    /// LINE Bot APIに通知を送信
+    async fn send(&self, ctx: &AppContext, payload: &Value) -> SendResult<Delivery> {
+        let line = Settings::from_config(&ctx.config)
+            .map_err(|e| ChannelError::permanent(format!("invalid LINE settings: {e}")))?
+            .line;
+        let Some(channel_access_token) = line.access_token().map_err(ChannelError::permanent)? else {
+            tracing::warn!("LINE channel access token is not properly configured. Skipping notification.");
+            return Ok(Delivery::Skipped);
+        };
+
+        let response = line
+            .http_client()?
+            .post(line.endpoint(PUSH_PATH))
+            .header("Authorization", format!("Bearer {}", channel_access_token))
+            .header("Content-Type", "application/json")
+            .json(payload)
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            tracing::debug!("LINE API response: {}", response.status());
+            let request_id = response
+                .headers()
+                .get("x-line-request-id")
+                .and_then(|value| value.to_str().ok())
+                .map(ToString::to_string);
+            Ok(Delivery::Sent { request_id })
+        } else {
+            let status = response.status();
+            let retry_after = response.headers().get(RETRY_AFTER).cloned();
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            tracing::error!("LINE API error: {} - {}", status, error_text);
+            Err(ChannelError::from_status(
+                status,
+                retry_after.as_ref(),
+                format!("LINE API error: {} - {}", status, error_text),
+            ))
+        }
+    }
+}
+
+impl LineChannel {
+    /// Flexメッセージのボタンアクションを作成
+    ///
+    /// 対象ログが分かる場合はログIDを埋め込んだポストバックにし、複数のリマインダーが
+    /// 未記録でもどの服薬への操作かを特定できるようにする。分からない場合は
+    /// コマンドのテキストを送信するメッセージアクションにする。
+    fn create_action(
+        args: &NotificationWorkerArgs,
+        label: &str,
+        command: Command,
+    ) -> Action {
+        let text = match command {
+            Command::Complete => "服薬完了",
+            Command::Snooze => "後で通知",
+            Command::Missed => "飲み忘れ",
+            Command::Skip => "スキップ",
+        };
+
+        match args.log_id {
+            Some(log_id) => Action::Postback {
+                label: label.to_string(),
+                data: PostbackData {
+                    command,
+                    log_id,
+                    medicine_id: args.medicine_id,
+                }
+                .encode(),
+                display_text: Some(text.to_string()),
+            },
+            None => Action::Message {
+                label: label.to_string(),
+                text: text.to_string(),
+            },
+        }
+    }
+
+    /// 見出しと本文、ボタンからなる通知のバブルを作成
+    fn notification_bubble(
+        title: &str,
+        color: &str,
+        background_color: &str,
+        message: &str,
+        buttons: Vec<Button>,
+    ) -> Bubble {
+        Bubble::new()
+            .header(
+                FlexBox::vertical(vec![Text::new(title).bold().size(Size::Lg).color(color).into()])
+                    .background_color(background_color),
+            )
+            .body(FlexBox::vertical(vec![Text::new(message).wrap().size(Size::Md).into()]))
+            .footer(
+                FlexBox::vertical(buttons.into_iter().map(Component::from).collect())
+                    .spacing(Size::Sm),
+            )
+    }
+
+    /// 服薬リマインダー用のFlexメッセージを作成
+    #[must_use]
+    pub fn medication_reminder_message(&self, args: &NotificationWorkerArgs) -> FlexMessage {
+        FlexMessage::new(
+            "服薬リマインダー",
+            Self::notification_bubble(
+                "🔔 服薬時間です",
+                "#2E86AB",
+                "#F3F7FA",
+                &args.message,
+                vec![
+                    Button::new(Self::create_action(args, "服薬完了", Command::Complete))
+                        .style(ButtonStyle::Primary)
+                        .color("#28a745"),
+                    Button::new(Self::create_action(args, "後で通知", Command::Snooze))
+                        .style(ButtonStyle::Secondary),
+                ],
+            ),
+        )
+    }
+
+    /// 未服薬警告用のFlexメッセージを作成
+    #[must_use]
+    pub fn missed_medication_message(&self, args: &NotificationWorkerArgs) -> FlexMessage {
+        FlexMessage::new(
+            "服薬忘れ通知",
+            Self::notification_bubble(
+                "⚠️ 服薬忘れ",
+                "#DC3545",
+                "#FDF2F2",
+                &args.message,
+                vec![
+                    Button::new(Self::create_action(args, "今から飲む", Command::Complete))
+                        .style(ButtonStyle::Primary)
+                        .color("#28a745"),
+                    Button::new(Self::create_action(args, "飲み忘れ記録", Command::Missed))
+                        .style(ButtonStyle::Secondary)
+                        .color("#6c757d"),
+                ],
+            ),
+        )
+    }
+
+    /// 服薬レポートの要約用のFlexメッセージを作成
+    #[must_use]
+    pub fn medication_report_message(&self, report: &ReportDigest) -> FlexMessage {
+        let title = format!("{} 服薬レポート（{}）", report.adherence_emoji(), report.report_type);
+        let row = |label: &str, value: String| -> Component {
+            FlexBox::horizontal(vec![
+                Text::new(label).size(Size::Sm).color("#555555").into(),
+                Text::new(value).size(Size::Sm).bold().align(Align::End).into(),
+            ])
+            .into()
+        };
+
+        let mut body = vec![
+            Text::new(format!("📊 期間: {}", report.period))
+                .size(Size::Sm)
+                .color("#888888")
+                .wrap()
+                .into(),
+            Separator::new().into(),
+            row("予定回数", format!("{}回", report.summary.total_scheduled)),
+            row("服薬完了", format!("{}回", report.summary.total_taken)),
+            row("飲み忘れ", format!("{}回", report.summary.total_missed)),
+            row("遵守率", format!("{:.1}%", report.summary.adherence_rate)),
+        ];
+        let notes: Vec<String> = [
+            report.recommendation.as_ref().map(|text| format!("💡 {text}")),
+            report.summary.best_adherence_medicine.as_ref().map(|name| format!("⭐ 最も良好: {name}")),
+            report.summary.worst_adherence_medicine.as_ref().map(|name| format!("🔸 要注意: {name}")),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if !notes.is_empty() {
+            body.push(Separator::new().into());
+            body.extend(notes.into_iter().map(|note| Text::new(note).size(Size::Sm).wrap().into()));
+        }
+
+        FlexMessage::new(
+            title.clone(),
+            Bubble::new()
+                .header(
+                    FlexBox::vertical(vec![Text::new(title).bold().size(Size::Lg).color("#2E86AB").into()])
+                        .background_color("#F3F7FA"),
+                )
+                .body(FlexBox::vertical(body).spacing(Size::Sm))
+                .footer(FlexBox::vertical(vec![Text::new("詳細はWebアプリでご確認ください。")
+                    .size(Size::Xs)
+                    .color("#888888")
+                    .wrap()
+                    .into()])),
+        )
+    }
+}
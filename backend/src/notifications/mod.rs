@@ -0,0 +1,197 @@
+//! 通知の送信経路
+//!
+//! LINE・メール・Webhookを [`NotificationChannel`] として同じように扱う。
+//! ユーザーは使う経路とその優先順を選び、通知ワーカーは
+//! [`NotificationStrategy`] に従って順に試すか、すべての経路に送る。
+
+mod email;
+mod line;
+mod retry;
+mod webhook;
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use loco_rs::prelude::*;
+use reqwest::{header::HeaderValue, StatusCode};
+use sea_orm::ActiveEnum;
+use serde_json::Value;
+
+pub use email::EmailChannel;
+pub use line::LineChannel;
+pub use retry::RetryPolicy;
+pub use webhook::{is_forbidden_ip, resolve_url, WebhookChannel, WebhookUrlError};
+
+pub use crate::models::_entities::sea_orm_active_enums::{
+    NotificationChannelKind, NotificationStrategy,
+};
+use crate::{models::users, workers::notification_worker::NotificationWorkerArgs};
+
+pub type SendResult<T> = Result<T, ChannelError>;
+
+/// 送信の失敗
+///
+/// 時間をおけば届く見込みがあるものだけを再送の対象にする。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChannelError {
+    /// 429・5xx・通信エラーなど、再送すれば届く見込みがある
+    Retryable {
+        message: String,
+        /// `Retry-After` で指定された待ち時間
+        retry_after: Option<Duration>,
+    },
+    /// 4xxや設定の不備など、再送しても届かない
+    Permanent(String),
+}
+
+impl ChannelError {
+    #[must_use]
+    pub fn retryable(message: impl Into<String>) -> Self {
+        Self::Retryable {
+            message: message.into(),
+            retry_after: None,
+        }
+    }
+
+    #[must_use]
+    pub fn permanent(message: impl Into<String>) -> Self {
+        Self::Permanent(message.into())
+    }
+
+    /// HTTPのエラー応答を分類する（429と5xxは再送する）
+    #[must_use]
+    pub fn from_status(status: StatusCode, retry_after: Option<&HeaderValue>, message: String) -> Self {
+        if status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+            Self::Retryable {
+                message,
+                retry_after: retry_after.and_then(parse_retry_after),
+            }
+        } else {
+            Self::Permanent(message)
+        }
+    }
+
+    #[must_use]
+    pub const fn is_retryable(&self) -> bool {
+        matches!(self, Self::Retryable { .. })
+    }
+}
+
+impl std::fmt::Display for ChannelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Retryable { message, .. } | Self::Permanent(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for ChannelError {}
+
+impl From<reqwest::Error> for ChannelError {
+    /// タイムアウトや接続の失敗は再送する
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_timeout() || err.is_connect() || err.is_request() {
+            Self::retryable(err.to_string())
+        } else {
+            Self::permanent(err.to_string())
+        }
+    }
+}
+
+/// `Retry-After` ヘッダー（秒数またはHTTP日付）を待ち時間にする
+#[must_use]
+pub fn parse_retry_after(value: &HeaderValue) -> Option<Duration> {
+    let value = value.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some((at.with_timezone(&chrono::Utc) - chrono::Utc::now()).to_std().unwrap_or_default())
+}
+
+/// 経路ごとの送信結果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Delivery {
+    /// 送信した（LINEの場合は `x-line-request-id` があればその値）
+    Sent { request_id: Option<String> },
+    /// 経路が設定されていないため送信しなかった
+    Skipped,
+}
+
+/// 通知の送信経路
+#[async_trait]
+pub trait NotificationChannel: Send + Sync {
+    fn kind(&self) -> NotificationChannelKind;
+
+    /// 送信先（LINE User ID・メールアドレス・URL）
+    fn recipient(&self) -> &str;
+
+    /// 送信する内容（通知履歴にはこのハッシュを残す）
+    fn payload(&self, args: &NotificationWorkerArgs) -> Value;
+
+    /// [`NotificationChannel::payload`] で作った内容を送信する
+    async fn send(&self, ctx: &AppContext, payload: &Value) -> SendResult<Delivery>;
+}
+
+impl NotificationChannelKind {
+    /// ログやエラーメッセージでの表示名
+    #[must_use]
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Line => "LINE",
+            Self::Email => "email",
+            Self::Webhook => "webhook",
+        }
+    }
+}
+
+/// `users.notification_channels` の保存値を優先順の経路に変換する
+///
+/// 不明な名前や重複は無視する。
+#[must_use]
+pub fn parse_channels(value: &str) -> Vec<NotificationChannelKind> {
+    let mut channels = Vec::new();
+    for name in value.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+        match NotificationChannelKind::try_from_value(&name.to_string()) {
+            Ok(kind) if !channels.contains(&kind) => channels.push(kind),
+            Ok(_) => {}
+            Err(_) => tracing::warn!("Unknown notification channel {:?}, ignoring", name),
+        }
+    }
+    channels
+}
+
+/// 経路を `users.notification_channels` の保存値にする
+#[must_use]
+pub fn format_channels(channels: &[NotificationChannelKind]) -> String {
+    channels
+        .iter()
+        .map(|kind| kind.to_value())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// ユーザーが選んだ経路を優先順に作る
+///
+/// 送信先が設定されていない経路（LINE未連携、Webhook URL未設定）は除く。
+#[must_use]
+pub fn channels_for(user: &users::Model) -> Vec<Box<dyn NotificationChannel>> {
+    parse_channels(&user.notification_channels)
+        .into_iter()
+        .filter_map(|kind| -> Option<Box<dyn NotificationChannel>> {
+            match kind {
+                NotificationChannelKind::Line => user.line_user_id.clone().map(|line_user_id| {
+                    Box::new(LineChannel { line_user_id }) as Box<dyn NotificationChannel>
+                }),
+                NotificationChannelKind::Email => Some(Box::new(EmailChannel {
+                    email: user.email.clone(),
+                    name: user.name.clone(),
+                })),
+                NotificationChannelKind::Webhook => user
+                    .webhook_url
+                    .clone()
+                    .map(|url| Box::new(WebhookChannel { url }) as Box<dyn NotificationChannel>),
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,67 @@
+use std::time::Duration;
+
+use super::ChannelError;
+use crate::common::settings::NotificationRetrySettings;
+
+/// 再送の間隔と回数
+///
+/// 再送はワーカーの中で待ってから行うので、待ち時間の合計を `max_total_delay` までに抑える。
+/// それより長く待つ必要がある場合は諦め、届かなかった通知として保存して後から再送する。
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// 1つの経路に送る最大回数（初回を含む）
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// 1つの経路で待つ時間の合計の上限
+    pub max_total_delay: Duration,
+}
+
+impl From<&NotificationRetrySettings> for RetryPolicy {
+    fn from(settings: &NotificationRetrySettings) -> Self {
+        Self {
+            max_attempts: settings.max_attempts.max(1),
+            base_delay: Duration::from_millis(settings.base_delay_ms),
+            max_delay: Duration::from_millis(settings.max_delay_ms),
+            max_total_delay: Duration::from_millis(settings.max_total_delay_ms),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// `attempt` 回目（1始まり）の送信が `error` で失敗した後、次を送るまで待つ時間
+    ///
+    /// `waited` はそれまでに待った時間の合計。再送しない場合（再送の対象外、回数の上限、
+    /// `Retry-After` が上限を超える、待ち時間の合計が上限を超える）は `None`。
+    #[must_use]
+    pub fn delay_after(
+        &self,
+        attempt: u32,
+        waited: Duration,
+        error: &ChannelError,
+    ) -> Option<Duration> {
+        let ChannelError::Retryable { retry_after, .. } = error else {
+            return None;
+        };
+        if attempt >= self.max_attempts {
+            return None;
+        }
+        let delay = match retry_after {
+            Some(retry_after) if *retry_after > self.max_delay => return None,
+            Some(retry_after) => *retry_after,
+            None => self.backoff(attempt),
+        };
+        (waited + delay <= self.max_total_delay).then_some(delay)
+    }
+
+    /// 倍々に増やした待ち時間の後半からランダムに選ぶ（同時に失敗した通知が一斉に再送しないように）
+    #[must_use]
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .base_delay
+            .saturating_mul(2_u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_delay);
+        let half = exponential / 2;
+        half + half.mul_f64(fastrand::f64())
+    }
+}
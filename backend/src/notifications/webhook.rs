@@ -0,0 +1,194 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use loco_rs::prelude::*;
+use serde_json::{json, Value};
+
+use reqwest::{header::RETRY_AFTER, redirect, Url};
+
+use super::{ChannelError, Delivery, NotificationChannel, NotificationChannelKind, SendResult};
+use crate::{common::settings::Settings, workers::notification_worker::NotificationWorkerArgs};
+
+/// 応答を待つ最大時間
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// ユーザーが登録したURLにJSONをPOSTする経路
+pub struct WebhookChannel {
+    pub url: String,
+}
+
+#[async_trait]
+impl NotificationChannel for WebhookChannel {
+    fn kind(&self) -> NotificationChannelKind {
+        NotificationChannelKind::Webhook
+    }
+
+    fn recipient(&self) -> &str {
+        &self.url
+    }
+
+    fn payload(&self, args: &NotificationWorkerArgs) -> Value {
+        json!({
+            "type": args.notification_type,
+            "message": args.message,
+            "medicine_id": args.medicine_id,
+            "log_id": args.log_id,
+        })
+    }
+
+    /// 2xx以外の応答は失敗として扱う（429と5xxは再送の対象）
+    ///
+    /// 送信先は送る直前に検証し、検証した通りのアドレスに接続する。リダイレクトには従わない。
+    async fn send(&self, ctx: &AppContext, payload: &Value) -> SendResult<Delivery> {
+        let allow_private_hosts = Settings::from_config(&ctx.config)
+            .map_err(|e| ChannelError::permanent(format!("invalid webhook settings: {e}")))?
+            .webhook
+            .allow_private_hosts;
+        let (url, addrs) = resolve_url(&self.url, allow_private_hosts)
+            .await
+            .map_err(|e| match e {
+                WebhookUrlError::Unresolvable(message) => ChannelError::retryable(message),
+                e => ChannelError::permanent(e.to_string()),
+            })?;
+
+        let mut client = reqwest::Client::builder().redirect(redirect::Policy::none());
+        if let Some(domain) = url.domain() {
+            client = client.resolve_to_addrs(domain, &addrs);
+        }
+        let response = client
+            .build()?
+            .post(url)
+            .timeout(TIMEOUT)
+            .json(payload)
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            tracing::debug!("Webhook response: {}", response.status());
+            Ok(Delivery::Sent { request_id: None })
+        } else {
+            let status = response.status();
+            let retry_after = response.headers().get(RETRY_AFTER).cloned();
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            tracing::error!("Webhook error: {} - {}", status, error_text);
+            Err(ChannelError::from_status(
+                status,
+                retry_after.as_ref(),
+                format!("Webhook error: {} - {}", status, error_text),
+            ))
+        }
+    }
+}
+
+/// WebhookのURLを使えない理由
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WebhookUrlError {
+    /// http(s)のURLではない
+    Invalid(String),
+    /// 内部のアドレスを指している
+    Forbidden(String),
+    /// 名前解決できない（時間をおけば解決できることもある）
+    Unresolvable(String),
+}
+
+impl std::fmt::Display for WebhookUrlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Invalid(message) | Self::Forbidden(message) | Self::Unresolvable(message) => {
+                f.write_str(message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for WebhookUrlError {}
+
+/// WebhookのURLを検証し、接続するアドレスを返す
+///
+/// ホスト名は名前解決し、`allow_private_hosts` でなければ内部のアドレス
+/// （[`is_forbidden_ip`]）が1つでも含まれる場合は拒否する。
+///
+/// # Errors
+///
+/// http(s)のURLでない場合、内部のアドレスを指す場合、または名前解決できない場合
+pub async fn resolve_url(
+    url: &str,
+    allow_private_hosts: bool,
+) -> Result<(Url, Vec<SocketAddr>), WebhookUrlError> {
+    let invalid = || WebhookUrlError::Invalid("webhook_url must be an http(s) URL".to_string());
+    let url = Url::parse(url).map_err(|_| invalid())?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(invalid());
+    }
+    let host = url.host_str().ok_or_else(invalid)?;
+    let port = url.port_or_known_default().ok_or_else(invalid)?;
+    let forbidden =
+        || WebhookUrlError::Forbidden(format!("webhook_url must not point to an internal host ({host})"));
+
+    let addrs: Vec<SocketAddr> = match url.domain() {
+        Some(domain) => {
+            if !allow_private_hosts
+                && (domain.eq_ignore_ascii_case("localhost") || domain.to_ascii_lowercase().ends_with(".localhost"))
+            {
+                return Err(forbidden());
+            }
+            tokio::net::lookup_host((domain, port))
+                .await
+                .map_err(|e| WebhookUrlError::Unresolvable(format!("could not resolve {domain}: {e}")))?
+                .collect()
+        }
+        // IPアドレスのホスト（IPv6は角括弧を外す）
+        None => {
+            let ip: IpAddr = host
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .parse()
+                .map_err(|_| invalid())?;
+            vec![SocketAddr::new(ip, port)]
+        }
+    };
+    if addrs.is_empty() {
+        return Err(WebhookUrlError::Unresolvable(format!("could not resolve {host}")));
+    }
+    if !allow_private_hosts && addrs.iter().any(|addr| is_forbidden_ip(addr.ip())) {
+        return Err(forbidden());
+    }
+    Ok((url, addrs))
+}
+
+/// ユーザーが指定した送信先として許可しないアドレスか
+///
+/// ループバック・プライベート・リンクローカル・未指定・CGNAT・ブロードキャスト・マルチキャスト
+/// （IPv4射影アドレスは射影元で判定する）。
+#[must_use]
+pub fn is_forbidden_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_forbidden_ipv4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_forbidden_ipv4(ip),
+            None => {
+                ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    || ip.is_unique_local()
+                    || ip.is_unicast_link_local()
+            }
+        },
+    }
+}
+
+fn is_forbidden_ipv4(ip: Ipv4Addr) -> bool {
+    let [first, second, ..] = ip.octets();
+    ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        // 0.0.0.0/8 と共有アドレス空間（100.64.0.0/10）
+        || first == 0
+        || (first == 100 && second & 0xc0 == 64)
+}
@@ -1,17 +1,33 @@
 use loco_rs::prelude::*;
 use loco_rs::task::{Task, TaskInfo};
-use sea_orm::{DatabaseConnection, EntityTrait, ColumnTrait, QueryFilter, DbErr, Set};
+use sea_orm::{DatabaseConnection, EntityTrait, ColumnTrait, QueryFilter, QuerySelect, DbErr, Set, TryInsertResult};
+use sea_orm::sea_query::{Expr, ExprTrait, OnConflict, Query, SelectStatement};
 use sea_orm::Condition;
-use chrono::{Local, Weekday, Duration, NaiveDateTime, NaiveTime, Timelike, Datelike};
+use chrono::{DateTime, Local, LocalResult, NaiveDate, NaiveDateTime, Utc, Duration, NaiveTime, Timelike, Datelike};
+use std::collections::BTreeMap;
 use chrono::TimeZone;
+use chrono_tz::Tz;
 
 use crate::models::{
     _entities::{medicines, medication_schedules, medication_logs, users},
     medicines::Model as Medicine,
-    medication_schedules::Model as MedicationSchedule,
-    medication_logs::Model as MedicationLog,
+    medication_schedules::{weekday_bit, ActiveModel as MedicationScheduleActiveModel, Model as MedicationSchedule},
+    medication_logs::{MedicationStatus, Model as MedicationLog},
+    scheduler_states,
+    users::parse_timezone,
 };
-use crate::workers::notification_worker::NotificationWorkerArgs;
+use crate::common::settings::Settings;
+use crate::workers::notification_worker::{NotificationWorker, NotificationWorkerArgs};
+
+/// 処理済み時刻（ウォーターマーク）の名前
+const WATERMARK_NAME: &str = "medication_reminder";
+
+/// 補充確認の処理済み時刻の名前（タイムゾーンごとに `refill_check:<タイムゾーン>`）
+const REFILL_WATERMARK_PREFIX: &str = "refill_check:";
+/// 停止していた場合にさかのぼって処理する最大時間
+const MAX_CATCH_UP_HOURS: i64 = 24;
+/// 予定時刻（スヌーズ時は再通知時刻）から未服薬とするまでの時間（分）
+const MISSED_AFTER_MINUTES: i64 = 30;
 
 pub struct MedicationReminderTask;
 
@@ -20,88 +36,518 @@ impl Task for MedicationReminderTask {
     fn task(&self) -> TaskInfo {
         TaskInfo {
             name: "medication_reminder".to_string(),
-            detail: "Checks and sends medication reminders every hour".to_string(),
+            detail: "Sends medication reminders due since the last run".to_string(),
         }
     }
 
     async fn run(&self, app_context: &AppContext, _vars: &task::Vars) -> Result<(), Error> {
         tracing::info!("🔔 Starting medication reminder task");
-        
-        let now = Local::now();
-        let current_time = now.time();
-        let current_weekday = now.weekday();
-        
-        // 現在時刻（分単位で丸める）
-        let current_hour_minute = NaiveTime::from_hms_opt(current_time.hour(), current_time.minute(), 0)
-            .unwrap_or_else(|| {
-                tracing::error!("Failed to create NaiveTime from current time");
-                NaiveTime::from_hms_opt(9, 0, 0).unwrap()
-            });
-        
-        tracing::info!("Checking schedules for time: {}", current_hour_minute.format("%H:%M"));
-
-        // アクティブなスケジュールを取得
-        let schedules = self.get_active_schedules_for_time(
-            &app_context.db, 
-            current_hour_minute, 
-            current_weekday
-        ).await?;
-
-        tracing::info!("Found {} schedules to process", schedules.len());
+        self.tick(app_context, Utc::now()).await?;
+        tracing::info!("✅ Medication reminder task completed");
+        Ok(())
+    }
+}
 
-        for schedule in schedules {
-            if let Err(e) = self.process_medication_schedule(app_context, &schedule, now.naive_local()).await {
-                tracing::error!("Failed to process schedule ID {}: {}", schedule.id, e);
+impl MedicationReminderTask {
+    /// 前回処理した時刻から `now` までに予定時刻を迎えた服薬を処理する
+    ///
+    /// 処理済み時刻はDBに保存し、すべて成功した場合だけ進める。再起動や実行の
+    /// 遅れがあっても取りこぼさず、同じ服薬のログは作成済みなら重複しない。
+    pub async fn tick(&self, app_context: &AppContext, now: DateTime<Utc>) -> Result<(), Error> {
+        let until = Self::truncate_to_minute(now);
+        let oldest = until - Duration::hours(MAX_CATCH_UP_HOURS);
+
+        let since = match scheduler_states::Model::last_run_at(&app_context.db, WATERMARK_NAME).await? {
+            Some(last_run_at) if last_run_at < oldest => {
+                tracing::warn!("Reminder scheduler was stopped since {}, catching up from {}", last_run_at, oldest);
+                oldest
+            }
+            Some(last_run_at) => last_run_at.with_timezone(&Utc),
+            // 初回は現在の分だけを処理する
+            None => until - Duration::minutes(1),
+        };
+
+        if since >= until {
+            tracing::debug!("Reminders up to {} are already processed", until);
+            return Ok(());
+        }
+
+        self.process_window(app_context, since, until).await?;
+        scheduler_states::ActiveModel::advance(&app_context.db, WATERMARK_NAME, until.into()).await?;
+        Ok(())
+    }
+
+    /// `since` より後、`until` 以前に予定時刻を迎えた服薬を処理する
+    ///
+    /// 途中で失敗しても残りの処理は続け、最後のエラーを返す。
+    pub async fn process_window(
+        &self,
+        app_context: &AppContext,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Result<(), Error> {
+        let mut result = Ok(());
+
+        // スケジュールは各ユーザーのタイムゾーンで評価する
+        for (tz, timezone_names) in self.user_timezones(&app_context.db).await? {
+            if let Err(e) = self.process_due_schedules(app_context, tz, &timezone_names, since, until).await {
+                tracing::error!("Failed to process schedules for timezone {}: {}", tz, e);
+                result = Err(e);
             }
         }
 
+        // スヌーズされた服薬の再通知
+        if let Err(e) = self.process_snoozed_medications(app_context, until).await {
+            tracing::error!("Failed to process snoozed medications: {}", e);
+            result = Err(e);
+        }
+
         // 未服薬チェック（30分後）
-        if let Err(e) = self.check_missed_medications(app_context).await {
+        if let Err(e) = self.check_missed_medications(app_context, since, until).await {
             tracing::error!("Failed to check missed medications: {}", e);
+            result = Err(e);
         }
 
-        tracing::info!("✅ Medication reminder task completed");
+        // 在庫が少なくなった薬の補充通知
+        if let Err(e) = self.check_refills(app_context, until).await {
+            tracing::error!("Failed to check refills: {}", e);
+            result = Err(e);
+        }
+
+        result
+    }
+
+    /// 在庫の残り日数が設定値を下回った薬について、補充を通知する
+    ///
+    /// 確認はユーザーの現地の日付ごとに1度だけ行う（タイムゾーンごとの処理済み時刻で判定）。
+    /// 通知は補充されるまで1度だけ行う（`refill_alerted_at` で判定）。
+    pub async fn check_refills(&self, app_context: &AppContext, now: DateTime<Utc>) -> Result<(), Error> {
+        let alert_days = Settings::from_config(&app_context.config)?.refill.alert_days;
+
+        let mut result = Ok(());
+        for (tz, timezone_names) in self.user_timezones(&app_context.db).await? {
+            let watermark = format!("{REFILL_WATERMARK_PREFIX}{}", tz.name());
+            let today = now.with_timezone(&tz).date_naive();
+            let last_run_at = scheduler_states::Model::last_run_at(&app_context.db, &watermark).await?;
+            if last_run_at.is_some_and(|last_run_at| last_run_at.with_timezone(&tz).date_naive() >= today) {
+                continue;
+            }
+
+            // 失敗した薬があっても、同じ日のうちには確認し直さない
+            if let Err(e) = self
+                .check_refills_in_timezones(app_context, &timezone_names, alert_days, now)
+                .await
+            {
+                result = Err(e);
+            }
+            scheduler_states::ActiveModel::advance(&app_context.db, &watermark, now.into()).await?;
+        }
+        result
+    }
+
+    /// `timezone_names` のタイムゾーンのユーザーの薬について在庫を確認する
+    async fn check_refills_in_timezones(
+        &self,
+        app_context: &AppContext,
+        timezone_names: &[Option<String>],
+        alert_days: i64,
+        now: DateTime<Utc>,
+    ) -> Result<(), Error> {
+        let medicines = medicines::Entity::find()
+            .filter(medicines::Column::Id.in_subquery(Self::medicines_in_timezones(timezone_names)))
+            .filter(medicines::Column::StockQuantity.is_not_null())
+            .filter(medicines::Column::RefillAlertedAt.is_null())
+            .filter(
+                Condition::any()
+                    .add(medicines::Column::Active.is_null())
+                    .add(medicines::Column::Active.eq(true)),
+            )
+            .all(&app_context.db)
+            .await?;
+
+        let mut result = Ok(());
+        for medicine in medicines {
+            if let Err(e) = self.check_refill(app_context, medicine, alert_days, now).await {
+                tracing::error!("Failed to check refill: {}", e);
+                result = Err(e);
+            }
+        }
+        result
+    }
+
+    /// 薬1件の在庫を確認し、必要なら補充を通知する
+    async fn check_refill(
+        &self,
+        app_context: &AppContext,
+        medicine: Medicine,
+        alert_days: i64,
+        now: DateTime<Utc>,
+    ) -> Result<(), Error> {
+        let user = users::Entity::find_by_id(medicine.user_id)
+            .one(&app_context.db)
+            .await?
+            .ok_or_else(|| Error::string("User not found"))?;
+        let schedules = medication_schedules::Entity::find()
+            .filter(medication_schedules::Column::MedicineId.eq(medicine.id))
+            .filter(medication_schedules::Column::Active.eq(true))
+            .all(&app_context.db)
+            .await?;
+
+        let local_now = now.with_timezone(&user.tz()).naive_local();
+        let Some(days_remaining) = medicine
+            .stock_forecast(&schedules, local_now)
+            .and_then(|forecast| forecast.days_remaining)
+        else {
+            return Ok(());
+        };
+        if days_remaining >= alert_days {
+            return Ok(());
+        }
+
+        // 先に通知済みにして、通知に失敗しても繰り返し送らないようにする
+        let stock_quantity = medicine.stock_quantity.unwrap_or_default();
+        let message = format!(
+            r"💊 お薬の補充をお忘れなく
+
+{}の残りは約{}日分（{}{}）です。
+早めに処方・購入をご検討ください。",
+            medicine.name,
+            days_remaining,
+            stock_quantity.normalize(),
+            medicine.unit.as_deref().unwrap_or_default()
+        );
+        let medicine_id = medicine.id;
+        let mut medicine = medicine.into_active_model();
+        medicine.refill_alerted_at = Set(Some(now.into()));
+        medicine.update(&app_context.db).await?;
+        tracing::info!("📦 Refill needed for medicine {} ({} days left)", medicine_id, days_remaining);
+
+        let notification_args = NotificationWorkerArgs {
+            user_id: Some(user.id),
+            line_user_id: None,
+            message,
+            notification_type: "refill_reminder".to_string(),
+            medicine_id: Some(medicine_id),
+            log_id: None,
+            report: None,
+            dead_letter_id: None,
+        };
+        NotificationWorker::perform_later(app_context, notification_args).await?;
         Ok(())
     }
-}
 
-impl MedicationReminderTask {
+    /// ユーザーのタイムゾーンを取得し、同じタイムゾーンになる保存値ごとにまとめる
+    ///
+    /// 未設定（`NULL`）や不正な値は既定のタイムゾーンとして扱う。
+    async fn user_timezones(
+        &self,
+        db: &DatabaseConnection,
+    ) -> Result<Vec<(Tz, Vec<Option<String>>)>, DbErr> {
+        let names: Vec<Option<String>> = users::Entity::find()
+            .select_only()
+            .column(users::Column::Timezone)
+            .distinct()
+            .into_tuple()
+            .all(db)
+            .await?;
+
+        let mut groups: Vec<(Tz, Vec<Option<String>>)> = Vec::new();
+        for name in names {
+            let tz = parse_timezone(name.as_deref());
+            match groups.iter_mut().find(|(group_tz, _)| *group_tz == tz) {
+                Some((_, group_names)) => group_names.push(name),
+                None => groups.push((tz, vec![name])),
+            }
+        }
+        Ok(groups)
+    }
+
+    /// 指定タイムゾーンのユーザーについて、`since` より後、`until` 以前の各分に
+    /// 予定時刻を迎えたスケジュールを処理
+    ///
+    /// `timezone_names` はそのタイムゾーンとして扱う `users.timezone` の保存値。
+    pub async fn process_due_schedules(
+        &self,
+        app_context: &AppContext,
+        tz: Tz,
+        timezone_names: &[Option<String>],
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Result<(), Error> {
+        // 現地の日付ごとに、対象の時刻とそれを迎えた時点をまとめる
+        let mut due: BTreeMap<NaiveDate, BTreeMap<NaiveTime, DateTime<Tz>>> = BTreeMap::new();
+        let mut minute = Self::truncate_to_minute(since) + Duration::minutes(1);
+        while minute <= until {
+            let local = minute.with_timezone(&tz);
+            let times = due.entry(local.date_naive()).or_default();
+            for time in Self::due_local_times(tz, minute) {
+                // 夏時間の終了で2回ある時刻は1回目だけ
+                times.entry(time).or_insert(local);
+            }
+            minute += Duration::minutes(1);
+        }
+
+        for (date, times) in due {
+            tracing::info!(
+                "Checking schedules for {} {}-{} ({})",
+                date,
+                times.keys().next().map_or_else(String::new, |time| time.format("%H:%M").to_string()),
+                times.keys().last().map_or_else(String::new, |time| time.format("%H:%M").to_string()),
+                tz
+            );
+
+            // アクティブなスケジュールを取得
+            let schedules = self.get_active_schedules_for_time(
+                &app_context.db,
+                &times.keys().copied().collect::<Vec<_>>(),
+                date,
+                timezone_names,
+            ).await?;
+
+            tracing::info!("Found {} schedules to process", schedules.len());
+
+            let (Some(&first), Some(&last)) = (times.keys().next(), times.keys().last()) else {
+                continue;
+            };
+            for mut schedule in schedules {
+                // 間隔指定やRRULEのスケジュールは1日に複数回の服薬時刻がありうる
+                for occurrence in schedule.occurrences(date.and_time(first), date.and_time(last)) {
+                    let time = occurrence.time();
+                    let Some(&due_at) = times.get(&time) else {
+                        continue;
+                    };
+                    if schedule.doses_exhausted() {
+                        break;
+                    }
+                    match self
+                        .process_medication_occurrence(app_context, &schedule, time, due_at)
+                        .await
+                    {
+                        Ok(updated) => schedule = updated,
+                        Err(e) => {
+                            tracing::error!("Failed to process schedule ID {}: {}", schedule.id, e);
+                            // 服薬回数が記録済みのこともあるので読み直す
+                            if let Some(current) = medication_schedules::Entity::find_by_id(schedule.id)
+                                .one(&app_context.db)
+                                .await?
+                            {
+                                schedule = current;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        // 終了日を過ぎた服薬コースを終了する
+        let today = until.with_timezone(&tz).date_naive();
+        self.finish_ended_courses(app_context, timezone_names, today).await
+    }
+
+    /// 終了日が `today`（現地の日付）より前か、総服薬回数に達していて、
+    /// まだアクティブなスケジュールを終了する
+    ///
+    /// 途中で失敗しても残りの処理は続け、最後のエラーを返す。
+    pub async fn finish_ended_courses(
+        &self,
+        app_context: &AppContext,
+        timezone_names: &[Option<String>],
+        today: NaiveDate,
+    ) -> Result<(), Error> {
+        let schedules = medication_schedules::Entity::find()
+            .filter(medication_schedules::Column::Active.eq(true))
+            .filter(
+                Condition::any()
+                    .add(medication_schedules::Column::EndDate.lt(today))
+                    .add(
+                        Expr::col(medication_schedules::Column::DoseCount)
+                            .gte(Expr::col(medication_schedules::Column::MaxDoses)),
+                    ),
+            )
+            .filter(medication_schedules::Column::MedicineId.in_subquery(Self::medicines_in_timezones(timezone_names)))
+            .all(&app_context.db)
+            .await?;
+
+        let mut result = Ok(());
+        for schedule in schedules {
+            if let Err(e) = self.finish_course(app_context, schedule).await {
+                tracing::error!("Failed to finish course: {}", e);
+                result = Err(e);
+            }
+        }
+        result
+    }
+
+    /// 服薬コースを終了する
+    ///
+    /// スケジュールを非アクティブにしてから、ユーザーにコース終了を通知する。
+    async fn finish_course(&self, app_context: &AppContext, schedule: MedicationSchedule) -> Result<(), Error> {
+        let medicine = medicines::Entity::find_by_id(schedule.medicine_id)
+            .one(&app_context.db)
+            .await?
+            .ok_or_else(|| Error::string("Medicine not found"))?;
+        let user = users::Entity::find_by_id(medicine.user_id)
+            .one(&app_context.db)
+            .await?
+            .ok_or_else(|| Error::string("User not found"))?;
+
+        let dose_count = schedule.dose_count;
+        let mut schedule = schedule.into_active_model();
+        schedule.active = Set(Some(false));
+        let schedule = schedule.update(&app_context.db).await?;
+        tracing::info!("🏁 Finished course of schedule {} - medicine: {}", schedule.id, medicine.name);
+
+        let notification_args = NotificationWorkerArgs {
+            user_id: Some(user.id),
+            line_user_id: None,
+            message: format!(
+                r"🎉 服薬コースが終了しました
+
+💊 {}
+✅ {}回の服薬リマインダーをお送りしました
+
+お疲れさまでした！",
+                medicine.name, dose_count
+            ),
+            notification_type: "course_finished".to_string(),
+            medicine_id: Some(medicine.id),
+            log_id: None,
+            report: None,
+            dead_letter_id: None,
+        };
+
+        NotificationWorker::perform_later(app_context, notification_args).await?;
+        Ok(())
+    }
+
+    /// `timezone_names` のいずれかを `users.timezone` に持つユーザーの薬のIDを選ぶサブクエリ
+    fn medicines_in_timezones(timezone_names: &[Option<String>]) -> SelectStatement {
+        let timezone_condition = timezone_names.iter().fold(Condition::any(), |condition, name| match name {
+            Some(name) => condition.add(users::Column::Timezone.eq(name.as_str())),
+            None => condition.add(users::Column::Timezone.is_null()),
+        });
+
+        Query::select()
+            .column(medicines::Column::Id)
+            .from(medicines::Entity)
+            .and_where(
+                medicines::Column::UserId.in_subquery(
+                    Query::select()
+                        .column(users::Column::Id)
+                        .from(users::Entity)
+                        .cond_where(timezone_condition)
+                        .to_owned(),
+                ),
+            )
+            .to_owned()
+    }
+
+    /// 現在時刻に通知すべき現地の時刻（分単位）
+    ///
+    /// 通常は現在の分だけだが、夏時間の開始で時計が進んだ直後は、飛ばされて
+    /// 存在しなかった時刻もまとめて含める。時計が戻る場合は現在の分だけで、
+    /// 2回目の同じ時刻は作成済みのログにより重複しない。
+    #[must_use]
+    pub fn due_local_times(tz: Tz, now: DateTime<Utc>) -> Vec<NaiveTime> {
+        let current = Self::truncate_to_minute(now.with_timezone(&tz).naive_local().time());
+        let previous_local = (now - Duration::minutes(1)).with_timezone(&tz).naive_local();
+        let current_local = now.with_timezone(&tz).naive_local();
+        let skipped_minutes = (current_local - previous_local).num_minutes() - 1;
+
+        (0..=skipped_minutes.max(0))
+            .rev()
+            .map(|minutes| current - Duration::minutes(minutes))
+            .collect()
+    }
+
+    /// 秒以下を切り捨てる
+    fn truncate_to_minute<T: Timelike + Copy>(time: T) -> T {
+        time.with_second(0)
+            .and_then(|time| time.with_nanosecond(0))
+            .unwrap_or(time)
+    }
+
     /// 指定時刻のアクティブなスケジュールを取得
+    ///
+    /// `timezone_names` のいずれかを `users.timezone` に持つユーザーの薬に限る。
+    /// 間隔指定（`custom`）とRRULE（`rrule`）のスケジュールは起点の日付以降の
+    /// ものをすべて返すので、呼び出し側で `occurrences` により絞り込む。
+    ///
+    /// 時刻指定と間隔指定をORでまとめるとどちらのインデックスも使えないため、
+    /// `time_of_day` と `frequency` のインデックスでそれぞれ引いて結合する。
     async fn get_active_schedules_for_time(
         &self,
         db: &DatabaseConnection,
-        time: NaiveTime,
-        weekday: Weekday,
+        times: &[NaiveTime],
+        date: NaiveDate,
+        timezone_names: &[Option<String>],
     ) -> Result<Vec<MedicationSchedule>, DbErr> {
-        let weekday_num = self.weekday_to_number(weekday);
+        let active = || {
+            medication_schedules::Entity::find()
+                .filter(medication_schedules::Column::Active.eq(true))
+                .filter(
+                    Condition::any()
+                        .add(medication_schedules::Column::EndDate.is_null())
+                        .add(medication_schedules::Column::EndDate.gte(date)),
+                )
+                .filter(medication_schedules::Column::MedicineId.in_subquery(Self::medicines_in_timezones(timezone_names)))
+        };
 
-        medication_schedules::Entity::find()
-            .filter(medication_schedules::Column::Active.eq(true))
-            .filter(medication_schedules::Column::ScheduledTime.like(format!("% {}:%", time.format("%H:%M"))))
+        let at_times = active()
+            .filter(medication_schedules::Column::TimeOfDay.is_in(times.iter().copied()))
             .filter(
                 Condition::any()
                     .add(medication_schedules::Column::Frequency.eq("daily"))
                     .add(
-                        Condition::all()
-                            .add(medication_schedules::Column::Frequency.eq("weekly"))
-                            .add(
-                                Condition::any()
-                                    .add(medication_schedules::Column::DaysOfWeek.is_null())
-                                    .add(medication_schedules::Column::DaysOfWeek.like(format!("%{}%", weekday_num)))
-                            )
-                    )
+                        Expr::col((medication_schedules::Entity, medication_schedules::Column::Weekdays))
+                            .bit_and(weekday_bit(date.weekday()))
+                            .ne(0),
+                    ),
             )
             .all(db)
-            .await
+            .await?;
+
+        let recurring = active()
+            .filter(medication_schedules::Column::Frequency.is_in(["custom", "rrule"]))
+            .filter(medication_schedules::Column::StartDate.lte(date))
+            .all(db)
+            .await?;
+
+        // 両方に該当するスケジュールは1件にまとめる
+        let schedules: BTreeMap<i32, MedicationSchedule> = at_times
+            .into_iter()
+            .chain(recurring)
+            .map(|schedule| (schedule.id, schedule))
+            .collect();
+        Ok(schedules.into_values().collect())
     }
 
     /// 服薬スケジュールを処理
-    async fn process_medication_schedule(
+    ///
+    /// `current_time` はユーザーのタイムゾーンでの現在時刻。ログの予定時刻は
+    /// その日の現地時刻として、その時点のUTCオフセット付きで記録する。
+    pub async fn process_medication_schedule(
         &self,
         app_context: &AppContext,
         schedule: &MedicationSchedule,
-        current_time: NaiveDateTime,
+        current_time: DateTime<Tz>,
     ) -> Result<(), Error> {
+        self.process_medication_occurrence(app_context, schedule, schedule.time_of_day, current_time)
+            .await?;
+        Ok(())
+    }
+
+    /// スケジュールの `time`（現地時刻）の服薬を処理
+    ///
+    /// 服薬回数を反映したスケジュールを返す。総服薬回数に達した場合は
+    /// 服薬コースを終了する。
+    pub async fn process_medication_occurrence(
+        &self,
+        app_context: &AppContext,
+        schedule: &MedicationSchedule,
+        time: NaiveTime,
+        current_time: DateTime<Tz>,
+    ) -> Result<MedicationSchedule, Error> {
         // 薬情報を取得
         let medicine = medicines::Entity::find_by_id(schedule.medicine_id)
             .one(&app_context.db)
@@ -114,83 +560,173 @@ impl MedicationReminderTask {
             .await?
             .ok_or_else(|| Error::string("User not found"))?;
 
-        // LINE User IDが設定されているかチェック
-        let line_user_id = user.line_user_id
-            .as_ref()
-            .ok_or_else(|| Error::string("User has no LINE ID"))?;
-
-        // 今日の同じ時刻の服薬ログがあるかチェック
-        let today_start = current_time.date().and_hms_opt(0, 0, 0).unwrap();
-        let scheduled_time = self.combine_date_and_schedule_time(today_start, &schedule.scheduled_time)?;
-
-        // Convert NaiveDateTime to DateTime<FixedOffset>
-        let fixed_offset = chrono::FixedOffset::east_opt(9 * 3600).unwrap_or_else(|| chrono::FixedOffset::east_opt(0).unwrap()); // adjust offset as needed
-        let scheduled_time_fixed = fixed_offset.from_local_datetime(&scheduled_time).single()
-            .ok_or_else(|| Error::string("Failed to convert scheduled_time to DateTime<FixedOffset>"))?;
-
-        let existing_log = medication_logs::Entity::find()
-            .filter(medication_logs::Column::MedicineId.eq(schedule.medicine_id))
-            .filter(medication_logs::Column::ScheduledTime.eq(scheduled_time_fixed))
-            .one(&app_context.db)
-            .await?;
-
-        if existing_log.is_some() {
-            tracing::debug!("Log already exists for medicine {} at {}", medicine.name, scheduled_time_fixed);
-            return Ok(());
-        }
+        // 今日の同じ時刻の予定時刻
+        let scheduled_time_fixed = Self::resolve_local_time(
+            current_time.timezone(),
+            current_time.date_naive().and_time(time),
+        )
+        .fixed_offset();
 
-        // 服薬ログを作成
+        // 服薬ログを作成（同じ予定の行が既にあれば何もしない）
+        //
+        // 重なったティックが同時に処理しても、(medicine_id, scheduled_time) の
+        // 一意インデックスにより1行しか作られず、通知も挿入した側だけが送る。
         let log = medication_logs::ActiveModel {
             medicine_id: Set(schedule.medicine_id),
             scheduled_time: Set(scheduled_time_fixed),
-            status: Set("pending".to_string()),
+            status: Set(MedicationStatus::Pending),
             taken_time: Set(None),
             notes: Set(None),
             ..Default::default()
         };
 
-        medication_logs::Entity::insert(log)
+        let inserted = medication_logs::Entity::insert(log)
+            .on_conflict(
+                OnConflict::columns([
+                    medication_logs::Column::MedicineId,
+                    medication_logs::Column::ScheduledTime,
+                ])
+                .do_nothing()
+                .to_owned(),
+            )
+            .do_nothing()
             .exec(&app_context.db)
             .await?;
 
+        let TryInsertResult::Inserted(inserted) = inserted else {
+            tracing::debug!("Log already exists for medicine {} at {}", medicine.name, scheduled_time_fixed);
+            return Ok(schedule.clone());
+        };
+
+        let schedule = MedicationScheduleActiveModel::record_dose(&app_context.db, schedule.id).await?;
+
         // 通知メッセージを作成
-        let message = self.create_reminder_message(&medicine, &schedule);
+        let message = self.create_reminder_message(&medicine, time, &schedule);
 
         // 通知ワーカーをエンキュー
-        let _notification_args = NotificationWorkerArgs {
-            line_user_id: line_user_id.clone(),
+        let notification_args = NotificationWorkerArgs {
+            user_id: Some(user.id),
+            line_user_id: None,
             message,
             notification_type: "medication_reminder".to_string(),
             medicine_id: Some(medicine.id),
-            log_id: None,
+            log_id: Some(inserted.last_insert_id),
+            report: None,
+            dead_letter_id: None,
         };
 
-        // TODO: Fix queue access pattern for Loco 0.15
-        // The queue access needs to be implemented properly
-        // if let Some(queue) = &app_context.queue {
-        //     queue.enqueue(
-        //         crate::workers::notification_worker::NotificationWorker::build(app_context),
-        //         &notification_args,
-        //         None,
-        //     ).await?;
-        // }
-
-        tracing::info!("Queued reminder for user {} - medicine: {}", line_user_id, medicine.name);
+        let notified = NotificationWorker::perform_later(app_context, notification_args).await;
+        if notified.is_ok() {
+            tracing::info!("Queued reminder for user {} - medicine: {}", user.id, medicine.name);
+        }
+
+        // リマインダーを送れなくても、服薬回数に達したコースは終了させる
+        if schedule.doses_exhausted() {
+            self.finish_course(app_context, schedule.clone()).await?;
+        }
+        notified?;
+        Ok(schedule)
+    }
+
+    /// スヌーズの再通知時刻を過ぎた服薬を再通知する
+    ///
+    /// 再通知は1回のスヌーズにつき1度だけ行う（`renotified_at` で判定）。
+    /// `now` は処理する範囲の終わり（`process_window` の `until`）。
+    pub async fn process_snoozed_medications(&self, app_context: &AppContext, now: DateTime<Utc>) -> Result<(), Error> {
+        let snoozed_logs = medication_logs::Entity::find()
+            .filter(medication_logs::Column::Status.eq(MedicationStatus::Snoozed))
+            .filter(medication_logs::Column::SnoozedUntil.lte(now))
+            .filter(medication_logs::Column::RenotifiedAt.is_null())
+            .all(&app_context.db)
+            .await?;
+
+        for log in snoozed_logs {
+            if let Err(e) = self.process_snoozed_medication(app_context, &log).await {
+                tracing::error!("Failed to process snoozed medication for log {}: {}", log.id, e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// スヌーズされた服薬の再通知
+    async fn process_snoozed_medication(
+        &self,
+        app_context: &AppContext,
+        log: &MedicationLog,
+    ) -> Result<(), Error> {
+        // 再通知済みとして記録（次回実行で重複して送らないよう先に更新）
+        let mut log_active: medication_logs::ActiveModel = log.clone().into();
+        log_active.renotified_at = Set(Some(Local::now().into()));
+        log_active.update(&app_context.db).await?;
+
+        let medicine = medicines::Entity::find_by_id(log.medicine_id)
+            .one(&app_context.db)
+            .await?
+            .ok_or_else(|| Error::string("Medicine not found"))?;
+
+        let user = users::Entity::find_by_id(medicine.user_id)
+            .one(&app_context.db)
+            .await?
+            .ok_or_else(|| Error::string("User not found"))?;
+
+        let message = format!(
+            r"🔔 先ほどのお薬の時間です！
+
+💊 {}
+⏰ {}
+
+「服薬完了」と返信して記録してください。",
+            medicine.name,
+            log.scheduled_time.with_timezone(&user.tz()).format("%H:%M")
+        );
+
+        let notification_args = NotificationWorkerArgs {
+            user_id: Some(user.id),
+            line_user_id: None,
+            message,
+            notification_type: "medication_reminder".to_string(),
+            medicine_id: Some(medicine.id),
+            log_id: Some(log.id),
+            report: None,
+            dead_letter_id: None,
+        };
+        NotificationWorker::perform_later(app_context, notification_args).await?;
+
+        tracing::info!("Queued snoozed reminder for user {} - medicine: {}", user.id, medicine.name);
         Ok(())
     }
 
     /// 未服薬の薬をチェック
-    async fn check_missed_medications(&self, app_context: &AppContext) -> Result<(), Error> {
-        let now = Local::now().naive_local();
-        let thirty_minutes_ago = now - Duration::minutes(30);
+    ///
+    /// `since` より後、`until` 以前に予定時刻から30分経ったログを未服薬とする。
+    /// スヌーズ中のログは予定時刻ではなく再通知時刻から30分後に未服薬とする。
+    pub async fn check_missed_medications(
+        &self,
+        app_context: &AppContext,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Result<(), Error> {
+        let window_start = since - Duration::minutes(MISSED_AFTER_MINUTES);
+        let window_end = until - Duration::minutes(MISSED_AFTER_MINUTES);
 
-        // 30分前の未完了ログを検索
+        // 予定時刻から30分経った未完了ログを検索
         let missed_logs = medication_logs::Entity::find()
-            .filter(medication_logs::Column::Status.eq("pending"))
-            .filter(medication_logs::Column::ScheduledTime.between(
-                thirty_minutes_ago - Duration::minutes(5),
-                thirty_minutes_ago + Duration::minutes(5)
-            ))
+            .filter(medication_logs::Column::Status.is_in([MedicationStatus::Pending, MedicationStatus::Snoozed]))
+            .filter(
+                Condition::any()
+                    .add(
+                        Condition::all()
+                            .add(medication_logs::Column::SnoozedUntil.is_null())
+                            .add(medication_logs::Column::ScheduledTime.gt(window_start))
+                            .add(medication_logs::Column::ScheduledTime.lte(window_end))
+                    )
+                    .add(
+                        Condition::all()
+                            .add(medication_logs::Column::SnoozedUntil.gt(window_start))
+                            .add(medication_logs::Column::SnoozedUntil.lte(window_end))
+                    )
+            )
             .all(&app_context.db)
             .await?;
 
@@ -204,14 +740,14 @@ impl MedicationReminderTask {
     }
 
     /// 未服薬の処理
-    async fn process_missed_medication(
+    pub async fn process_missed_medication(
         &self,
         app_context: &AppContext,
         log: &MedicationLog,
     ) -> Result<(), Error> {
         // ステータスを「未服薬」に更新
         let mut log_active: medication_logs::ActiveModel = log.clone().into();
-        log_active.status = Set("missed".to_string());
+        log_active.status = Set(MedicationStatus::Missed);
         log_active.update(&app_context.db).await?;
 
         // 薬とユーザー情報を取得
@@ -225,10 +761,6 @@ impl MedicationReminderTask {
             .await?
             .ok_or_else(|| Error::string("User not found"))?;
 
-        let line_user_id = user.line_user_id
-            .as_ref()
-            .ok_or_else(|| Error::string("User has no LINE ID"))?;
-
         // 未服薬通知メッセージを作成
         let message = format!(
             r"⚠️ 服薬を忘れていませんか？
@@ -238,74 +770,92 @@ impl MedicationReminderTask {
 
 まだ時間がある場合は「服薬完了」と返信してください。",
             medicine.name,
-            log.scheduled_time.format("%H:%M")
+            log.scheduled_time.with_timezone(&user.tz()).format("%H:%M")
         );
 
         // 通知ワーカーをエンキュー
-        let _notification_args = NotificationWorkerArgs {
-            line_user_id: line_user_id.clone(),
+        let notification_args = NotificationWorkerArgs {
+            user_id: Some(user.id),
+            line_user_id: None,
             message,
             notification_type: "missed_medication".to_string(),
             medicine_id: Some(medicine.id),
             log_id: Some(log.id),
+            report: None,
+            dead_letter_id: None,
         };
 
-        // TODO: Fix queue access pattern for Loco 0.15
-        // The queue access needs to be implemented properly
-        // if let Some(queue) = &app_context.queue {
-        //     queue.enqueue(
-        //         crate::workers::notification_worker::NotificationWorker::build(app_context),
-        //         &notification_args,
-        //         None,
-        //     ).await?;
-        // }
-
-        tracing::info!("Queued missed medication reminder for user {} - medicine: {}", line_user_id, medicine.name);
+        NotificationWorker::perform_later(app_context, notification_args).await?;
+
+        tracing::info!("Queued missed medication reminder for user {} - medicine: {}", user.id, medicine.name);
         Ok(())
     }
 
     /// リマインダーメッセージを作成
-    fn create_reminder_message(&self, medicine: &Medicine, schedule: &MedicationSchedule) -> String {
+    fn create_reminder_message(&self, medicine: &Medicine, time: NaiveTime, schedule: &MedicationSchedule) -> String {
         let dosage_info = match (&medicine.dosage, &medicine.unit) {
             (Some(dosage), Some(unit)) => format!(" ({}{})", dosage, unit),
             (Some(dosage), None) => format!(" ({})", dosage),
             _ => String::new(),
         };
+        let course_info = match schedule.max_doses {
+            Some(max_doses) => format!("\n📅 {}/{}回目", schedule.dose_count, max_doses),
+            None => String::new(),
+        };
 
         format!(
             r"🔔 服薬時間です！
 
 💊 {}{}
-⏰ {}
+⏰ {}{}
 
 「服薬完了」と返信して記録してください。",
             medicine.name,
             dosage_info,
-            schedule.scheduled_time.format("%H:%M")
+            time.format("%H:%M"),
+            course_info
         )
     }
 
-    /// 曜日を数値に変換
-    fn weekday_to_number(&self, weekday: Weekday) -> u8 {
-        match weekday {
-            Weekday::Mon => 1,
-            Weekday::Tue => 2,
-            Weekday::Wed => 3,
-            Weekday::Thu => 4,
-            Weekday::Fri => 5,
-            Weekday::Sat => 6,
-            Weekday::Sun => 7,
+    /// 現地の服薬日時を、その時点のUTCオフセット付きの時刻として解決
+    ///
+    /// 夏時間の開始で存在しない時刻は切り替え直後、終了で2回ある時刻は
+    /// 早い方とする。
+    #[must_use]
+    pub fn resolve_local_time(tz: Tz, local: NaiveDateTime) -> DateTime<Tz> {
+        let local = Self::truncate_to_minute(local);
+        let mut candidate = local;
+        loop {
+            match tz.from_local_datetime(&candidate) {
+                LocalResult::Single(time) | LocalResult::Ambiguous(time, _) => return time,
+                // 存在しない時刻は、存在する時刻になるまで1分ずつ進める
+                LocalResult::None => candidate += Duration::minutes(1),
+            }
         }
     }
 
-    /// 日付とスケジュール時刻を結合
-    fn combine_date_and_schedule_time(
-        &self,
-        date: NaiveDateTime,
-        schedule_time: &NaiveDateTime,
-    ) -> Result<NaiveDateTime, Error> {
-        let time = schedule_time.time();
-        let combined = date.date().and_time(time);
-        Ok(combined)
+    /// `from` 以降 `to` 以前にスケジュールが服薬時刻を迎える時点
+    ///
+    /// リマインダーと同じ展開（[`MedicationSchedule::occurrences`]）と時刻の
+    /// 解決を使うため、プレビューと実際の通知は一致する。
+    #[must_use]
+    pub fn occurrences_between(
+        schedule: &MedicationSchedule,
+        tz: Tz,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Vec<DateTime<Tz>> {
+        // 夏時間の切り替えで前後にずれる分を含めて現地の日時で展開し、範囲で絞る
+        let local_from = from.with_timezone(&tz).naive_local() - Duration::hours(1);
+        let local_to = to.with_timezone(&tz).naive_local() + Duration::hours(1);
+
+        let mut occurrences: Vec<DateTime<Tz>> = schedule
+            .occurrences(local_from, local_to)
+            .into_iter()
+            .map(|local| Self::resolve_local_time(tz, local))
+            .filter(|time| *time >= from && *time <= to)
+            .collect();
+        occurrences.dedup();
+        occurrences
     }
-}
\ No newline at end of file
+}
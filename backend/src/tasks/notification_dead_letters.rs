@@ -0,0 +1,125 @@
+use loco_rs::prelude::*;
+use loco_rs::task::{Task, TaskInfo};
+
+use crate::models::notification_dead_letters::{self, Entity, Model};
+use crate::workers::notification_worker::{NotificationWorker, NotificationWorkerArgs};
+
+/// 届かなかった通知の確認と再送
+///
+/// ```sh
+/// cargo loco task notification_dead_letters             # 未再送の一覧
+/// cargo loco task notification_dead_letters replay:3    # 1件を再送
+/// cargo loco task notification_dead_letters replay:all  # すべて再送
+/// ```
+pub struct NotificationDeadLettersTask;
+
+#[async_trait]
+impl Task for NotificationDeadLettersTask {
+    fn task(&self) -> TaskInfo {
+        TaskInfo {
+            name: "notification_dead_letters".to_string(),
+            detail: "Lists notifications that could not be delivered; `replay:<id|all>` sends them again".to_string(),
+        }
+    }
+
+    async fn run(&self, app_context: &AppContext, vars: &task::Vars) -> Result<(), Error> {
+        let Ok(target) = vars.cli_arg("replay") else {
+            for dead_letter in Entity::find_pending().all(&app_context.db).await? {
+                println!(
+                    "#{} {} user={:?} attempts={} parked_at={}\n    {}",
+                    dead_letter.id,
+                    dead_letter.notification_type,
+                    dead_letter.user_id,
+                    dead_letter.attempts,
+                    dead_letter.created_at,
+                    dead_letter.error
+                );
+            }
+            return Ok(());
+        };
+
+        let dead_letters = if target == "all" {
+            Entity::find_pending().all(&app_context.db).await?
+        } else {
+            let id: i32 = target
+                .parse()
+                .map_err(|_| Error::string("replay must be a dead letter id or `all`"))?;
+            let dead_letter = Entity::find_by_id(id)
+                .one(&app_context.db)
+                .await?
+                .ok_or_else(|| Error::string(&format!("Dead letter {id} not found")))?;
+            vec![dead_letter]
+        };
+
+        let mut failed = 0;
+        for dead_letter in dead_letters {
+            let id = dead_letter.id;
+            match Self::replay(app_context, dead_letter).await {
+                Ok(()) => println!("#{id} replayed"),
+                Err(e) => {
+                    failed += 1;
+                    println!("#{id} failed: {e}");
+                }
+            }
+        }
+        if failed > 0 {
+            return Err(Error::string(&format!(
+                "{failed} dead letter(s) could not be replayed"
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl NotificationDeadLettersTask {
+    /// 保存したジョブを通知ワーカーに渡し直す
+    ///
+    /// 先に再送済みにしておき、再送でも届かなければワーカーが同じ通知を未再送に戻す。
+    /// ワーカーに渡せなかった場合は再送済みの記録を取り消す。
+    ///
+    /// # Errors
+    ///
+    /// 再送済みの場合、保存した内容を読めない場合、または再送でも届かなかった場合
+    pub async fn replay(app_context: &AppContext, dead_letter: Model) -> Result<(), Error> {
+        if dead_letter.is_replayed() {
+            return Err(Error::string(&format!(
+                "Dead letter {} was already replayed",
+                dead_letter.id
+            )));
+        }
+        let mut args: NotificationWorkerArgs = serde_json::from_value(dead_letter.args.clone())?;
+        args.dead_letter_id = Some(dead_letter.id);
+        tracing::info!(
+            "📮 Replaying dead letter {} ({})",
+            dead_letter.id,
+            dead_letter.notification_type
+        );
+
+        let id = dead_letter.id;
+        notification_dead_letters::ActiveModel::from(dead_letter)
+            .mark_replayed(&app_context.db)
+            .await?;
+        let performed = NotificationWorker::perform_later(app_context, args).await;
+
+        // ワーカーがすでに動いた場合、届かなければ未再送に戻っている
+        let current = Entity::find_by_id(id)
+            .one(&app_context.db)
+            .await?
+            .ok_or_else(|| Error::string(&format!("Dead letter {id} not found")))?;
+        match performed {
+            Err(e) => {
+                if current.is_replayed() {
+                    let mut current: notification_dead_letters::ActiveModel = current.into();
+                    current.replayed_at = ActiveValue::Set(None);
+                    current.update(&app_context.db).await?;
+                }
+                Err(e)
+            }
+            Ok(()) if !current.is_replayed() => Err(Error::string(&format!(
+                "Dead letter {id} could not be delivered: {}",
+                current.error
+            ))),
+            Ok(()) => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::models::{
+    _entities::{medication_logs, medicines},
+    medication_logs::MedicationStatus,
+};
+
+/// ダッシュボード（フロントエンドの `DashboardData`）
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DashboardResponse {
+    pub today_schedules: Vec<TodaySchedule>,
+    pub upcoming_schedules: Vec<UpcomingSchedule>,
+    pub weekly_stats: WeeklyStats,
+    pub recent_logs: Vec<RecentLog>,
+}
+
+/// 今日の服薬予定と記録の状態
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TodaySchedule {
+    /// スケジュールのID
+    pub id: i32,
+    pub medicine_id: i32,
+    pub medicine_name: String,
+    pub dosage: Option<String>,
+    pub scheduled_time: DateTime<FixedOffset>,
+    /// 記録がまだない場合は `pending`
+    pub status: MedicationStatus,
+    pub log_id: Option<i32>,
+}
+
+/// これからの服薬予定
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpcomingSchedule {
+    /// スケジュールのID
+    pub id: i32,
+    pub medicine_id: i32,
+    pub medicine_name: String,
+    pub dosage: Option<String>,
+    pub scheduled_time: DateTime<FixedOffset>,
+    /// 今日からの日数（今日は0）
+    pub days_until: i64,
+}
+
+/// 直近7日間の服薬率
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WeeklyStats {
+    pub total_scheduled: i32,
+    pub total_completed: i32,
+    pub adherence_rate: f64,
+    pub daily_stats: Vec<DailyStats>,
+}
+
+/// 1日の服薬率
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyStats {
+    pub date: NaiveDate,
+    pub scheduled: i32,
+    pub completed: i32,
+    pub adherence_rate: f64,
+}
+
+/// 服薬率（%、小数第1位まで）。予定がない場合は0
+#[must_use]
+pub fn adherence_rate(scheduled: i32, completed: i32) -> f64 {
+    if scheduled == 0 {
+        return 0.0;
+    }
+    (f64::from(completed) * 1000.0 / f64::from(scheduled)).round() / 10.0
+}
+
+/// 最近の服薬記録（フロントエンドの `MedicationLog`）
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentLog {
+    pub id: i32,
+    pub medicine_id: i32,
+    pub scheduled_time: DateTime<FixedOffset>,
+    pub taken_time: Option<DateTime<FixedOffset>>,
+    pub status: MedicationStatus,
+    pub notes: Option<String>,
+    pub medicine: Option<RecentLogMedicine>,
+    pub created_at: DateTime<FixedOffset>,
+    pub updated_at: DateTime<FixedOffset>,
+}
+
+/// 服薬記録の薬
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentLogMedicine {
+    pub id: i32,
+    pub name: String,
+    pub dosage: Option<String>,
+    pub unit: Option<String>,
+}
+
+impl RecentLog {
+    #[must_use]
+    pub fn new(log: medication_logs::Model, medicine: Option<&medicines::Model>) -> Self {
+        Self {
+            id: log.id,
+            medicine_id: log.medicine_id,
+            scheduled_time: log.scheduled_time,
+            taken_time: log.taken_time,
+            status: log.status,
+            notes: log.notes,
+            medicine: medicine.map(|medicine| RecentLogMedicine {
+                id: medicine.id,
+                name: medicine.name.clone(),
+                dosage: medicine.dosage.clone(),
+                unit: medicine.unit.clone(),
+            }),
+            created_at: log.created_at,
+            updated_at: log.updated_at,
+        }
+    }
+}
@@ -1 +1,3 @@
 pub mod auth;
+pub mod dashboard;
+pub mod users;
@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    models::_entities::users,
+    notifications::{parse_channels, NotificationChannelKind, NotificationStrategy},
+};
+
+/// ログイン中のユーザーのプロフィール
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UserResponse {
+    pub pid: String,
+    pub name: String,
+    pub email: String,
+    /// IANAのタイムゾーン名（未設定ならサーバーの既定）
+    pub timezone: String,
+    /// LINE連携済みか
+    pub line_linked: bool,
+}
+
+impl UserResponse {
+    #[must_use]
+    pub fn new(user: &users::Model) -> Self {
+        Self {
+            pid: user.pid.to_string(),
+            name: user.name.clone(),
+            email: user.email.clone(),
+            timezone: user.tz().name().to_string(),
+            line_linked: user.line_user_id.is_some(),
+        }
+    }
+}
+
+/// 通知の送信経路の設定
+#[derive(Debug, Deserialize, Serialize)]
+pub struct NotificationSettingsResponse {
+    /// 優先順の経路
+    pub channels: Vec<NotificationChannelKind>,
+    pub strategy: NotificationStrategy,
+    pub webhook_url: Option<String>,
+    /// LINE連携済みか（未連携ならLINEには送れない）
+    pub line_linked: bool,
+}
+
+impl NotificationSettingsResponse {
+    #[must_use]
+    pub fn new(user: &users::Model) -> Self {
+        Self {
+            channels: parse_channels(&user.notification_channels),
+            strategy: user.notification_strategy,
+            webhook_url: user.webhook_url.clone(),
+            line_linked: user.line_user_id.is_some(),
+        }
+    }
+}
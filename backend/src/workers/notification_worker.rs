@@ -1,14 +1,38 @@
 use loco_rs::prelude::*;
 use serde::{Deserialize, Serialize};
-use serde_json::json;
 
-#[derive(Debug, Deserialize, Serialize)]
+use crate::{
+    common::settings::Settings,
+    models::{
+        _entities::users,
+        notification_dead_letters,
+        notification_logs::{self, NotificationStatus},
+    },
+    notifications::{
+        self, Delivery, LineChannel, NotificationChannel, NotificationStrategy, RetryPolicy,
+        SendResult,
+    },
+    workers::report_generator::ReportDigest,
+};
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct NotificationWorkerArgs {
-    pub line_user_id: String,
+    /// 通知先のユーザー（ユーザーが選んだ経路で送る）
+    #[serde(default)]
+    pub user_id: Option<i32>,
+    /// ユーザーが分からない場合にLINEで直接送る宛先
+    #[serde(default)]
+    pub line_user_id: Option<String>,
     pub message: String,
-    pub notification_type: String, // "medication_reminder", "missed_medication", "general"
+    pub notification_type: String, // "medication_reminder", "missed_medication", "course_finished", "prn_warning", "refill_reminder", "general"
     pub medicine_id: Option<i32>,
     pub log_id: Option<i32>,
+    /// `medication_report` の場合のレポートの要約
+    #[serde(default)]
+    pub report: Option<ReportDigest>,
+    /// 保存済みの通知を再送している場合、その通知のID（届かなければ新しく保存せずに更新する）
+    #[serde(default)]
+    pub dead_letter_id: Option<i32>,
 }
 
 pub struct NotificationWorker {
@@ -22,235 +46,256 @@ impl BackgroundWorker<NotificationWorkerArgs> for NotificationWorker {
     }
     
     async fn perform(&self, args: NotificationWorkerArgs) -> Result<()> {
-        tracing::info!(
-            "📱 Sending {} notification to LINE user: {}",
-            args.notification_type,
-            args.line_user_id
-        );
+        let user = self.find_user(&args).await?;
+        let (channels, strategy) = match &user {
+            Some(user) => (notifications::channels_for(user), user.notification_strategy),
+            None => (
+                args.line_user_id
+                    .clone()
+                    .map(|line_user_id| Box::new(LineChannel { line_user_id }) as Box<dyn NotificationChannel>)
+                    .into_iter()
+                    .collect(),
+                NotificationStrategy::Fallback,
+            ),
+        };
+
+        if channels.is_empty() {
+            tracing::warn!(
+                "No notification channel available for {} notification (user: {:?}, LINE user: {:?})",
+                args.notification_type,
+                args.user_id,
+                args.line_user_id
+            );
+            return Ok(());
+        }
+
+        let policy = RetryPolicy::from(&Settings::from_config(&self.ctx.config)?.notification_retry);
+
+        // fallback: 送信できるまで優先順に試す / fan_out: すべての経路に送る
+        let mut delivered = false;
+        let mut last_error = None;
+        let mut total_attempts = 0;
+        for channel in &channels {
+            tracing::info!(
+                "📱 Sending {} notification via {} to {}",
+                args.notification_type,
+                channel.kind().label(),
+                channel.recipient()
+            );
+
+            let (result, attempts) = self
+                .send_with_retry(user.as_ref(), channel.as_ref(), &args, &policy)
+                .await;
+            total_attempts += attempts;
 
-        // LINE Bot APIを通じて通知を送信
-        match self.send_line_notification(&args).await {
-            Ok(_) => {
-                tracing::info!("✅ LINE notification sent successfully to {}", args.line_user_id);
-                
-                // 通知履歴をデータベースに記録
-                if let Err(e) = self.log_notification(&args).await {
-                    tracing::error!("Failed to log notification: {}", e);
+            match result {
+                Ok(Delivery::Sent { .. }) => {
+                    tracing::info!(
+                        "✅ {} notification sent successfully to {}",
+                        channel.kind().label(),
+                        channel.recipient()
+                    );
+                    delivered = true;
+                    if strategy == NotificationStrategy::Fallback {
+                        break;
+                    }
+                }
+                // 経路が設定されていないだけなので届いたことにはせず、次の経路を試す
+                Ok(Delivery::Skipped) => {}
+                Err(e) => {
+                    tracing::error!(
+                        "❌ Failed to send {} notification to {}: {}",
+                        channel.kind().label(),
+                        channel.recipient(),
+                        e
+                    );
+                    last_error = Some(format!("{} notification failed: {}", channel.kind().label(), e));
                 }
-            }
-            Err(e) => {
-                tracing::error!("❌ Failed to send LINE notification to {}: {}", args.line_user_id, e);
-                return Err(loco_rs::Error::string(&format!("LINE notification failed: {}", e)));
             }
         }
 
-        Ok(())
+        // どの経路でも届かなかった場合は、後から再送できるように保存する
+        // 再送はここで済ませているので、保存できればキューには成功として返す
+        // （失敗にするとキューが再試行し、送り直すたびに保存が重なる）
+        match last_error {
+            Some(error) if !delivered => self
+                .park(user.as_ref(), &args, &error, total_attempts)
+                .await
+                .map_err(|e| {
+                    loco_rs::Error::string(&format!("{error} (and could not be parked: {e})"))
+                }),
+            None if !delivered => {
+                tracing::warn!(
+                    "{} notification was not sent to any channel (user: {:?}, LINE user: {:?})",
+                    args.notification_type,
+                    args.user_id,
+                    args.line_user_id
+                );
+                Ok(())
+            }
+            _ => Ok(()),
+        }
     }
 }
 
 impl NotificationWorker {
-    /// LINE Bot APIに通知を送信
-    async fn send_line_notification(&self, args: &NotificationWorkerArgs) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let channel_access_token = std::env::var("LINE_CHANNEL_ACCESS_TOKEN")
-            .map_err(|_| "LINE_CHANNEL_ACCESS_TOKEN environment variable not found")?;
-
-        if channel_access_token == "YOUR_LINE_CHANNEL_ACCESS_TOKEN" {
-            tracing::warn!("LINE_CHANNEL_ACCESS_TOKEN is not properly configured. Skipping notification.");
-            return Ok(());
+    /// 通知先のユーザーを `user_id`、なければLINE User IDから探す
+    async fn find_user(&self, args: &NotificationWorkerArgs) -> Result<Option<users::Model>> {
+        if let Some(user_id) = args.user_id {
+            let user = users::Entity::find_by_id(user_id).one(&self.ctx.db).await?;
+            return user
+                .map(Some)
+                .ok_or_else(|| Error::string("User not found"));
         }
+        match &args.line_user_id {
+            Some(line_user_id) => Ok(users::Entity::find()
+                .filter(users::Column::LineUserId.eq(line_user_id))
+                .one(&self.ctx.db)
+                .await?),
+            None => Ok(None),
+        }
+    }
 
-        let client = reqwest::Client::new();
-        
-        // メッセージタイプに応じてLINE Flexメッセージまたは通常テキストを使い分け
-        let message_payload = match args.notification_type.as_str() {
-            "medication_reminder" => self.create_medication_reminder_flex_message(args),
-            "missed_medication" => self.create_missed_medication_flex_message(args),
-            _ => json!({
-                "type": "text",
-                "text": args.message
-            })
-        };
-
-        let push_message = json!({
-            "to": args.line_user_id,
-            "messages": [message_payload]
-        });
+    /// 1つの経路に送信し、再送の対象となる失敗は `policy` に従って待ってから送り直す
+    ///
+    /// ワーカーの中で待つので、待ち時間の合計は `policy.max_total_delay` までに限る。
+    /// 最後の送信結果と送信した回数を返す。
+    async fn send_with_retry(
+        &self,
+        user: Option<&users::Model>,
+        channel: &dyn NotificationChannel,
+        args: &NotificationWorkerArgs,
+        policy: &RetryPolicy,
+    ) -> (SendResult<Delivery>, u32) {
+        let payload = channel.payload(args);
+        let payload_hash = notification_logs::payload_hash(payload.to_string().as_bytes());
+        let mut attempt = 1;
+        let mut waited = std::time::Duration::ZERO;
+        loop {
+            let result = channel.send(&self.ctx, &payload).await;
 
-        let response = client
-            .post("https://api.line.me/v2/bot/message/push")
-            .header("Authorization", format!("Bearer {}", channel_access_token))
-            .header("Content-Type", "application/json")
-            .json(&push_message)
-            .send()
-            .await?;
+            // 成否にかかわらず通知履歴をデータベースに記録
+            if let Err(e) = self
+                .log_notification(user, channel, args, &payload_hash, attempt, &result)
+                .await
+            {
+                tracing::error!("Failed to log notification: {}", e);
+            }
 
-        if response.status().is_success() {
-            tracing::debug!("LINE API response: {}", response.status());
-            Ok(())
-        } else {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-            tracing::error!("LINE API error: {} - {}", status, error_text);
-            Err(format!("LINE API error: {} - {}", status, error_text).into())
+            let delay = match &result {
+                Err(e) => policy.delay_after(attempt, waited, e),
+                Ok(_) => None,
+            };
+            let Some(delay) = delay else {
+                return (result, attempt);
+            };
+            tracing::warn!(
+                "🔁 Retrying {} notification to {} in {:?} (attempt {}/{})",
+                channel.kind().label(),
+                channel.recipient(),
+                delay,
+                attempt + 1,
+                policy.max_attempts
+            );
+            tokio::time::sleep(delay).await;
+            waited += delay;
+            attempt += 1;
         }
     }
 
-    /// 服薬リマインダー用のFlexメッセージを作成
-    fn create_medication_reminder_flex_message(&self, args: &NotificationWorkerArgs) -> serde_json::Value {
-        json!({
-            "type": "flex",
-            "altText": "服薬リマインダー",
-            "contents": {
-                "type": "bubble",
-                "header": {
-                    "type": "box",
-                    "layout": "vertical",
-                    "contents": [
-                        {
-                            "type": "text",
-                            "text": "🔔 服薬時間です",
-                            "weight": "bold",
-                            "size": "lg",
-                            "color": "#2E86AB"
-                        }
-                    ],
-                    "backgroundColor": "#F3F7FA"
-                },
-                "body": {
-                    "type": "box",
-                    "layout": "vertical",
-                    "contents": [
-                        {
-                            "type": "text",
-                            "text": args.message,
-                            "wrap": true,
-                            "size": "md"
-                        }
-                    ]
-                },
-                "footer": {
-                    "type": "box",
-                    "layout": "vertical",
-                    "contents": [
-                        {
-                            "type": "button",
-                            "action": {
-                                "type": "message",
-                                "label": "服薬完了",
-                                "text": "服薬完了"
-                            },
-                            "style": "primary",
-                            "color": "#28a745"
-                        },
-                        {
-                            "type": "button",
-                            "action": {
-                                "type": "message",
-                                "label": "後で通知",
-                                "text": "後で通知"
-                            },
-                            "style": "secondary"
-                        }
-                    ],
-                    "spacing": "sm"
-                }
+    /// 届かなかった通知を、CLIタスクから再送できるように保存する
+    ///
+    /// 再送したジョブの場合は元の通知を未再送に戻す。
+    async fn park(
+        &self,
+        user: Option<&users::Model>,
+        args: &NotificationWorkerArgs,
+        error: &str,
+        attempts: u32,
+    ) -> Result<(), String> {
+        let attempts = i32::try_from(attempts).unwrap_or(i32::MAX);
+        let result = match (args.dead_letter_id, serde_json::to_value(args)) {
+            (Some(id), _) => {
+                notification_dead_letters::ActiveModel::repark(&self.ctx.db, id, error, attempts)
+                    .await
+                    .map_err(|e| e.to_string())
             }
-        })
-    }
-
-    /// 未服薬警告用のFlexメッセージを作成
-    fn create_missed_medication_flex_message(&self, args: &NotificationWorkerArgs) -> serde_json::Value {
-        json!({
-            "type": "flex",
-            "altText": "服薬忘れ通知",
-            "contents": {
-                "type": "bubble",
-                "header": {
-                    "type": "box",
-                    "layout": "vertical",
-                    "contents": [
-                        {
-                            "type": "text",
-                            "text": "⚠️ 服薬忘れ",
-                            "weight": "bold",
-                            "size": "lg",
-                            "color": "#DC3545"
-                        }
-                    ],
-                    "backgroundColor": "#FDF2F2"
-                },
-                "body": {
-                    "type": "box",
-                    "layout": "vertical",
-                    "contents": [
-                        {
-                            "type": "text",
-                            "text": args.message,
-                            "wrap": true,
-                            "size": "md"
-                        }
-                    ]
-                },
-                "footer": {
-                    "type": "box",
-                    "layout": "vertical",
-                    "contents": [
-                        {
-                            "type": "button",
-                            "action": {
-                                "type": "message",
-                                "label": "今から飲む",
-                                "text": "服薬完了"
-                            },
-                            "style": "primary",
-                            "color": "#28a745"
-                        },
-                        {
-                            "type": "button",
-                            "action": {
-                                "type": "message",
-                                "label": "飲み忘れ記録",
-                                "text": "飲み忘れ"
-                            },
-                            "style": "secondary",
-                            "color": "#6c757d"
-                        }
-                    ],
-                    "spacing": "sm"
-                }
+            (None, Ok(value)) => notification_dead_letters::ActiveModel::park(
+                &self.ctx.db,
+                user.map(|user| user.id),
+                &args.notification_type,
+                value,
+                error,
+                attempts,
+            )
+            .await
+            .map_err(|e| e.to_string()),
+            (None, Err(e)) => Err(e.to_string()),
+        };
+        match result {
+            Ok(dead_letter) => {
+                tracing::warn!(
+                    "📮 Parked {} notification as dead letter {}: {}",
+                    args.notification_type,
+                    dead_letter.id,
+                    error
+                );
+                Ok(())
+            }
+            Err(e) => {
+                tracing::error!("Failed to park notification: {}", e);
+                Err(e)
             }
-        })
+        }
     }
 
     /// 通知履歴をデータベースに記録
-    async fn log_notification(&self, args: &NotificationWorkerArgs) -> Result<(), DbErr> {
-        // 通知履歴テーブルがあれば記録
-        // 今回は簡単のためログ出力のみ
-        tracing::info!(
-            "Notification logged: user={}, type={}, medicine_id={:?}",
-            args.line_user_id,
-            args.notification_type,
-            args.medicine_id
-        );
+    async fn log_notification(
+        &self,
+        user: Option<&users::Model>,
+        channel: &dyn NotificationChannel,
+        args: &NotificationWorkerArgs,
+        payload_hash: &str,
+        attempt: u32,
+        result: &SendResult<Delivery>,
+    ) -> Result<(), DbErr> {
+        let (status, line_request_id, error, sent_at) = match result {
+            Ok(Delivery::Sent { request_id }) => (
+                NotificationStatus::Sent,
+                request_id.clone(),
+                None,
+                Some(chrono::Utc::now().into()),
+            ),
+            Ok(Delivery::Skipped) => (NotificationStatus::Skipped, None, None, None),
+            Err(e) => (NotificationStatus::Failed, None, Some(e.to_string()), None),
+        };
 
-        // 将来的には notification_logs テーブルを作成して記録
-        /*
-        let log = notification_logs::ActiveModel {
-            line_user_id: Set(args.line_user_id.clone()),
-            message: Set(args.message.clone()),
+        notification_logs::ActiveModel {
+            user_id: Set(user.map(|user| user.id)),
+            channel: Set(channel.kind()),
+            recipient: Set(channel.recipient().to_string()),
             notification_type: Set(args.notification_type.clone()),
+            payload_hash: Set(payload_hash.to_string()),
             medicine_id: Set(args.medicine_id),
-            sent_at: Set(chrono::Utc::now().naive_utc()),
-            status: Set("sent".to_string()),
+            log_id: Set(args.log_id),
+            status: Set(status),
+            line_request_id: Set(line_request_id),
+            error: Set(error),
+            sent_at: Set(sent_at),
+            attempt: Set(i32::try_from(attempt).unwrap_or(i32::MAX)),
             ..Default::default()
-        };
-
-        notification_logs::Entity::insert(log)
-            .exec(&self.ctx.db)
-            .await?;
-        */
+        }
+        .insert(&self.ctx.db)
+        .await?;
 
+        tracing::info!(
+            "Notification logged: channel={}, recipient={}, type={}, medicine_id={:?}, status={:?}",
+            channel.kind().label(),
+            channel.recipient(),
+            args.notification_type,
+            args.medicine_id,
+            status
+        );
         Ok(())
     }
 }
-
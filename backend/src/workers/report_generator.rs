@@ -7,10 +7,11 @@ use std::collections::HashMap;
 
 use crate::models::{
     _entities::{users, medicines, medication_logs},
+    medication_logs::MedicationStatus,
     users::Model as User,
     medication_logs::Model as MedicationLog,
 };
-use crate::workers::notification_worker::NotificationWorkerArgs;
+use crate::workers::notification_worker::{NotificationWorker, NotificationWorkerArgs};
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ReportGeneratorArgs {
@@ -32,7 +33,7 @@ pub struct MedicationReport {
     pub generated_at: chrono::NaiveDateTime,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ReportSummary {
     pub total_scheduled: i32,
     pub total_taken: i32,
@@ -43,6 +44,42 @@ pub struct ReportSummary {
     pub worst_adherence_medicine: Option<String>,
 }
 
+/// 通知で送るレポートの要約（LINEではFlexメッセージで表示する）
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ReportDigest {
+    pub report_type: String,
+    pub period: String,
+    pub summary: ReportSummary,
+    /// 最初の推奨事項
+    pub recommendation: Option<String>,
+}
+
+impl ReportDigest {
+    #[must_use]
+    pub fn new(report: &MedicationReport) -> Self {
+        Self {
+            report_type: report.report_type.clone(),
+            period: report.period.clone(),
+            summary: report.summary.clone(),
+            recommendation: report.recommendations.first().cloned(),
+        }
+    }
+
+    /// 遵守率に応じた絵文字
+    #[must_use]
+    pub fn adherence_emoji(&self) -> &'static str {
+        if self.summary.adherence_rate >= 90.0 {
+            "🎉"
+        } else if self.summary.adherence_rate >= 70.0 {
+            "👍"
+        } else if self.summary.adherence_rate >= 50.0 {
+            "⚠️"
+        } else {
+            "🚨"
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct MedicineReport {
     pub medicine_id: i32,
@@ -104,7 +141,11 @@ impl BackgroundWorker<ReportGeneratorArgs> for ReportGeneratorWorker {
 
 impl ReportGeneratorWorker {
     /// レポート期間を決定
-    fn determine_report_period(&self, args: &ReportGeneratorArgs) -> Result<(NaiveDate, NaiveDate)> {
+    ///
+    /// # Errors
+    ///
+    /// 期間の指定が無く、`report_type` が `daily` / `weekly` / `monthly` のいずれでもない場合
+    pub fn determine_report_period(&self, args: &ReportGeneratorArgs) -> Result<(NaiveDate, NaiveDate)> {
         match (args.start_date, args.end_date) {
             (Some(start), Some(end)) => Ok((start, end)),
             _ => {
@@ -127,7 +168,11 @@ impl ReportGeneratorWorker {
     }
 
     /// 服薬レポートを生成
-    async fn generate_medication_report(
+    ///
+    /// # Errors
+    ///
+    /// データベースの読み込みに失敗した場合
+    pub async fn generate_medication_report(
         &self,
         db: &DatabaseConnection,
         user: &User,
@@ -171,11 +216,11 @@ impl ReportGeneratorWorker {
             let scheduled_count = medicine_logs.len() as i32;
             let taken_count = medicine_logs
                 .iter()
-                .filter(|log| log.status == "completed")
+                .filter(|log| log.status == MedicationStatus::Completed)
                 .count() as i32;
             let missed_count = medicine_logs
                 .iter()
-                .filter(|log| log.status == "missed")
+                .filter(|log| log.status == MedicationStatus::Missed)
                 .count() as i32;
 
             let adherence_rate = if scheduled_count > 0 {
@@ -186,7 +231,7 @@ impl ReportGeneratorWorker {
 
             let missed_times: Vec<String> = medicine_logs
                 .iter()
-                .filter(|log| log.status == "missed")
+                .filter(|log| log.status == MedicationStatus::Missed)
                 .map(|log| log.scheduled_time.format("%H:%M").to_string())
                 .collect();
 
@@ -261,7 +306,7 @@ impl ReportGeneratorWorker {
         let mut time_miss_count: HashMap<String, i32> = HashMap::new();
 
         for log in logs {
-            if log.status == "missed" {
+            if log.status == MedicationStatus::Missed {
                 let hour = log.scheduled_time.hour();
                 let time_range = match hour {
                     6..=11 => "朝（6-11時）",
@@ -346,50 +391,43 @@ impl ReportGeneratorWorker {
     }
 
     /// レポート通知を送信
-    async fn send_report_notification(
+    ///
+    /// # Errors
+    ///
+    /// 通知ワーカーの実行に失敗した場合
+    pub async fn send_report_notification(
         &self,
-        _ctx: &AppContext,
+        ctx: &AppContext,
         user: &User,
         report: &MedicationReport,
     ) -> Result<()> {
-        let line_user_id = user.line_user_id
-            .as_ref()
-            .ok_or_else(|| loco_rs::Error::string("User has no LINE ID"))?;
-
         // レポートサマリーメッセージを作成
-        let message = self.create_report_summary_message(report);
+        let digest = ReportDigest::new(report);
+        let message = self.create_report_summary_message(&digest);
 
         // 通知ワーカーをエンキュー
-        let _notification_args = NotificationWorkerArgs {
-            line_user_id: line_user_id.clone(),
+        let notification_args = NotificationWorkerArgs {
+            user_id: Some(user.id),
+            line_user_id: None,
             message,
             notification_type: "medication_report".to_string(),
             medicine_id: None,
             log_id: None,
+            report: Some(digest),
+            dead_letter_id: None,
         };
 
-        // TODO: Queue::queue の実装を修正する必要があります
-        // Queue::queue(&self.ctx.queue, NotificationWorker, &notification_args).await?;
+        NotificationWorker::perform_later(ctx, notification_args).await?;
 
-        tracing::info!("Queued report notification for user {}", line_user_id);
+        tracing::info!("Queued report notification for user {}", user.id);
         Ok(())
     }
 
     /// レポートサマリーメッセージを作成
-    fn create_report_summary_message(&self, report: &MedicationReport) -> String {
-        let adherence_emoji = if report.summary.adherence_rate >= 90.0 {
-            "🎉"
-        } else if report.summary.adherence_rate >= 70.0 {
-            "👍"
-        } else if report.summary.adherence_rate >= 50.0 {
-            "⚠️"
-        } else {
-            "🚨"
-        };
-
+    fn create_report_summary_message(&self, report: &ReportDigest) -> String {
         let mut message = format!(
             "{} 服薬レポート（{}）\n\n📊 期間: {}\n\n📈 服薬状況:\n• 予定回数: {}回\n• 服薬完了: {}回\n• 飲み忘れ: {}回\n• 遵守率: {:.1}%\n",
-            adherence_emoji,
+            report.adherence_emoji(),
             report.report_type,
             report.period,
             report.summary.total_scheduled,
@@ -399,7 +437,7 @@ impl ReportGeneratorWorker {
         );
 
         // 最初の推奨事項を追加
-        if let Some(first_recommendation) = report.recommendations.first() {
+        if let Some(first_recommendation) = &report.recommendation {
             message.push_str(&format!("\n💡 {}\n", first_recommendation));
         }
 
@@ -0,0 +1,17 @@
+use backend::line::command::Command;
+use rstest::rstest;
+
+#[rstest]
+#[case("服薬完了", Some(Command::Complete))]
+#[case("飲んだ", Some(Command::Complete))]
+#[case(" 飲みました！", Some(Command::Complete))]
+#[case("後で通知", Some(Command::Snooze))]
+#[case("あとで", Some(Command::Snooze))]
+#[case("飲み忘れ", Some(Command::Missed))]
+#[case("スキップ", Some(Command::Skip))]
+#[case("Skip", Some(Command::Skip))]
+#[case("こんにちは", None)]
+#[case("", None)]
+fn can_parse_command(#[case] text: &str, #[case] expected: Option<Command>) {
+    assert_eq!(Command::parse(text), expected);
+}
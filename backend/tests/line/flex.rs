@@ -0,0 +1,132 @@
+use backend::{
+    line::flex::FlexMessage,
+    notifications::{LineChannel, NotificationChannel},
+    workers::{
+        notification_worker::NotificationWorkerArgs,
+        report_generator::{ReportDigest, ReportSummary},
+    },
+};
+use insta::assert_snapshot;
+
+macro_rules! configure_insta {
+    ($($expr:expr),*) => {
+        let mut settings = insta::Settings::clone_current();
+        settings.set_prepend_module_to_snapshot(false);
+        settings.set_snapshot_suffix("flex");
+        let _guard = settings.bind_to_scope();
+    };
+}
+
+fn args(notification_type: &str, message: &str) -> NotificationWorkerArgs {
+    NotificationWorkerArgs {
+        user_id: Some(1),
+        line_user_id: None,
+        message: message.to_string(),
+        notification_type: notification_type.to_string(),
+        medicine_id: Some(3),
+        log_id: Some(12),
+        report: None,
+        dead_letter_id: None,
+    }
+}
+
+fn report() -> ReportDigest {
+    ReportDigest {
+        report_type: "weekly".to_string(),
+        period: "2025-06-02 - 2025-06-08".to_string(),
+        summary: ReportSummary {
+            total_scheduled: 14,
+            total_taken: 11,
+            total_missed: 3,
+            adherence_rate: 78.571,
+            most_missed_time: Some("20:00".to_string()),
+            best_adherence_medicine: Some("アスピリン".to_string()),
+            worst_adherence_medicine: Some("ロキソニン".to_string()),
+        },
+        recommendation: Some("夜の服薬を忘れがちです。アラームを設定しましょう。".to_string()),
+    }
+}
+
+/// LINEのPush APIに送るリクエストボディ
+fn line_payload(args: &NotificationWorkerArgs) -> String {
+    let line = LineChannel {
+        line_user_id: "U4af4980629a0a1b2c3d4e5f6a7b8c9d0".to_string(),
+    };
+    serde_json::to_string_pretty(&line.payload(args)).unwrap()
+}
+
+#[test]
+fn medication_reminder_payload() {
+    configure_insta!();
+
+    let mut reminder = args("medication_reminder", "💊 アスピリン（1錠）を飲む時間です");
+    assert_snapshot!(line_payload(&reminder));
+
+    // 対象のログが無い場合はコマンドを送るボタンにする
+    reminder.log_id = None;
+    assert_snapshot!("medication_reminder_payload_without_log", line_payload(&reminder));
+}
+
+#[test]
+fn missed_medication_payload() {
+    configure_insta!();
+
+    assert_snapshot!(line_payload(&args(
+        "missed_medication",
+        "⚠️ 08:00のアスピリン（1錠）がまだ記録されていません"
+    )));
+}
+
+#[test]
+fn medication_report_payload() {
+    configure_insta!();
+
+    let mut digest = report();
+    let mut report_args = args("medication_report", "服薬レポート");
+    report_args.report = Some(digest.clone());
+    assert_snapshot!(line_payload(&report_args));
+
+    // 推奨事項や分析結果が無ければ区切り線ごと省く
+    digest.recommendation = None;
+    digest.summary.best_adherence_medicine = None;
+    digest.summary.worst_adherence_medicine = None;
+    report_args.report = Some(digest);
+    assert_snapshot!("medication_report_payload_without_notes", line_payload(&report_args));
+
+    // 要約が無い場合は本文をテキストで送る
+    report_args.report = None;
+    assert_snapshot!("medication_report_payload_without_digest", line_payload(&report_args));
+}
+
+#[test]
+fn text_notification_payloads() {
+    configure_insta!();
+
+    for (notification_type, message) in [
+        ("course_finished", "✅ アスピリンの服用期間が終了しました"),
+        ("prn_warning", "⚠️ ロキソニンは前回の服用から4時間空いていません"),
+        ("refill_reminder", "📦 アスピリンの残りが3日分です"),
+        ("general", "お知らせ"),
+    ] {
+        assert_snapshot!(
+            format!("{notification_type}_payload"),
+            line_payload(&args(notification_type, message))
+        );
+    }
+}
+
+#[test]
+fn flex_message_round_trips_through_json() {
+    let line = LineChannel {
+        line_user_id: "U4af4980629a0a1b2c3d4e5f6a7b8c9d0".to_string(),
+    };
+    for flex in [
+        line.medication_reminder_message(&args("medication_reminder", "お薬の時間です")),
+        line.missed_medication_message(&args("missed_medication", "飲み忘れです")),
+        line.medication_report_message(&report()),
+    ] {
+        let json = serde_json::to_value(&flex).unwrap();
+        assert_eq!(json["type"], "flex");
+        assert_eq!(serde_json::from_value::<FlexMessage>(json).unwrap(), flex);
+    }
+}
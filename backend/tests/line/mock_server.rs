@@ -0,0 +1,148 @@
+//! テスト用のLINE Messaging APIのモックサーバー
+//!
+//! Push・Reply APIへのリクエストを記録し、指定した応答を返す。
+//! [`MockLineServer::context`] で作ったコンテキストを使うと、通知や返信が
+//! 実際のLINEではなくこのサーバーに送られる。
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{header::RETRY_AFTER, HeaderMap, HeaderValue, StatusCode, Uri},
+    response::IntoResponse,
+    Router,
+};
+use backend::common::settings::LineSettings;
+use loco_rs::app::AppContext;
+use serde_json::Value;
+
+/// モックサーバーを使うときのアクセストークン
+pub const ACCESS_TOKEN: &str = "test-access-token";
+
+/// 記録したリクエスト
+#[derive(Debug, Clone)]
+pub struct RecordedCall {
+    pub path: String,
+    pub authorization: Option<String>,
+    pub body: Value,
+}
+
+#[derive(Default)]
+struct MockState {
+    calls: Mutex<Vec<RecordedCall>>,
+    /// 次に返す応答（空なら200）
+    responses: Mutex<VecDeque<(StatusCode, Option<&'static str>)>>,
+}
+
+pub struct MockLineServer {
+    base_url: String,
+    state: Arc<MockState>,
+}
+
+impl MockLineServer {
+    /// 空いているポートでモックサーバーを起動する
+    pub async fn start() -> Self {
+        let state = Arc::new(MockState::default());
+        let app = Router::new().fallback(record).with_state(state.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        Self {
+            base_url: format!("http://{addr}"),
+            state,
+        }
+    }
+
+    /// 次のリクエストに返す応答を追加する（追加した順に返す）
+    pub fn respond_with(&self, status: StatusCode, retry_after: Option<&'static str>) {
+        self.state
+            .responses
+            .lock()
+            .unwrap()
+            .push_back((status, retry_after));
+    }
+
+    /// LINE APIをこのサーバーに向けたコンテキスト
+    pub fn context(&self, ctx: &AppContext) -> AppContext {
+        with_line_settings(ctx, |line| {
+            line.api_base_url.clone_from(&self.base_url);
+            line.channel_access_token = Some(ACCESS_TOKEN.to_string());
+        })
+    }
+
+    pub fn calls(&self) -> Vec<RecordedCall> {
+        self.state.calls.lock().unwrap().clone()
+    }
+
+    /// Push APIに送られたリクエストボディ
+    pub fn pushes(&self) -> Vec<Value> {
+        self.bodies("/v2/bot/message/push")
+    }
+
+    /// Reply APIに送られたリクエストボディ
+    pub fn replies(&self) -> Vec<Value> {
+        self.bodies("/v2/bot/message/reply")
+    }
+
+    fn bodies(&self, path: &str) -> Vec<Value> {
+        self.calls()
+            .into_iter()
+            .filter(|call| call.path == path)
+            .map(|call| call.body)
+            .collect()
+    }
+}
+
+async fn record(
+    State(state): State<Arc<MockState>>,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    let mut calls = state.calls.lock().unwrap();
+    calls.push(RecordedCall {
+        path: uri.path().to_string(),
+        authorization: headers
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .map(ToString::to_string),
+        body: serde_json::from_slice(&body).unwrap_or(Value::Null),
+    });
+
+    let (status, retry_after) = state
+        .responses
+        .lock()
+        .unwrap()
+        .pop_front()
+        .unwrap_or((StatusCode::OK, None));
+    let mut response_headers = HeaderMap::new();
+    response_headers.insert(
+        "x-line-request-id",
+        HeaderValue::from_str(&format!("mock-request-{}", calls.len())).unwrap(),
+    );
+    if let Some(retry_after) = retry_after {
+        response_headers.insert(RETRY_AFTER, HeaderValue::from_static(retry_after));
+    }
+    (status, response_headers, "{}")
+}
+
+/// LINEの設定だけを変更したコンテキスト
+pub fn with_line_settings(ctx: &AppContext, update: impl FnOnce(&mut LineSettings)) -> AppContext {
+    let mut settings = ctx.config.settings.clone().unwrap_or_else(|| serde_json::json!({}));
+    let mut line: LineSettings = serde_json::from_value(settings["line"].clone()).unwrap_or_default();
+    update(&mut line);
+    settings["line"] = serde_json::to_value(line).unwrap();
+
+    let mut ctx = ctx.clone();
+    ctx.config.settings = Some(settings);
+    ctx
+}
+
+/// アクセストークンを設定していないコンテキスト（LINEへの送信は失敗する）
+pub fn without_access_token(ctx: &AppContext) -> AppContext {
+    with_line_settings(ctx, |line| line.channel_access_token = None)
+}
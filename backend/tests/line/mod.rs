@@ -0,0 +1,4 @@
+mod command;
+mod flex;
+pub mod mock_server;
+mod postback;
@@ -0,0 +1,33 @@
+use backend::line::{command::Command, postback::PostbackData};
+
+#[test]
+fn can_encode_and_parse_postback_data() {
+    let data = PostbackData {
+        command: Command::Complete,
+        log_id: 12,
+        medicine_id: Some(3),
+    };
+
+    assert_eq!(data.encode(), "action=complete&log_id=12&medicine_id=3");
+    assert_eq!(PostbackData::parse(&data.encode()), Some(data));
+}
+
+#[test]
+fn can_parse_postback_data_without_medicine_id() {
+    assert_eq!(
+        PostbackData::parse("action=skip&log_id=7"),
+        Some(PostbackData {
+            command: Command::Skip,
+            log_id: 7,
+            medicine_id: None,
+        })
+    );
+}
+
+#[test]
+fn rejects_malformed_postback_data() {
+    assert_eq!(PostbackData::parse("action=complete"), None);
+    assert_eq!(PostbackData::parse("action=unknown&log_id=1"), None);
+    assert_eq!(PostbackData::parse("action=complete&log_id=abc"), None);
+    assert_eq!(PostbackData::parse("服薬完了"), None);
+}
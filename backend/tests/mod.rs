@@ -1,4 +1,6 @@
+mod line;
 mod models;
+mod notifications;
 mod requests;
 mod tasks;
 mod workers;
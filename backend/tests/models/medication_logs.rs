@@ -1,7 +1,20 @@
-use backend::app::App;
-use loco_rs::testing::prelude::*;
+use backend::{
+    app::App,
+    models::{
+        _entities::{medication_logs, medicines},
+        medication_logs::MedicationStatus,
+        medicines::Model as Medicine,
+    },
+};
+use chrono::Utc;
+use loco_rs::{app::AppContext, testing::prelude::*};
+use sea_orm::{
+    prelude::Decimal, ActiveModelTrait, ActiveValue, EntityTrait, IntoActiveModel, ModelTrait,
+};
 use serial_test::serial;
 
+use crate::requests::prepare_data;
+
 macro_rules! configure_insta {
     ($($expr:expr),*) => {
         let mut settings = insta::Settings::clone_current();
@@ -29,3 +42,105 @@ async fn test_model() {
     // snapshot the result:
     // assert_debug_snapshot!(item);
 }
+
+async fn stock_of(ctx: &AppContext, medicine: &Medicine) -> Option<Decimal> {
+    medicines::Entity::find_by_id(medicine.id)
+        .one(&ctx.db)
+        .await
+        .unwrap()
+        .unwrap()
+        .stock_quantity
+}
+
+#[tokio::test]
+#[serial]
+async fn completing_a_log_consumes_stock_and_undo_restores_it() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        let medicine = prepare_data::create_medicine(&ctx, logged_in.user.id, "アスピリン").await;
+        let mut medicine = medicine.into_active_model();
+        medicine.dosage = ActiveValue::Set(Some("2錠".to_string()));
+        medicine.stock_quantity = ActiveValue::Set(Some(Decimal::from(10)));
+        let medicine = medicine.update(&ctx.db).await.unwrap();
+
+        let log = prepare_data::create_medication_log(
+            &ctx,
+            medicine.id,
+            "2025-06-09T08:00:00+09:00",
+            "pending",
+        )
+        .await;
+        assert_eq!(stock_of(&ctx, &medicine).await, Some(Decimal::from(10)));
+
+        let log = log
+            .into_active_model()
+            .mark_completed(&ctx.db, Utc::now().into())
+            .await
+            .unwrap();
+        assert_eq!(stock_of(&ctx, &medicine).await, Some(Decimal::from(8)));
+
+        // 完了のまま保存し直しても二重に減らさない
+        let mut notes = log.clone().into_active_model();
+        notes.status = ActiveValue::Set(MedicationStatus::Completed);
+        notes.notes = ActiveValue::Set(Some("食後".to_string()));
+        notes.update(&ctx.db).await.unwrap();
+        assert_eq!(stock_of(&ctx, &medicine).await, Some(Decimal::from(8)));
+
+        // 完了を取り消すと戻す
+        let log = log.into_active_model().mark_skipped(&ctx.db).await.unwrap();
+        assert_eq!(stock_of(&ctx, &medicine).await, Some(Decimal::from(10)));
+
+        let log = log
+            .into_active_model()
+            .mark_completed(&ctx.db, Utc::now().into())
+            .await
+            .unwrap();
+        assert_eq!(stock_of(&ctx, &medicine).await, Some(Decimal::from(8)));
+
+        // 完了したログを削除しても戻す
+        log.delete(&ctx.db).await.unwrap();
+        assert_eq!(stock_of(&ctx, &medicine).await, Some(Decimal::from(10)));
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn completing_a_log_without_tracked_stock_leaves_it_untracked() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        let medicine = prepare_data::create_medicine(&ctx, logged_in.user.id, "アスピリン").await;
+        medication_logs::ActiveModel {
+            medicine_id: ActiveValue::Set(medicine.id),
+            scheduled_time: ActiveValue::Set(Utc::now().into()),
+            taken_time: ActiveValue::Set(Some(Utc::now().into())),
+            status: ActiveValue::Set(MedicationStatus::Completed),
+            ..Default::default()
+        }
+        .insert(&ctx.db)
+        .await
+        .unwrap();
+        assert_eq!(stock_of(&ctx, &medicine).await, None);
+    })
+    .await;
+}
+
+#[test]
+fn final_statuses_need_override_to_change() {
+    assert!(MedicationStatus::Pending.can_transition_to(MedicationStatus::Completed));
+    assert!(MedicationStatus::Snoozed.can_transition_to(MedicationStatus::Missed));
+    assert!(MedicationStatus::Missed.can_transition_to(MedicationStatus::Completed));
+    assert!(MedicationStatus::Skipped.can_transition_to(MedicationStatus::Skipped));
+    assert!(!MedicationStatus::Skipped.can_transition_to(MedicationStatus::Completed));
+    assert!(!MedicationStatus::Completed.can_transition_to(MedicationStatus::Pending));
+}
+
+#[test]
+fn unknown_status_names_the_allowed_values() {
+    assert_eq!("snoozed".parse(), Ok(MedicationStatus::Snoozed));
+    assert_eq!(
+        "complete".parse::<MedicationStatus>(),
+        Err("unknown status `complete`; allowed values: pending, completed, missed, skipped, snoozed"
+            .to_string())
+    );
+}
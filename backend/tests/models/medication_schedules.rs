@@ -1,4 +1,10 @@
-use backend::app::App;
+use backend::{
+    app::App,
+    models::medication_schedules::{
+        validate_course, validate_recurrence, weekday_bit, weekdays_mask, Model, ALL_WEEKDAYS,
+    },
+};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, Utc, Weekday};
 use loco_rs::testing::prelude::*;
 use serial_test::serial;
 
@@ -29,3 +35,203 @@ async fn test_model() {
     // snapshot the result:
     // assert_debug_snapshot!(item);
 }
+
+#[test]
+fn weekdays_mask_maps_iso_days_to_bits() {
+    assert_eq!(weekdays_mask(&[1]), Some(weekday_bit(Weekday::Mon)));
+    assert_eq!(weekdays_mask(&[7]), Some(weekday_bit(Weekday::Sun)));
+    assert_eq!(weekdays_mask(&[1, 3, 5]), Some(0b001_0101));
+    assert_eq!(weekdays_mask(&[1, 2, 3, 4, 5, 6, 7]), Some(ALL_WEEKDAYS));
+    assert_eq!(weekdays_mask(&[]), Some(0));
+    assert_eq!(weekdays_mask(&[0]), None);
+    assert_eq!(weekdays_mask(&[1, 8]), None);
+}
+
+#[test]
+fn weekly_schedule_runs_only_on_its_days() {
+    let schedule = Model {
+        created_at: Utc::now().into(),
+        updated_at: Utc::now().into(),
+        id: 1,
+        medicine_id: 1,
+        frequency: "weekly".to_string(),
+        active: Some(true),
+        time_of_day: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+        weekdays: weekdays_mask(&[2, 6]).unwrap(),
+        interval_count: None,
+        interval_unit: None,
+        start_date: None,
+        end_date: None,
+        max_doses: None,
+        dose_count: 0,
+        rrule: None,
+        paused_by_archive: false,
+    };
+    assert_eq!(schedule.days_of_week(), vec![2, 6]);
+    assert!(schedule.runs_on(Weekday::Tue));
+    assert!(schedule.runs_on(Weekday::Sat));
+    assert!(!schedule.runs_on(Weekday::Mon));
+
+    let daily = Model {
+        frequency: "daily".to_string(),
+        weekdays: 0,
+        ..schedule
+    };
+    assert!(daily.runs_on(Weekday::Mon));
+}
+
+fn at(datetime: &str) -> NaiveDateTime {
+    NaiveDateTime::parse_from_str(datetime, "%Y-%m-%d %H:%M").unwrap()
+}
+
+fn every(count: i32, unit: &str) -> Model {
+    Model {
+        created_at: Utc::now().into(),
+        updated_at: Utc::now().into(),
+        id: 1,
+        medicine_id: 1,
+        frequency: "custom".to_string(),
+        active: Some(true),
+        time_of_day: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+        weekdays: ALL_WEEKDAYS,
+        interval_count: Some(count),
+        interval_unit: Some(unit.to_string()),
+        start_date: NaiveDate::from_ymd_opt(2025, 6, 1),
+        end_date: None,
+        max_doses: None,
+        dose_count: 0,
+        rrule: None,
+        paused_by_archive: false,
+    }
+}
+
+#[test]
+fn custom_schedule_occurs_every_n_days() {
+    let schedule = every(2, "days");
+    assert!(schedule.occurs_at(at("2025-06-01 08:00")));
+    assert!(!schedule.occurs_at(at("2025-06-02 08:00")));
+    assert!(schedule.occurs_at(at("2025-06-03 08:00")));
+    assert!(!schedule.occurs_at(at("2025-06-03 09:00")));
+    assert!(!schedule.occurs_at(at("2025-05-30 08:00")));
+}
+
+#[test]
+fn custom_schedule_occurs_every_n_hours_from_its_start() {
+    let schedule = every(8, "hours");
+    assert!(!schedule.occurs_at(at("2025-06-01 00:00")));
+    assert!(schedule.occurs_at(at("2025-06-01 08:00")));
+    assert!(schedule.occurs_at(at("2025-06-01 16:00")));
+    assert!(schedule.occurs_at(at("2025-06-02 00:00")));
+    assert!(!schedule.occurs_at(at("2025-06-02 04:00")));
+}
+
+#[test]
+fn custom_schedule_occurs_every_n_weeks() {
+    let schedule = every(2, "weeks");
+    assert!(schedule.occurs_at(at("2025-06-01 08:00")));
+    assert!(!schedule.occurs_at(at("2025-06-08 08:00")));
+    assert!(schedule.occurs_at(at("2025-06-15 08:00")));
+}
+
+#[test]
+fn recurrence_requires_interval_for_custom_frequency() {
+    let start = NaiveDate::from_ymd_opt(2025, 6, 1);
+    assert!(validate_recurrence("daily", None, None, None, None).is_ok());
+    assert!(validate_recurrence("custom", Some(2), Some("days"), None, start).is_ok());
+    assert!(validate_recurrence("hourly", None, None, None, None).is_err());
+    assert!(validate_recurrence("custom", None, Some("days"), None, start).is_err());
+    assert!(validate_recurrence("custom", Some(0), Some("days"), None, start).is_err());
+    assert!(validate_recurrence("custom", Some(2), Some("months"), None, start).is_err());
+    assert!(validate_recurrence("custom", Some(2), Some("days"), None, None).is_err());
+}
+
+#[test]
+fn schedule_stops_after_its_course() {
+    let schedule = Model {
+        frequency: "daily".to_string(),
+        end_date: NaiveDate::from_ymd_opt(2025, 6, 7),
+        ..every(1, "days")
+    };
+    assert!(!schedule.occurs_at(at("2025-05-31 08:00")));
+    assert!(schedule.occurs_at(at("2025-06-01 08:00")));
+    assert!(schedule.occurs_at(at("2025-06-07 08:00")));
+    assert!(!schedule.occurs_at(at("2025-06-08 08:00")));
+    assert!(!schedule.course_finished(NaiveDate::from_ymd_opt(2025, 6, 7).unwrap()));
+    assert!(schedule.course_finished(NaiveDate::from_ymd_opt(2025, 6, 8).unwrap()));
+
+    let counted = Model {
+        end_date: None,
+        max_doses: Some(3),
+        dose_count: 3,
+        ..schedule
+    };
+    assert!(counted.doses_exhausted());
+    assert!(!counted.occurs_at(at("2025-06-02 08:00")));
+}
+
+#[test]
+fn course_requires_ordered_dates_and_positive_doses() {
+    let start = NaiveDate::from_ymd_opt(2025, 6, 1);
+    assert!(validate_course(None, None, None).is_ok());
+    assert!(validate_course(start, start, Some(1)).is_ok());
+    assert!(validate_course(start, NaiveDate::from_ymd_opt(2025, 5, 31), None).is_err());
+    assert!(validate_course(start, None, Some(0)).is_err());
+}
+
+fn rrule(rule: &str) -> Model {
+    Model {
+        frequency: "rrule".to_string(),
+        rrule: Some(rule.to_string()),
+        ..every(1, "days")
+    }
+}
+
+#[test]
+fn rrule_schedule_expands_first_monday_of_the_month() {
+    let schedule = rrule("FREQ=MONTHLY;BYDAY=1MO");
+    assert_eq!(
+        schedule.occurrences(at("2025-06-01 00:00"), at("2025-08-31 23:59")),
+        vec![at("2025-06-02 08:00"), at("2025-07-07 08:00"), at("2025-08-04 08:00")]
+    );
+    assert!(schedule.occurs_at(at("2025-07-07 08:00")));
+    assert!(!schedule.occurs_at(at("2025-07-14 08:00")));
+}
+
+#[test]
+fn rrule_schedule_expands_every_other_thursday() {
+    // 起点の 2025-06-01（日曜日）を含む週から2週ごと（週の始まりは月曜日）
+    let schedule = rrule("RRULE:FREQ=WEEKLY;INTERVAL=2;BYDAY=TH");
+    assert_eq!(
+        schedule.occurrences(at("2025-06-01 00:00"), at("2025-06-30 23:59")),
+        vec![at("2025-06-12 08:00"), at("2025-06-26 08:00")]
+    );
+}
+
+#[test]
+fn occurrences_are_limited_by_the_course() {
+    let schedule = Model {
+        max_doses: Some(5),
+        dose_count: 3,
+        ..rrule("FREQ=DAILY")
+    };
+    assert_eq!(
+        schedule.occurrences(at("2025-06-01 00:00"), at("2025-06-30 23:59")),
+        vec![at("2025-06-01 08:00"), at("2025-06-02 08:00")]
+    );
+
+    let hourly = every(8, "hours");
+    assert_eq!(
+        hourly.occurrences(at("2025-06-01 09:00"), at("2025-06-02 08:00")),
+        vec![at("2025-06-01 16:00"), at("2025-06-02 00:00"), at("2025-06-02 08:00")]
+    );
+}
+
+#[test]
+fn rrule_is_validated() {
+    let start = NaiveDate::from_ymd_opt(2025, 6, 1);
+    assert!(validate_recurrence("rrule", None, None, Some("FREQ=MONTHLY;BYDAY=1MO"), start).is_ok());
+    assert!(validate_recurrence("rrule", None, None, Some("FREQ=MONTHLY;BYDAY=1MO"), None).is_err());
+    assert!(validate_recurrence("rrule", None, None, None, start).is_err());
+    assert!(validate_recurrence("rrule", None, None, Some("FREQ=SOMETIMES"), start).is_err());
+    assert!(validate_recurrence("rrule", None, None, Some("FREQ=SECONDLY"), start).is_err());
+}
@@ -1,4 +1,13 @@
-use backend::app::App;
+use backend::{
+    app::App,
+    models::{
+        medication_schedules::{Model as MedicationSchedule, ALL_WEEKDAYS},
+        medicines::{validate_prn, Model, PrnWarning},
+    },
+};
+use chrono::{DateTime, FixedOffset, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use sea_orm::prelude::Decimal;
+use chrono_tz::{Asia::Tokyo, Tz};
 use loco_rs::testing::prelude::*;
 use serial_test::serial;
 
@@ -29,3 +38,138 @@ async fn test_model() {
     // snapshot the result:
     // assert_debug_snapshot!(item);
 }
+
+fn loxonin() -> Model {
+    Model {
+        created_at: Utc::now().into(),
+        updated_at: Utc::now().into(),
+        id: 1,
+        name: "ロキソニン".to_string(),
+        description: None,
+        dosage: Some("60".to_string()),
+        unit: Some("mg".to_string()),
+        user_id: 1,
+        active: Some(true),
+        as_needed: true,
+        min_interval_minutes: Some(240),
+        max_daily_doses: Some(3),
+        stock_quantity: None,
+        refill_alerted_at: None,
+        archived_at: None,
+    }
+}
+
+fn tokyo(datetime: &str) -> DateTime<Tz> {
+    Tokyo
+        .from_local_datetime(&NaiveDateTime::parse_from_str(datetime, "%Y-%m-%d %H:%M").unwrap())
+        .unwrap()
+}
+
+fn taken(datetimes: &[&str]) -> Vec<DateTime<FixedOffset>> {
+    datetimes.iter().map(|datetime| tokyo(datetime).fixed_offset()).collect()
+}
+
+#[test]
+fn prn_dose_after_interval_has_no_warnings() {
+    let medicine = loxonin();
+    assert!(medicine.prn_warnings(&[], &tokyo("2025-06-09 08:00")).is_empty());
+    assert!(medicine
+        .prn_warnings(&taken(&["2025-06-09 08:00"]), &tokyo("2025-06-09 12:00"))
+        .is_empty());
+}
+
+#[test]
+fn prn_dose_too_soon_is_warned() {
+    let warnings = loxonin().prn_warnings(&taken(&["2025-06-09 08:00"]), &tokyo("2025-06-09 10:30"));
+    assert_eq!(
+        warnings,
+        vec![PrnWarning::TooSoon {
+            last_taken_at: tokyo("2025-06-09 08:00").fixed_offset(),
+            next_allowed_at: tokyo("2025-06-09 12:00").fixed_offset(),
+        }]
+    );
+    assert!(warnings[0].message().contains("12:00"));
+}
+
+#[test]
+fn prn_daily_limit_counts_doses_on_the_local_day() {
+    let medicine = loxonin();
+
+    // 前日の服用は数えない
+    let previous = taken(&["2025-06-08 22:00", "2025-06-09 06:00", "2025-06-09 12:00"]);
+    assert!(medicine.prn_warnings(&previous, &tokyo("2025-06-09 18:00")).is_empty());
+
+    let previous = taken(&["2025-06-09 06:00", "2025-06-09 12:00", "2025-06-09 18:00"]);
+    assert_eq!(
+        medicine.prn_warnings(&previous, &tokyo("2025-06-09 23:00")),
+        vec![PrnWarning::DailyLimitExceeded {
+            taken_today: 4,
+            max_daily_doses: 3,
+        }]
+    );
+}
+
+#[test]
+fn prn_limits_must_be_positive() {
+    assert!(validate_prn(None, None).is_ok());
+    assert!(validate_prn(Some(240), Some(3)).is_ok());
+    assert!(validate_prn(Some(0), None).is_err());
+    assert!(validate_prn(None, Some(0)).is_err());
+}
+
+#[test]
+fn dose_amount_is_the_leading_number_of_dosage() {
+    let with_dosage = |dosage: Option<&str>| Model {
+        dosage: dosage.map(str::to_string),
+        ..loxonin()
+    };
+    assert_eq!(with_dosage(Some("60")).dose_amount(), Decimal::from(60));
+    assert_eq!(with_dosage(Some("2錠")).dose_amount(), Decimal::from(2));
+    assert_eq!(with_dosage(Some("0.5")).dose_amount(), Decimal::new(5, 1));
+    assert_eq!(with_dosage(Some("適量")).dose_amount(), Decimal::ONE);
+    assert_eq!(with_dosage(None).dose_amount(), Decimal::ONE);
+}
+
+fn schedule_at(time_of_day: &str) -> MedicationSchedule {
+    MedicationSchedule {
+        created_at: Utc::now().into(),
+        updated_at: Utc::now().into(),
+        id: 1,
+        medicine_id: 1,
+        frequency: "daily".to_string(),
+        active: Some(true),
+        time_of_day: NaiveTime::parse_from_str(time_of_day, "%H:%M").unwrap(),
+        weekdays: ALL_WEEKDAYS,
+        interval_count: None,
+        interval_unit: None,
+        start_date: None,
+        end_date: None,
+        max_doses: None,
+        dose_count: 0,
+        rrule: None,
+        paused_by_archive: false,
+    }
+}
+
+#[test]
+fn stock_forecast_uses_active_schedules() {
+    let medicine = Model {
+        dosage: Some("1錠".to_string()),
+        stock_quantity: Some(Decimal::from(9)),
+        ..loxonin()
+    };
+    let now = NaiveDateTime::parse_from_str("2025-06-09 07:00", "%Y-%m-%d %H:%M").unwrap();
+
+    // 1日2回なので9錠は4日分
+    let forecast = medicine
+        .stock_forecast(&[schedule_at("08:00"), schedule_at("20:00")], now)
+        .unwrap();
+    assert_eq!(forecast.doses_per_day, Decimal::from(2));
+    assert_eq!(forecast.days_remaining, Some(4));
+
+    // 服用予定がなければ日数は出さない
+    assert_eq!(medicine.stock_forecast(&[], now).unwrap().days_remaining, None);
+
+    // 在庫を管理していない
+    assert_eq!(loxonin().stock_forecast(&[schedule_at("08:00")], now), None);
+}
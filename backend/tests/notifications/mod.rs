@@ -0,0 +1,2 @@
+mod retry;
+mod webhook;
@@ -0,0 +1,83 @@
+use std::time::Duration;
+
+use axum::http::{HeaderValue, StatusCode};
+use backend::notifications::{parse_retry_after, ChannelError, RetryPolicy};
+
+fn policy() -> RetryPolicy {
+    RetryPolicy {
+        max_attempts: 4,
+        base_delay: Duration::from_millis(100),
+        max_delay: Duration::from_secs(1),
+        max_total_delay: Duration::from_secs(2),
+    }
+}
+
+#[test]
+fn rate_limits_and_server_errors_are_retryable() {
+    let retry_after = HeaderValue::from_static("7");
+    assert_eq!(
+        ChannelError::from_status(StatusCode::TOO_MANY_REQUESTS, Some(&retry_after), "429".to_string()),
+        ChannelError::Retryable {
+            message: "429".to_string(),
+            retry_after: Some(Duration::from_secs(7)),
+        }
+    );
+    assert!(ChannelError::from_status(StatusCode::BAD_GATEWAY, None, String::new()).is_retryable());
+    assert!(!ChannelError::from_status(StatusCode::BAD_REQUEST, None, String::new()).is_retryable());
+    assert!(!ChannelError::from_status(StatusCode::UNAUTHORIZED, None, String::new()).is_retryable());
+}
+
+#[test]
+fn can_parse_retry_after_seconds_and_dates() {
+    assert_eq!(
+        parse_retry_after(&HeaderValue::from_static("120")),
+        Some(Duration::from_secs(120))
+    );
+    // 過去の日付は待たずに再送する
+    assert_eq!(
+        parse_retry_after(&HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT")),
+        Some(Duration::ZERO)
+    );
+    assert_eq!(parse_retry_after(&HeaderValue::from_static("soon")), None);
+}
+
+#[test]
+fn backoff_doubles_with_jitter_up_to_the_limit() {
+    let policy = policy();
+    for (attempt, full) in [(1, 100), (2, 200), (3, 400), (4, 800), (5, 1000), (10, 1000)] {
+        let delay = policy.backoff(attempt);
+        let full = Duration::from_millis(full);
+        assert!(delay >= full / 2 && delay <= full, "attempt {attempt}: {delay:?}");
+    }
+}
+
+#[test]
+fn retries_only_retryable_errors_within_limits() {
+    let policy = policy();
+    let retryable = ChannelError::retryable("503");
+    assert!(policy.delay_after(1, Duration::ZERO, &retryable).is_some());
+    assert!(policy.delay_after(3, Duration::ZERO, &retryable).is_some());
+    assert_eq!(policy.delay_after(4, Duration::ZERO, &retryable), None);
+    assert_eq!(
+        policy.delay_after(1, Duration::ZERO, &ChannelError::permanent("400")),
+        None
+    );
+
+    // Retry-After は指定どおり待ち、上限を超える場合は再送しない
+    let wait = |seconds| ChannelError::Retryable {
+        message: "429".to_string(),
+        retry_after: Some(Duration::from_secs(seconds)),
+    };
+    assert_eq!(
+        policy.delay_after(1, Duration::ZERO, &wait(1)),
+        Some(Duration::from_secs(1))
+    );
+    assert_eq!(policy.delay_after(1, Duration::ZERO, &wait(60)), None);
+
+    // ワーカーの中で待つ時間の合計は上限までにする
+    assert_eq!(
+        policy.delay_after(2, Duration::from_secs(1), &wait(1)),
+        Some(Duration::from_secs(1))
+    );
+    assert_eq!(policy.delay_after(3, Duration::from_secs(2), &wait(1)), None);
+}
@@ -0,0 +1,50 @@
+use std::net::IpAddr;
+
+use backend::notifications::{is_forbidden_ip, resolve_url, WebhookUrlError};
+
+#[test]
+fn internal_addresses_are_forbidden() {
+    for ip in [
+        "127.0.0.1",
+        "10.1.2.3",
+        "172.16.0.1",
+        "192.168.1.1",
+        "169.254.169.254",
+        "100.64.0.1",
+        "0.0.0.0",
+        "255.255.255.255",
+        "::1",
+        "::",
+        "fd00::1",
+        "fe80::1",
+        "::ffff:10.0.0.1",
+    ] {
+        assert!(is_forbidden_ip(ip.parse::<IpAddr>().unwrap()), "{ip}");
+    }
+    for ip in ["93.184.216.34", "8.8.8.8", "2606:4700:4700::1111", "::ffff:8.8.8.8"] {
+        assert!(!is_forbidden_ip(ip.parse::<IpAddr>().unwrap()), "{ip}");
+    }
+}
+
+#[tokio::test]
+async fn webhook_urls_are_checked_before_use() {
+    let (url, addrs) = resolve_url("https://93.184.216.34/hook", false).await.unwrap();
+    assert_eq!(url.path(), "/hook");
+    assert_eq!(addrs, ["93.184.216.34:443".parse().unwrap()]);
+
+    for url in ["http://127.0.0.1:8080/hook", "http://[::1]/hook", "http://localhost/hook"] {
+        assert!(
+            matches!(resolve_url(url, false).await, Err(WebhookUrlError::Forbidden(_))),
+            "{url}"
+        );
+    }
+    for url in ["ftp://example.com", "not a url"] {
+        assert!(
+            matches!(resolve_url(url, false).await, Err(WebhookUrlError::Invalid(_))),
+            "{url}"
+        );
+    }
+
+    // 開発・テスト用に許可した場合は内部のアドレスも使える
+    assert!(resolve_url("http://127.0.0.1:8080/hook", true).await.is_ok());
+}
@@ -1,17 +1,103 @@
-use backend::app::App;
+use backend::{app::App, models::users, notifications::NotificationStrategy};
 use loco_rs::testing::prelude::*;
 use serial_test::serial;
 
+use super::prepare_data;
+
 #[tokio::test]
 #[serial]
 async fn can_get_users() {
-    request::<App, _, _>(|request, _ctx| async move {
-        let res = request.get("/api/users/").await;
-        assert_eq!(res.status_code(), 200);
+    request::<App, _, _>(|request, ctx| async move {
+        let res = request.get("/api/users").await;
+        assert_eq!(res.status_code(), 401);
 
-        // you can assert content like this:
-        // assert_eq!(res.text(), "content");
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&logged_in.token);
+        let res = request
+            .get("/api/users")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(res.status_code(), 200);
+        let body = res.json::<serde_json::Value>();
+        assert_eq!(body["pid"], logged_in.user.pid.to_string());
+        assert_eq!(body["email"], logged_in.user.email);
+        assert_eq!(body["line_linked"], false);
+        assert!(body["timezone"].is_string());
     })
     .await;
 }
 
+
+#[tokio::test]
+#[serial]
+async fn can_update_notification_settings() {
+    request::<App, _, _>(|request, ctx| async move {
+        let res = request.get("/api/users/notifications").await;
+        assert_eq!(res.status_code(), 401);
+
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&logged_in.token);
+        let res = request
+            .get("/api/users/notifications")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(res.status_code(), 200);
+        let body = res.json::<serde_json::Value>();
+        assert_eq!(body["channels"], serde_json::json!(["line"]));
+        assert_eq!(body["strategy"], "fallback");
+        assert_eq!(body["line_linked"], false);
+
+        let invalid = [
+            // LINE未連携
+            serde_json::json!({ "channels": ["line", "email"] }),
+            serde_json::json!({ "channels": [] }),
+            serde_json::json!({ "channels": ["email", "email"] }),
+            serde_json::json!({ "channels": ["webhook"] }),
+            serde_json::json!({ "channels": ["webhook"], "webhook_url": "ftp://example.com" }),
+            // 内部のアドレス
+            serde_json::json!({ "channels": ["webhook"], "webhook_url": "http://127.0.0.1:8080/hook" }),
+            serde_json::json!({ "channels": ["webhook"], "webhook_url": "http://169.254.169.254/latest" }),
+            serde_json::json!({ "channels": ["webhook"], "webhook_url": "http://localhost/hook" }),
+            serde_json::json!({ "channels": ["webhook"], "webhook_url": "http://[::1]/hook" }),
+        ];
+        for params in invalid {
+            let res = request
+                .put("/api/users/notifications")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&params)
+                .await;
+            assert_eq!(res.status_code(), 400, "{params}");
+        }
+
+        let res = request
+            .put("/api/users/notifications")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&serde_json::json!({
+                "channels": ["webhook", "email"],
+                "strategy": "fan_out",
+                "webhook_url": "https://example.com/hook"
+            }))
+            .await;
+        assert_eq!(res.status_code(), 200);
+
+        let user = users::Model::find_by_pid(&ctx.db, &logged_in.user.pid.to_string())
+            .await
+            .unwrap();
+        assert_eq!(user.notification_channels, "webhook,email");
+        assert_eq!(user.notification_strategy, NotificationStrategy::FanOut);
+
+        // LINE連携後はLINEも選べる
+        prepare_data::link_line_user(&ctx, user, "U-settings").await;
+        let res = request
+            .put("/api/users/notifications")
+            .add_header(auth_key, auth_value)
+            .json(&serde_json::json!({ "channels": ["line", "email"] }))
+            .await;
+        assert_eq!(res.status_code(), 200);
+        let body = res.json::<serde_json::Value>();
+        assert_eq!(body["channels"], serde_json::json!(["line", "email"]));
+        assert_eq!(body["strategy"], "fallback");
+        assert_eq!(body["line_linked"], true);
+    })
+    .await;
+}
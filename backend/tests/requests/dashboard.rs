@@ -0,0 +1,119 @@
+use backend::{app::App, controllers::dashboard::dashboard_for, models::medication_logs::MedicationStatus};
+use chrono::{DateTime, NaiveDate, Utc};
+use loco_rs::testing::prelude::*;
+use sea_orm::{ActiveModelTrait, ActiveValue, IntoActiveModel};
+use serial_test::serial;
+
+use super::prepare_data;
+
+#[tokio::test]
+#[serial]
+async fn can_get_dashboard() {
+    request::<App, _, _>(|request, ctx| async move {
+        let res = request.get("/api/dashboard").await;
+        assert_eq!(res.status_code(), 401);
+
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&logged_in.token);
+        let res = request
+            .get("/api/dashboard")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(res.status_code(), 200);
+        let body = res.json::<serde_json::Value>();
+        assert_eq!(body["todaySchedules"], serde_json::json!([]));
+        assert_eq!(body["weeklyStats"]["dailyStats"].as_array().unwrap().len(), 7);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn dashboard_is_computed_in_user_timezone() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        let mut user = logged_in.user.into_active_model();
+        user.timezone = ActiveValue::Set(Some("America/New_York".to_string()));
+        let user = user.update(&ctx.db).await.unwrap();
+
+        let aspirin = prepare_data::create_medicine(&ctx, user.id, "アスピリン").await;
+        prepare_data::create_medication_schedule(&ctx, aspirin.id, "08:00").await;
+        prepare_data::create_medication_schedule(&ctx, aspirin.id, "20:00").await;
+        // 昨日の20時（UTCでは今日）は昨日の記録として数える
+        prepare_data::create_medication_log(&ctx, aspirin.id, "2025-06-09T08:00:00-04:00", "completed")
+            .await;
+        prepare_data::create_medication_log(&ctx, aspirin.id, "2025-06-09T20:00:00-04:00", "missed")
+            .await;
+        let taken = prepare_data::create_medication_log(
+            &ctx,
+            aspirin.id,
+            "2025-06-10T08:00:00-04:00",
+            "completed",
+        )
+        .await;
+
+        // 頓服薬は予定にも服薬率にも含めない
+        let loxonin = prepare_data::create_medicine(&ctx, user.id, "ロキソニン").await;
+        let mut loxonin = loxonin.into_active_model();
+        loxonin.as_needed = ActiveValue::Set(true);
+        let loxonin = loxonin.update(&ctx.db).await.unwrap();
+        prepare_data::create_medication_log(&ctx, loxonin.id, "2025-06-10T09:00:00-04:00", "completed")
+            .await;
+
+        // アーカイブした薬の予定は表示しない
+        let archived = prepare_data::create_medicine(&ctx, user.id, "ビタミンC").await;
+        prepare_data::create_medication_schedule(&ctx, archived.id, "12:00").await;
+        archived.into_active_model().archive(&ctx.db).await.unwrap();
+
+        let now: DateTime<Utc> = "2025-06-10T14:00:00Z".parse().unwrap();
+        let dashboard = dashboard_for(&ctx.db, &user, now).await.unwrap();
+
+        let today: Vec<_> = dashboard
+            .today_schedules
+            .iter()
+            .map(|schedule| (schedule.scheduled_time.to_rfc3339(), schedule.status, schedule.log_id))
+            .collect();
+        assert_eq!(
+            today,
+            vec![
+                ("2025-06-10T08:00:00-04:00".to_string(), MedicationStatus::Completed, Some(taken.id)),
+                ("2025-06-10T20:00:00-04:00".to_string(), MedicationStatus::Pending, None),
+            ]
+        );
+
+        let upcoming: Vec<_> = dashboard
+            .upcoming_schedules
+            .iter()
+            .map(|schedule| (schedule.scheduled_time.to_rfc3339(), schedule.days_until))
+            .collect();
+        assert_eq!(
+            upcoming,
+            vec![
+                ("2025-06-10T20:00:00-04:00".to_string(), 0),
+                ("2025-06-11T08:00:00-04:00".to_string(), 1),
+                ("2025-06-11T20:00:00-04:00".to_string(), 1),
+                ("2025-06-12T08:00:00-04:00".to_string(), 2),
+                ("2025-06-12T20:00:00-04:00".to_string(), 2),
+            ]
+        );
+
+        let weekly = &dashboard.weekly_stats;
+        assert_eq!(weekly.daily_stats.len(), 7);
+        assert_eq!(weekly.daily_stats[0].date, NaiveDate::from_ymd_opt(2025, 6, 4).unwrap());
+        let yesterday = &weekly.daily_stats[5];
+        assert_eq!((yesterday.scheduled, yesterday.completed), (2, 1));
+        assert!((yesterday.adherence_rate - 50.0).abs() < f64::EPSILON);
+        let today = &weekly.daily_stats[6];
+        assert_eq!((today.scheduled, today.completed), (1, 1));
+        assert_eq!((weekly.total_scheduled, weekly.total_completed), (3, 2));
+        assert!((weekly.adherence_rate - 66.7).abs() < f64::EPSILON);
+
+        let recent: Vec<_> = dashboard
+            .recent_logs
+            .iter()
+            .map(|log| log.medicine.as_ref().unwrap().name.clone())
+            .collect();
+        assert_eq!(recent, ["ロキソニン", "アスピリン", "アスピリン", "アスピリン"]);
+    })
+    .await;
+}
@@ -1,12 +1,24 @@
-use backend::app::App;
+use backend::{
+    app::App,
+    models::{_entities::medication_logs, medication_logs::MedicationStatus},
+};
 use loco_rs::testing::prelude::*;
+use sea_orm::EntityTrait;
+use serde_json::json;
 use serial_test::serial;
 
+use super::prepare_data;
+
 #[tokio::test]
 #[serial]
 async fn can_get_medication_logs() {
-    request::<App, _, _>(|request, _ctx| async move {
-        let res = request.get("/api/medication_logs/").await;
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&logged_in.token);
+        let res = request
+            .get("/api/medication_logs")
+            .add_header(auth_key, auth_value)
+            .await;
         assert_eq!(res.status_code(), 200);
 
         // you can assert content like this:
@@ -14,3 +26,306 @@ async fn can_get_medication_logs() {
     })
     .await;
 }
+
+#[tokio::test]
+#[serial]
+async fn other_users_log_is_not_found() {
+    request::<App, _, _>(|request, ctx| async move {
+        let owner = prepare_data::init_user_login(&request, &ctx).await;
+        let medicine = prepare_data::create_medicine(&ctx, owner.user.id, "アスピリン").await;
+        let log = prepare_data::create_medication_log(
+            &ctx,
+            medicine.id,
+            "2025-06-01T08:00:00+09:00",
+            "pending",
+        )
+        .await;
+        let other =
+            prepare_data::init_user_login_with_email(&request, &ctx, "other@loco.com").await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&other.token);
+        let path = format!("/api/medication_logs/{}", log.id);
+        let params = json!({
+            "medicine_id": medicine.id,
+            "scheduled_time": "2025-06-01T08:00:00+09:00",
+            "status": "completed",
+        });
+
+        let res = request
+            .get("/api/medication_logs")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(res.json::<serde_json::Value>()["results"], json!([]));
+
+        let res = request
+            .get(&path)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(res.status_code(), 404);
+
+        let res = request
+            .put(&path)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&params)
+            .await;
+        assert_eq!(res.status_code(), 404);
+
+        // 他のユーザーの薬の服薬記録は作成できない
+        let res = request
+            .post("/api/medication_logs")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&params)
+            .await;
+        assert_eq!(res.status_code(), 404);
+
+        let res = request
+            .delete(&path)
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(res.status_code(), 404);
+
+        let log = medication_logs::Entity::find_by_id(log.id)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(log.status, MedicationStatus::Pending);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_create_update_and_delete_log() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        let medicine = prepare_data::create_medicine(&ctx, logged_in.user.id, "アスピリン").await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&logged_in.token);
+
+        let res = request
+            .post("/api/medication_logs")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({
+                "medicine_id": medicine.id,
+                "scheduled_time": "2025-06-01T08:00:00+09:00",
+                "status": "pending",
+            }))
+            .await;
+        assert_eq!(res.status_code(), 200);
+        let path = format!("/api/medication_logs/{}", res.json::<serde_json::Value>()["id"]);
+
+        let res = request
+            .put(&path)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({
+                "medicine_id": medicine.id,
+                "scheduled_time": "2025-06-01T08:00:00+09:00",
+                "taken_time": "2025-06-01T08:05:00+09:00",
+                "status": "completed",
+                "notes": "朝食後",
+            }))
+            .await;
+        assert_eq!(res.status_code(), 200);
+
+        let res = request
+            .get(&path)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(res.status_code(), 200);
+        let log = res.json::<serde_json::Value>();
+        assert_eq!(log["status"], "completed");
+        assert_eq!(log["notes"], "朝食後");
+
+        let res = request
+            .delete(&path)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(res.status_code(), 200);
+
+        let res = request
+            .get("/api/medication_logs")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(res.json::<serde_json::Value>()["results"], json!([]));
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn duplicate_scheduled_time_is_rejected() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        let medicine = prepare_data::create_medicine(&ctx, logged_in.user.id, "アスピリン").await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&logged_in.token);
+
+        let mut ids = Vec::new();
+        for scheduled_time in ["2025-06-01T08:00:00+09:00", "2025-06-01T20:00:00+09:00"] {
+            let res = request
+                .post("/api/medication_logs")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&json!({
+                    "medicine_id": medicine.id,
+                    "scheduled_time": scheduled_time,
+                    "status": "pending",
+                }))
+                .await;
+            assert_eq!(res.status_code(), 200);
+            ids.push(res.json::<serde_json::Value>()["id"].clone());
+        }
+
+        let res = request
+            .post("/api/medication_logs")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({
+                "medicine_id": medicine.id,
+                "scheduled_time": "2025-06-01T08:00:00+09:00",
+                "status": "pending",
+            }))
+            .await;
+        assert_eq!(res.status_code(), 400);
+
+        let res = request
+            .put(&format!("/api/medication_logs/{}", ids[1]))
+            .add_header(auth_key, auth_value)
+            .json(&json!({
+                "medicine_id": medicine.id,
+                "scheduled_time": "2025-06-01T08:00:00+09:00",
+                "status": "pending",
+            }))
+            .await;
+        assert_eq!(res.status_code(), 400);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn logs_can_be_filtered_sorted_and_paginated() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        let aspirin = prepare_data::create_medicine(&ctx, logged_in.user.id, "アスピリン").await;
+        let vitamin = prepare_data::create_medicine(&ctx, logged_in.user.id, "ビタミンC").await;
+        for (day, status) in [(1, "completed"), (2, "missed"), (3, "skipped"), (4, "completed")] {
+            let scheduled_time = format!("2025-06-0{day}T12:00:00+09:00");
+            prepare_data::create_medication_log(&ctx, aspirin.id, &scheduled_time, status).await;
+        }
+        prepare_data::create_medication_log(&ctx, vitamin.id, "2025-06-02T12:00:00+09:00", "missed")
+            .await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&logged_in.token);
+
+        let scheduled_times = |body: &serde_json::Value| -> Vec<String> {
+            body["results"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|log| log["scheduled_time"].as_str().unwrap()[..10].to_string())
+                .collect()
+        };
+
+        // 既定は予定時刻の新しい順
+        let res = request
+            .get("/api/medication_logs")
+            .add_query_param("medicine_id", aspirin.id)
+            .add_query_param("page_size", 3)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(res.status_code(), 200);
+        let body = res.json::<serde_json::Value>();
+        assert_eq!(scheduled_times(&body), ["2025-06-04", "2025-06-03", "2025-06-02"]);
+        assert_eq!(
+            body["pagination"],
+            json!({ "page": 1, "page_size": 3, "total_pages": 2, "total_items": 4 })
+        );
+
+        let res = request
+            .get("/api/medication_logs")
+            .add_query_param("medicine_id", aspirin.id)
+            .add_query_param("page_size", 3)
+            .add_query_param("page", 2)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(scheduled_times(&res.json::<serde_json::Value>()), ["2025-06-01"]);
+
+        let res = request
+            .get("/api/medication_logs")
+            .add_query_param("status", "missed,skipped")
+            .add_query_param("date_from", "2025-06-02T00:00:00+09:00")
+            .add_query_param("date_to", "2025-06-03T00:00:00+09:00")
+            .add_query_param("sort", "asc")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        let body = res.json::<serde_json::Value>();
+        assert_eq!(body["pagination"]["total_items"], 2);
+        assert!(body["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|log| log["status"] == "missed"));
+
+        let res = request
+            .get("/api/medication_logs")
+            .add_query_param("status", "forgotten")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(res.status_code(), 400);
+
+        let res = request
+            .get("/api/medication_logs")
+            .add_query_param("page_size", 0)
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(res.status_code(), 400);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn status_changes_follow_transition_rules() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        let medicine = prepare_data::create_medicine(&ctx, logged_in.user.id, "アスピリン").await;
+        let log = prepare_data::create_medication_log(
+            &ctx,
+            medicine.id,
+            "2025-06-01T08:00:00+09:00",
+            "skipped",
+        )
+        .await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&logged_in.token);
+        let path = format!("/api/medication_logs/{}", log.id);
+        let params = |status: &str, force: bool| {
+            json!({
+                "medicine_id": medicine.id,
+                "scheduled_time": "2025-06-01T08:00:00+09:00",
+                "status": status,
+                "force": force,
+            })
+        };
+
+        let res = request
+            .put(&path)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&params("complete", false))
+            .await;
+        assert_eq!(res.status_code(), 400);
+        assert!(res.text().contains("pending, completed, missed, skipped, snoozed"));
+
+        // スキップした服薬は上書きを指定しないと完了にできない
+        let res = request
+            .put(&path)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&params("completed", false))
+            .await;
+        assert_eq!(res.status_code(), 400);
+
+        let res = request
+            .put(&path)
+            .add_header(auth_key, auth_value)
+            .json(&params("completed", true))
+            .await;
+        assert_eq!(res.status_code(), 200);
+        assert_eq!(res.json::<serde_json::Value>()["status"], "completed");
+    })
+    .await;
+}
@@ -1,12 +1,21 @@
-use backend::app::App;
-use loco_rs::testing::prelude::*;
+use backend::{app::App, models::_entities::medication_schedules};
+use loco_rs::{app::AppContext, testing::prelude::*, TestServer};
+use sea_orm::{ActiveModelTrait, ActiveValue, EntityTrait, IntoActiveModel};
+use serde_json::json;
 use serial_test::serial;
 
+use super::prepare_data;
+
 #[tokio::test]
 #[serial]
 async fn can_get_medication_schedules() {
-    request::<App, _, _>(|request, _ctx| async move {
-        let res = request.get("/api/medication_schedules/").await;
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&logged_in.token);
+        let res = request
+            .get("/api/medication_schedules")
+            .add_header(auth_key, auth_value)
+            .await;
         assert_eq!(res.status_code(), 200);
 
         // you can assert content like this:
@@ -14,3 +23,325 @@ async fn can_get_medication_schedules() {
     })
     .await;
 }
+
+/// ニューヨークのユーザーの薬を作成し、ログインしたユーザーと薬のIDを返す
+async fn create_medicine_in_new_york(
+    request: &TestServer,
+    ctx: &AppContext,
+) -> (prepare_data::LoggedInUser, i32) {
+    let logged_in = prepare_data::init_user_login(request, ctx).await;
+    let mut user = logged_in.user.into_active_model();
+    user.timezone = ActiveValue::Set(Some("America/New_York".to_string()));
+    let user = user.update(&ctx.db).await.unwrap();
+    let medicine_id = prepare_data::create_medicine(ctx, user.id, "アスピリン").await.id;
+    (
+        prepare_data::LoggedInUser {
+            user,
+            token: logged_in.token,
+        },
+        medicine_id,
+    )
+}
+
+#[tokio::test]
+#[serial]
+async fn occurrences_are_listed_in_user_timezone() {
+    request::<App, _, _>(|request, ctx| async move {
+        let (logged_in, medicine_id) = create_medicine_in_new_york(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&logged_in.token);
+        let schedule =
+            prepare_data::create_medication_schedule(&ctx, medicine_id, "02:30").await;
+
+        // 2025-03-09 は夏時間の開始日で、02:30 は 03:00 に通知する
+        let res = request
+            .get(&format!("/api/medication_schedules/{}/occurrences", schedule.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .add_query_param("from", "2025-03-08T00:00:00Z")
+            .add_query_param("to", "2025-03-11T00:00:00Z")
+            .await;
+        assert_eq!(res.status_code(), 200);
+        assert_eq!(
+            res.json::<serde_json::Value>(),
+            json!({
+                "timezone": "America/New_York",
+                "occurrences": [
+                    "2025-03-08T02:30:00-05:00",
+                    "2025-03-09T03:00:00-04:00",
+                    "2025-03-10T02:30:00-04:00",
+                ],
+            })
+        );
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn occurrences_follow_rrule_and_course() {
+    request::<App, _, _>(|request, ctx| async move {
+        let (logged_in, medicine_id) = create_medicine_in_new_york(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&logged_in.token);
+        let schedule =
+            prepare_data::create_medication_schedule(&ctx, medicine_id, "08:00").await;
+        let mut schedule = schedule.into_active_model();
+        schedule.frequency = ActiveValue::Set("rrule".to_string());
+        schedule.rrule = ActiveValue::Set(Some("FREQ=MONTHLY;BYDAY=1MO".to_string()));
+        schedule.start_date = ActiveValue::Set(chrono::NaiveDate::from_ymd_opt(2025, 6, 1));
+        schedule.max_doses = ActiveValue::Set(Some(2));
+        let schedule: medication_schedules::Model = schedule.update(&ctx.db).await.unwrap();
+
+        let res = request
+            .get(&format!("/api/medication_schedules/{}/occurrences", schedule.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .add_query_param("from", "2025-06-01T00:00:00Z")
+            .add_query_param("to", "2025-12-31T00:00:00Z")
+            .await;
+        assert_eq!(res.status_code(), 200);
+        assert_eq!(
+            res.json::<serde_json::Value>()["occurrences"],
+            json!(["2025-06-02T08:00:00-04:00", "2025-07-07T08:00:00-04:00"])
+        );
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn unsaved_schedule_can_be_previewed() {
+    request::<App, _, _>(|request, ctx| async move {
+        let (logged_in, medicine_id) = create_medicine_in_new_york(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&logged_in.token);
+
+        let res = request
+            .post("/api/medication_schedules/occurrences")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .add_query_param("from", "2025-06-01T00:00:00Z")
+            .add_query_param("to", "2025-06-02T00:00:00Z")
+            .json(&json!({
+                "medicine_id": medicine_id,
+                "time_of_day": "08:00:00",
+                "frequency": "custom",
+                "active": true,
+                "interval_count": 8,
+                "interval_unit": "hours",
+                "start_date": "2025-06-01",
+            }))
+            .await;
+        assert_eq!(res.status_code(), 200);
+        assert_eq!(
+            res.json::<serde_json::Value>()["occurrences"],
+            json!(["2025-06-01T08:00:00-04:00", "2025-06-01T16:00:00-04:00"])
+        );
+
+        // 保存時と同じく検証する
+        let res = request
+            .post("/api/medication_schedules/occurrences")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({
+                "medicine_id": medicine_id,
+                "time_of_day": "08:00:00",
+                "frequency": "rrule",
+                "rrule": "FREQ=SOMETIMES",
+                "start_date": "2025-06-01",
+            }))
+            .await;
+        assert_eq!(res.status_code(), 400);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn occurrences_reject_invalid_range() {
+    request::<App, _, _>(|request, ctx| async move {
+        let (logged_in, medicine_id) = create_medicine_in_new_york(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&logged_in.token);
+        let schedule =
+            prepare_data::create_medication_schedule(&ctx, medicine_id, "08:00").await;
+
+        let res = request
+            .get(&format!("/api/medication_schedules/{}/occurrences", schedule.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .add_query_param("from", "2025-06-02T00:00:00Z")
+            .add_query_param("to", "2025-06-01T00:00:00Z")
+            .await;
+        assert_eq!(res.status_code(), 400);
+
+        let res = request
+            .get(&format!("/api/medication_schedules/{}/occurrences", schedule.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .add_query_param("from", "2025-01-01T00:00:00Z")
+            .add_query_param("to", "2027-01-01T00:00:00Z")
+            .await;
+        assert_eq!(res.status_code(), 400);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn other_users_schedule_is_not_found() {
+    request::<App, _, _>(|request, ctx| async move {
+        let owner = prepare_data::init_user_login(&request, &ctx).await;
+        let medicine = prepare_data::create_medicine(&ctx, owner.user.id, "アスピリン").await;
+        let schedule = prepare_data::create_medication_schedule(&ctx, medicine.id, "08:00").await;
+        let other =
+            prepare_data::init_user_login_with_email(&request, &ctx, "other@loco.com").await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&other.token);
+        let path = format!("/api/medication_schedules/{}", schedule.id);
+        let params = json!({
+            "medicine_id": medicine.id,
+            "time_of_day": "09:00:00",
+            "frequency": "daily",
+        });
+
+        let res = request
+            .get("/api/medication_schedules")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(res.json::<serde_json::Value>(), json!([]));
+
+        let res = request
+            .get(&path)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(res.status_code(), 404);
+
+        let res = request
+            .get(&format!("{path}/occurrences"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(res.status_code(), 404);
+
+        let res = request
+            .put(&path)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&params)
+            .await;
+        assert_eq!(res.status_code(), 404);
+
+        // 他のユーザーの薬にはスケジュールを追加できない
+        let res = request
+            .post("/api/medication_schedules")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&params)
+            .await;
+        assert_eq!(res.status_code(), 404);
+
+        let res = request
+            .delete(&path)
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(res.status_code(), 404);
+
+        let schedule = medication_schedules::Entity::find_by_id(schedule.id)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(schedule.time_of_day.to_string(), "08:00:00");
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_create_update_and_delete_schedule() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        let medicine = prepare_data::create_medicine(&ctx, logged_in.user.id, "アスピリン").await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&logged_in.token);
+
+        let res = request
+            .post("/api/medication_schedules")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({
+                "medicine_id": medicine.id,
+                "time_of_day": "08:00:00",
+                "frequency": "daily",
+                "active": true,
+            }))
+            .await;
+        assert_eq!(res.status_code(), 200);
+        let path = format!(
+            "/api/medication_schedules/{}",
+            res.json::<serde_json::Value>()["id"]
+        );
+
+        let res = request
+            .patch(&path)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({
+                "medicine_id": medicine.id,
+                "time_of_day": "21:30:45",
+                "frequency": "weekly",
+                "days_of_week": [1, 3, 5],
+                "active": true,
+            }))
+            .await;
+        assert_eq!(res.status_code(), 200);
+
+        let res = request
+            .get("/api/medication_schedules")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        let schedules = res.json::<serde_json::Value>();
+        assert_eq!(schedules.as_array().unwrap().len(), 1);
+        // 秒は切り捨てて保存する
+        assert_eq!(schedules[0]["time_of_day"], "21:30:00");
+        assert_eq!(schedules[0]["frequency"], "weekly");
+
+        let res = request
+            .delete(&path)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(res.status_code(), 200);
+
+        let res = request
+            .get(&path)
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(res.status_code(), 404);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn archived_medicine_cannot_be_scheduled() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        let medicine = prepare_data::create_medicine(&ctx, logged_in.user.id, "アスピリン").await;
+        let schedule = prepare_data::create_medication_schedule(&ctx, medicine.id, "08:00").await;
+        medicine.into_active_model().archive(&ctx.db).await.unwrap();
+        let (auth_key, auth_value) = prepare_data::auth_header(&logged_in.token);
+        let params = json!({
+            "medicine_id": schedule.medicine_id,
+            "time_of_day": "08:00:00",
+            "frequency": "daily",
+            "active": true,
+        });
+
+        let res = request
+            .post("/api/medication_schedules")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&params)
+            .await;
+        assert_eq!(res.status_code(), 400);
+
+        // アーカイブで止めたスケジュールも再開できない
+        let res = request
+            .patch(&format!("/api/medication_schedules/{}", schedule.id))
+            .add_header(auth_key, auth_value)
+            .json(&params)
+            .await;
+        assert_eq!(res.status_code(), 400);
+        let paused = medication_schedules::Entity::find_by_id(schedule.id)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(paused.active, Some(false));
+    })
+    .await;
+}
@@ -1,12 +1,21 @@
-use backend::app::App;
-use loco_rs::testing::prelude::*;
+use backend::{
+    app::App,
+    models::_entities::{medication_logs, medication_schedules, medicines},
+};
+use loco_rs::{app::AppContext, testing::prelude::*, TestServer};
+use sea_orm::{ActiveModelTrait, ActiveValue, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter};
+use serde_json::json;
 use serial_test::serial;
 
+use super::prepare_data;
+
 #[tokio::test]
 #[serial]
 async fn can_get_medicines() {
-    request::<App, _, _>(|request, _ctx| async move {
-        let res = request.get("/api/medicines/").await;
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&logged_in.token);
+        let res = request.get("/api/medicines").add_header(auth_key, auth_value).await;
         assert_eq!(res.status_code(), 200);
 
         // you can assert content like this:
@@ -14,3 +23,407 @@ async fn can_get_medicines() {
     })
     .await;
 }
+
+/// 頓服薬を登録したユーザーを作成する
+async fn create_prn_medicine(
+    request: &TestServer,
+    ctx: &AppContext,
+) -> (prepare_data::LoggedInUser, medicines::Model) {
+    let logged_in = prepare_data::init_user_login(request, ctx).await;
+    let medicine = prepare_data::create_medicine(ctx, logged_in.user.id, "ロキソニン").await;
+    let mut medicine = medicine.into_active_model();
+    medicine.as_needed = ActiveValue::Set(true);
+    medicine.min_interval_minutes = ActiveValue::Set(Some(240));
+    medicine.max_daily_doses = ActiveValue::Set(Some(1));
+    let medicine = medicine.update(&ctx.db).await.unwrap();
+    (logged_in, medicine)
+}
+
+#[tokio::test]
+#[serial]
+async fn prn_dose_is_logged_without_schedule() {
+    request::<App, _, _>(|request, ctx| async move {
+        let (logged_in, medicine) = create_prn_medicine(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&logged_in.token);
+
+        let res = request
+            .post(&format!("/api/medicines/{}/doses", medicine.id))
+            .add_header(auth_key, auth_value)
+            .json(&json!({ "notes": "頭痛" }))
+            .await;
+        assert_eq!(res.status_code(), 200);
+        let body = res.json::<serde_json::Value>();
+        assert_eq!(body["warnings"], json!([]));
+        assert_eq!(body["log"]["status"], "completed");
+        assert_eq!(body["log"]["notes"], "頭痛");
+
+        let log = medication_logs::Entity::find_by_id(body["log"]["id"].as_i64().unwrap() as i32)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(log.medicine_id, medicine.id);
+        assert_eq!(log.taken_time, Some(log.scheduled_time));
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn prn_dose_too_soon_and_over_daily_limit_is_warned() {
+    request::<App, _, _>(|request, ctx| async move {
+        let (logged_in, medicine) = create_prn_medicine(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&logged_in.token);
+        prepare_data::link_line_user(&ctx, logged_in.user, "U4af4980629a0a1b2c3d4e5f6a7b8c9d0").await;
+        let path = format!("/api/medicines/{}/doses", medicine.id);
+
+        request
+            .post(&path)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({}))
+            .await
+            .assert_status_ok();
+
+        // 警告があっても服用は記録する
+        let res = request
+            .post(&path)
+            .add_header(auth_key, auth_value)
+            .json(&json!({ "line_alert": true }))
+            .await;
+        assert_eq!(res.status_code(), 200);
+        let body = res.json::<serde_json::Value>();
+        let codes: Vec<_> = body["warnings"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|warning| warning["code"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(codes, vec!["too_soon", "daily_limit_exceeded"]);
+        assert_eq!(body["warnings"][1]["taken_today"], 2);
+        assert!(body["warnings"][0]["message"].as_str().is_some());
+
+        let logs = medication_logs::Entity::find()
+            .filter(medication_logs::Column::MedicineId.eq(medicine.id))
+            .all(&ctx.db)
+            .await
+            .unwrap();
+        assert_eq!(logs.len(), 2);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn scheduled_medicine_cannot_be_taken_as_needed() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        let medicine = prepare_data::create_medicine(&ctx, logged_in.user.id, "アスピリン").await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&logged_in.token);
+
+        let res = request
+            .post(&format!("/api/medicines/{}/doses", medicine.id))
+            .add_header(auth_key, auth_value)
+            .json(&json!({}))
+            .await;
+        assert_eq!(res.status_code(), 400);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn inventory_forecast_and_refill() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        let medicine = prepare_data::create_medicine(&ctx, logged_in.user.id, "アスピリン").await;
+        prepare_data::create_medication_schedule(&ctx, medicine.id, "08:00").await;
+        prepare_data::create_medication_schedule(&ctx, medicine.id, "20:00").await;
+        let path = format!("/api/medicines/{}", medicine.id);
+        let (auth_key, auth_value) = prepare_data::auth_header(&logged_in.token);
+
+        // 在庫を管理していない
+        let res = request
+            .get(&format!("{path}/inventory"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(res.status_code(), 400);
+
+        let res = request
+            .post(&format!("{path}/refill"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "quantity": 30 }))
+            .await;
+        assert_eq!(res.status_code(), 200);
+
+        let res = request
+            .get(&format!("{path}/inventory"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(res.status_code(), 200);
+        let body = res.json::<serde_json::Value>();
+        assert_eq!(body["days_remaining"], 15);
+        assert_eq!(body["doses_per_day"], "2");
+
+        let res = request
+            .post(&format!("{path}/refill"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "quantity": 0 }))
+            .await;
+        assert_eq!(res.status_code(), 400);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn other_users_medicine_is_not_found() {
+    request::<App, _, _>(|request, ctx| async move {
+        let owner = prepare_data::init_user_login(&request, &ctx).await;
+        let medicine = prepare_data::create_medicine(&ctx, owner.user.id, "アスピリン").await;
+        let other =
+            prepare_data::init_user_login_with_email(&request, &ctx, "other@loco.com").await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&other.token);
+        let path = format!("/api/medicines/{}", medicine.id);
+
+        let res = request
+            .get("/api/medicines")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(res.status_code(), 200);
+        assert_eq!(res.json::<serde_json::Value>(), json!([]));
+
+        let res = request
+            .get(&path)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(res.status_code(), 404);
+
+        let res = request
+            .put(&path)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "name": "乗っ取り" }))
+            .await;
+        assert_eq!(res.status_code(), 404);
+
+        let res = request
+            .post(&format!("{path}/refill"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "quantity": 10 }))
+            .await;
+        assert_eq!(res.status_code(), 404);
+
+        let res = request
+            .delete(&path)
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(res.status_code(), 404);
+
+        let medicine = medicines::Entity::find_by_id(medicine.id)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(medicine.name, "アスピリン");
+        assert_eq!(medicine.stock_quantity, None);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn medicines_require_login() {
+    request::<App, _, _>(|request, _ctx| async move {
+        let res = request.get("/api/medicines").await;
+        assert_eq!(res.status_code(), 401);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_create_update_and_delete_medicine() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&logged_in.token);
+
+        let res = request
+            .post("/api/medicines")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "name": "アスピリン", "dosage": "1", "unit": "錠", "active": true }))
+            .await;
+        assert_eq!(res.status_code(), 200);
+        let created = res.json::<serde_json::Value>();
+        assert_eq!(created["user_id"], logged_in.user.id);
+        let path = format!("/api/medicines/{}", created["id"]);
+
+        let res = request
+            .put(&path)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "name": "バファリン", "dosage": "2", "unit": "錠", "active": true }))
+            .await;
+        assert_eq!(res.status_code(), 200);
+
+        let res = request
+            .get(&path)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(res.status_code(), 200);
+        assert_eq!(res.json::<serde_json::Value>()["name"], "バファリン");
+
+        let res = request
+            .delete(&path)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(res.status_code(), 200);
+
+        // 削除した薬はアーカイブとして残る
+        let res = request
+            .get(&path)
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(res.status_code(), 200);
+        assert!(res.json::<serde_json::Value>()["archived_at"].is_string());
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn archived_medicine_cannot_be_updated() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        let medicine = prepare_data::create_medicine(&ctx, logged_in.user.id, "アスピリン").await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&logged_in.token);
+        let path = format!("/api/medicines/{}", medicine.id);
+
+        let res = request
+            .delete(&path)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(res.status_code(), 200);
+
+        let body = json!({ "name": "バファリン", "dosage": "2", "unit": "錠", "active": true });
+        let res = request
+            .put(&path)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&body)
+            .await;
+        assert_eq!(res.status_code(), 400);
+        let res = request
+            .patch(&path)
+            .add_header(auth_key, auth_value)
+            .json(&body)
+            .await;
+        assert_eq!(res.status_code(), 400);
+
+        // アーカイブ中のまま変わらない
+        let archived = medicines::Entity::find_by_id(medicine.id)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(archived.name, "アスピリン");
+        assert_eq!(archived.active, Some(false));
+        assert!(archived.archived_at.is_some());
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn deleted_medicine_is_archived_with_history() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        let medicine = prepare_data::create_medicine(&ctx, logged_in.user.id, "アスピリン").await;
+        let schedule = prepare_data::create_medication_schedule(&ctx, medicine.id, "08:00").await;
+        // アーカイブ前に終了していたコース
+        let finished = prepare_data::create_medication_schedule(&ctx, medicine.id, "20:00").await;
+        let mut finished = finished.into_active_model();
+        finished.active = ActiveValue::Set(Some(false));
+        let finished = finished.update(&ctx.db).await.unwrap();
+        let log = prepare_data::create_medication_log(
+            &ctx,
+            medicine.id,
+            "2025-06-01T08:00:00+09:00",
+            "completed",
+        )
+        .await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&logged_in.token);
+        let path = format!("/api/medicines/{}", medicine.id);
+
+        let res = request
+            .delete(&path)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(res.status_code(), 200);
+
+        // 薬は残り、服薬記録も消えない
+        let archived = medicines::Entity::find_by_id(medicine.id)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(archived.active, Some(false));
+        assert!(archived.archived_at.is_some());
+        assert!(medication_logs::Entity::find_by_id(log.id)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .is_some());
+        let paused = medication_schedules::Entity::find_by_id(schedule.id)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(paused.active, Some(false));
+
+        let res = request
+            .get("/api/medicines")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(res.json::<serde_json::Value>(), json!([]));
+
+        let res = request
+            .get("/api/medicines")
+            .add_query_param("include_archived", true)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        let listed = res.json::<serde_json::Value>();
+        assert_eq!(listed.as_array().unwrap().len(), 1);
+        assert_eq!(listed[0]["id"], medicine.id);
+
+        let res = request
+            .post(&format!("{path}/restore"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "reactivate_schedules": true }))
+            .await;
+        assert_eq!(res.status_code(), 200);
+        let restored = res.json::<serde_json::Value>();
+        assert_eq!(restored["active"], true);
+        assert_eq!(restored["archived_at"], serde_json::Value::Null);
+        let resumed = medication_schedules::Entity::find_by_id(schedule.id)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(resumed.active, Some(true));
+        assert!(!resumed.paused_by_archive);
+        // アーカイブで止めたものだけを再開する
+        let still_finished = medication_schedules::Entity::find_by_id(finished.id)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(still_finished.active, Some(false));
+        assert!(!still_finished.paused_by_archive);
+
+        // アーカイブしていない薬は復元できない
+        let res = request
+            .post(&format!("{path}/restore"))
+            .add_header(auth_key, auth_value)
+            .json(&json!({}))
+            .await;
+        assert_eq!(res.status_code(), 400);
+    })
+    .await;
+}
@@ -1,9 +1,11 @@
 mod auth;
-mod prepare_data;
+pub mod prepare_data;
 
 pub mod webhook_line;
 pub mod reports;
+pub mod dashboard;
 pub mod api_users;
 pub mod medicine;
 pub mod medication_schedule;
-pub mod medication_log;
\ No newline at end of file
+pub mod medication_log;
+pub mod notifications;
\ No newline at end of file
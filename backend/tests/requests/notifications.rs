@@ -0,0 +1,77 @@
+use backend::{
+    app::App,
+    workers::notification_worker::{NotificationWorker, NotificationWorkerArgs},
+};
+use loco_rs::{bgworker::BackgroundWorker, testing::prelude::*};
+use serial_test::serial;
+
+use super::prepare_data;
+use crate::line::mock_server;
+
+fn args(line_user_id: &str, message: &str) -> NotificationWorkerArgs {
+    NotificationWorkerArgs {
+        user_id: None,
+        line_user_id: Some(line_user_id.to_string()),
+        message: message.to_string(),
+        notification_type: "general".to_string(),
+        medicine_id: None,
+        log_id: None,
+        report: None,
+        dead_letter_id: None,
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn can_list_own_notification_history() {
+    request::<App, _, _>(|request, ctx| async move {
+        let res = request.get("/api/notifications").await;
+        assert_eq!(res.status_code(), 401);
+
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        prepare_data::link_line_user(&ctx, logged_in.user, "U-mine").await;
+        let other = prepare_data::init_user_login_with_email(&request, &ctx, "other@loco.com").await;
+        prepare_data::link_line_user(&ctx, other.user, "U-other").await;
+
+        let no_token = mock_server::without_access_token(&ctx);
+        NotificationWorker::perform_later(&no_token, args("U-mine", "first"))
+            .await
+            .unwrap();
+        NotificationWorker::perform_later(&ctx, args("U-mine", "second"))
+            .await
+            .unwrap();
+        NotificationWorker::perform_later(&ctx, args("U-other", "other"))
+            .await
+            .unwrap();
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&logged_in.token);
+        let res = request
+            .get("/api/notifications")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(res.status_code(), 200);
+        let body = res.json::<serde_json::Value>();
+        assert_eq!(body["pagination"]["total_items"], 2);
+        let results = body["results"].as_array().unwrap();
+        let statuses: Vec<_> = results.iter().map(|log| log["status"].clone()).collect();
+        assert_eq!(statuses, ["skipped", "failed"]);
+        assert!(results.iter().all(|log| log["recipient"] == "U-mine"));
+        assert!(results[1]["error"]
+            .as_str()
+            .unwrap()
+            .contains("access token is not configured"));
+
+        let res = request
+            .get("/api/notifications?status=failed")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(res.json::<serde_json::Value>()["pagination"]["total_items"], 1);
+
+        let res = request
+            .get("/api/notifications?page_size=0")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(res.status_code(), 400);
+    })
+    .await;
+}
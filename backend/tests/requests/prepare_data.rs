@@ -1,6 +1,14 @@
 use axum::http::{HeaderName, HeaderValue};
-use backend::{models::users, views::auth::LoginResponse};
+use backend::{
+    models::{
+        _entities::{medication_logs, medication_schedules, medicines},
+        users,
+    },
+    views::auth::LoginResponse,
+};
+use chrono::{DateTime, FixedOffset, NaiveTime};
 use loco_rs::{app::AppContext, TestServer};
+use sea_orm::{ActiveModelTrait, ActiveValue, IntoActiveModel};
 
 const USER_EMAIL: &str = "test@loco.com";
 const USER_PASSWORD: &str = "1234";
@@ -11,9 +19,18 @@ pub struct LoggedInUser {
 }
 
 pub async fn init_user_login(request: &TestServer, ctx: &AppContext) -> LoggedInUser {
+    init_user_login_with_email(request, ctx, USER_EMAIL).await
+}
+
+/// 指定したメールアドレスでユーザーを登録してログインする
+pub async fn init_user_login_with_email(
+    request: &TestServer,
+    ctx: &AppContext,
+    email: &str,
+) -> LoggedInUser {
     let register_payload = serde_json::json!({
         "name": "loco",
-        "email": USER_EMAIL,
+        "email": email,
         "password": USER_PASSWORD
     });
 
@@ -22,15 +39,14 @@ pub async fn init_user_login(request: &TestServer, ctx: &AppContext) -> LoggedIn
         .post("/api/auth/register")
         .json(&register_payload)
         .await;
-    let _user = users::Model::find_by_email(&ctx.db, USER_EMAIL)
+    let _user = users::Model::find_by_email(&ctx.db, email)
         .await
         .unwrap();
 
-
     let response = request
         .post("/api/auth/login")
         .json(&serde_json::json!({
-            "email": USER_EMAIL,
+            "email": email,
             "password": USER_PASSWORD
         }))
         .await;
@@ -38,7 +54,7 @@ pub async fn init_user_login(request: &TestServer, ctx: &AppContext) -> LoggedIn
     let login_response: LoginResponse = serde_json::from_str(&response.text()).unwrap();
 
     LoggedInUser {
-        user: users::Model::find_by_email(&ctx.db, USER_EMAIL)
+        user: users::Model::find_by_email(&ctx.db, email)
             .await
             .unwrap(),
         token: login_response.token,
@@ -50,3 +66,65 @@ pub fn auth_header(token: &str) -> (HeaderName, HeaderValue) {
 
     (HeaderName::from_static("authorization"), auth_header_value)
 }
+
+pub async fn link_line_user(
+    ctx: &AppContext,
+    user: users::Model,
+    line_user_id: &str,
+) -> users::Model {
+    let mut user = user.into_active_model();
+    user.line_user_id = ActiveValue::Set(Some(line_user_id.to_string()));
+    user.notification_enabled = ActiveValue::Set(Some(true));
+    user.update(&ctx.db).await.unwrap()
+}
+
+pub async fn create_medicine(ctx: &AppContext, user_id: i32, name: &str) -> medicines::Model {
+    medicines::ActiveModel {
+        name: ActiveValue::Set(name.to_string()),
+        dosage: ActiveValue::Set(Some("1".to_string())),
+        unit: ActiveValue::Set(Some("錠".to_string())),
+        user_id: ActiveValue::Set(user_id),
+        active: ActiveValue::Set(Some(true)),
+        ..Default::default()
+    }
+    .insert(&ctx.db)
+    .await
+    .unwrap()
+}
+
+pub async fn create_medication_log(
+    ctx: &AppContext,
+    medicine_id: i32,
+    scheduled_time: &str,
+    status: &str,
+) -> medication_logs::Model {
+    let scheduled_time: DateTime<FixedOffset> =
+        DateTime::parse_from_rfc3339(scheduled_time).unwrap();
+    medication_logs::ActiveModel {
+        medicine_id: ActiveValue::Set(medicine_id),
+        scheduled_time: ActiveValue::Set(scheduled_time),
+        status: ActiveValue::Set(status.parse().unwrap()),
+        ..Default::default()
+    }
+    .insert(&ctx.db)
+    .await
+    .unwrap()
+}
+
+pub async fn create_medication_schedule(
+    ctx: &AppContext,
+    medicine_id: i32,
+    time_of_day: &str,
+) -> medication_schedules::Model {
+    let time_of_day = NaiveTime::parse_from_str(time_of_day, "%H:%M").unwrap();
+    medication_schedules::ActiveModel {
+        medicine_id: ActiveValue::Set(medicine_id),
+        time_of_day: ActiveValue::Set(time_of_day),
+        frequency: ActiveValue::Set("daily".to_string()),
+        active: ActiveValue::Set(Some(true)),
+        ..Default::default()
+    }
+    .insert(&ctx.db)
+    .await
+    .unwrap()
+}
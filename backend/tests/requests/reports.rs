@@ -2,16 +2,53 @@ use backend::app::App;
 use loco_rs::testing::prelude::*;
 use serial_test::serial;
 
+use super::prepare_data;
+
 #[tokio::test]
 #[serial]
 async fn can_get_reports() {
-    request::<App, _, _>(|request, _ctx| async move {
-        let res = request.get("/api/reports/").await;
+    request::<App, _, _>(|request, ctx| async move {
+        let res = request.get("/api/reports").await;
+        assert_eq!(res.status_code(), 401);
+
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        let medicine = prepare_data::create_medicine(&ctx, logged_in.user.id, "アスピリン").await;
+        for (scheduled_time, status) in [
+            ("2025-06-02T08:00:00Z", "completed"),
+            ("2025-06-03T08:00:00Z", "completed"),
+            ("2025-06-04T08:00:00Z", "completed"),
+            ("2025-06-05T08:00:00Z", "missed"),
+            // 期間外
+            ("2025-06-09T08:00:00Z", "missed"),
+        ] {
+            prepare_data::create_medication_log(&ctx, medicine.id, scheduled_time, status).await;
+        }
+        let (auth_key, auth_value) = prepare_data::auth_header(&logged_in.token);
+
+        let res = request
+            .get("/api/reports")
+            .add_query_param("report_type", "weekly")
+            .add_query_param("start_date", "2025-06-02")
+            .add_query_param("end_date", "2025-06-08")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
         assert_eq!(res.status_code(), 200);
+        let report = res.json::<serde_json::Value>();
+        assert_eq!(report["user_id"], logged_in.user.id);
+        assert_eq!(report["report_type"], "weekly");
+        assert_eq!(report["summary"]["total_scheduled"], 4);
+        assert_eq!(report["summary"]["total_taken"], 3);
+        assert_eq!(report["summary"]["total_missed"], 1);
+        assert_eq!(report["summary"]["adherence_rate"], 75.0);
+        assert_eq!(report["medicines"][0]["medicine_name"], "アスピリン");
+        assert_eq!(report["medicines"][0]["missed_times"], serde_json::json!(["08:00"]));
 
-        // you can assert content like this:
-        // assert_eq!(res.text(), "content");
+        let res = request
+            .get("/api/reports")
+            .add_query_param("report_type", "yearly")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(res.status_code(), 400);
     })
     .await;
 }
-
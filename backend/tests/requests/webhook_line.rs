@@ -1,17 +1,526 @@
-use backend::app::App;
-use loco_rs::testing::prelude::*;
+use axum::{body::Bytes, http::StatusCode};
+use backend::{
+    app::App,
+    line::{handler, signature, webhook::WebhookRequest},
+    models::{
+        _entities::medication_logs,
+        medication_logs::{self as medication_logs_model, MedicationStatus},
+        users,
+    },
+};
+use loco_rs::{testing::prelude::*, TestServer};
+use sea_orm::EntityTrait;
 use serial_test::serial;
 
+use super::prepare_data;
+use crate::line::mock_server::{self, MockLineServer};
+
+/// config/test.yaml の `settings.line.channel_secret`
+const CHANNEL_SECRET: &str = "test-channel-secret";
+const LINE_USER_ID: &str = "U4af4980629a0a1b2c3d4e5f6a7b8c9d0";
+const WEBHOOK_PATH: &str = "/api/webhook/line";
+
+/// 録画済みのテキストメッセージイベントの本文だけを差し替える
+fn text_message_payload(text: &str) -> String {
+    let mut payload: serde_json::Value =
+        serde_json::from_str(include_str!("../fixtures/line/message_text.json")).unwrap();
+    payload["events"][0]["message"]["text"] = serde_json::Value::from(text);
+    payload.to_string()
+}
+
+/// 録画済みのポストバックイベントのデータだけを差し替える
+fn postback_payload(data: &str) -> String {
+    let mut payload: serde_json::Value =
+        serde_json::from_str(include_str!("../fixtures/line/postback.json")).unwrap();
+    payload["events"][0]["postback"]["data"] = serde_json::Value::from(data);
+    payload.to_string()
+}
+
+async fn post_signed(request: &TestServer, payload: &str) -> StatusCode {
+    request
+        .post(WEBHOOK_PATH)
+        .add_header(
+            signature::SIGNATURE_HEADER,
+            signature::sign(CHANNEL_SECRET, payload.as_bytes()),
+        )
+        .content_type("application/json")
+        .bytes(Bytes::copy_from_slice(payload.as_bytes()))
+        .await
+        .status_code()
+}
+
+#[tokio::test]
+#[serial]
+async fn rejects_request_without_signature() {
+    request::<App, _, _>(|request, _ctx| async move {
+        let payload = include_str!("../fixtures/line/message_text.json");
+        let res = request
+            .post(WEBHOOK_PATH)
+            .content_type("application/json")
+            .bytes(Bytes::from_static(payload.as_bytes()))
+            .await;
+        assert_eq!(res.status_code(), 401);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn rejects_request_with_invalid_signature() {
+    request::<App, _, _>(|request, _ctx| async move {
+        let payload = include_str!("../fixtures/line/message_text.json");
+        let res = request
+            .post(WEBHOOK_PATH)
+            .add_header(
+                signature::SIGNATURE_HEADER,
+                signature::sign("another-channel-secret", payload.as_bytes()),
+            )
+            .content_type("application/json")
+            .bytes(Bytes::from_static(payload.as_bytes()))
+            .await;
+        assert_eq!(res.status_code(), 401);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn accepts_webhook_verification() {
+    request::<App, _, _>(|request, _ctx| async move {
+        let status = post_signed(&request, include_str!("../fixtures/line/verify.json")).await;
+        assert_eq!(status, 200);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_receive_message_and_postback_events() {
+    request::<App, _, _>(|request, _ctx| async move {
+        let status =
+            post_signed(&request, include_str!("../fixtures/line/message_text.json")).await;
+        assert_eq!(status, 200);
+
+        let status = post_signed(&request, include_str!("../fixtures/line/postback.json")).await;
+        assert_eq!(status, 200);
+    })
+    .await;
+}
+
 #[tokio::test]
 #[serial]
-async fn can_get_webhook_lines() {
+async fn ignores_unsupported_events_in_batch() {
     request::<App, _, _>(|request, _ctx| async move {
-        let res = request.get("/api/webhook_lines/").await;
-        assert_eq!(res.status_code(), 200);
+        let status = post_signed(&request, include_str!("../fixtures/line/mixed_batch.json")).await;
+        assert_eq!(status, 200);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn follow_and_unfollow_toggle_notifications() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        prepare_data::link_line_user(&ctx, logged_in.user, LINE_USER_ID).await;
+
+        let status = post_signed(&request, include_str!("../fixtures/line/unfollow.json")).await;
+        assert_eq!(status, 200);
+        let user = users::Model::find_by_line_user_id(&ctx.db, LINE_USER_ID)
+            .await
+            .unwrap();
+        assert_eq!(user.notification_enabled, Some(false));
+
+        let status = post_signed(&request, include_str!("../fixtures/line/follow.json")).await;
+        assert_eq!(status, 200);
+        let user = users::Model::find_by_line_user_id(&ctx.db, LINE_USER_ID)
+            .await
+            .unwrap();
+        assert_eq!(user.notification_enabled, Some(true));
+    })
+    .await;
+}
 
-        // you can assert content like this:
-        // assert_eq!(res.text(), "content");
+#[tokio::test]
+#[serial]
+async fn completes_latest_open_log_from_text_reply() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        let user = prepare_data::link_line_user(&ctx, logged_in.user, LINE_USER_ID).await;
+        let medicine = prepare_data::create_medicine(&ctx, user.id, "ロキソニン").await;
+        let older = prepare_data::create_medication_log(
+            &ctx,
+            medicine.id,
+            "2025-06-08T08:00:00+09:00",
+            "missed",
+        )
+        .await;
+        let latest = prepare_data::create_medication_log(
+            &ctx,
+            medicine.id,
+            "2025-06-08T12:00:00+09:00",
+            "pending",
+        )
+        .await;
+
+        let status = post_signed(&request, &text_message_payload("服薬完了")).await;
+        assert_eq!(status, 200);
+
+        let latest = medication_logs::Entity::find_by_id(latest.id)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(latest.status, MedicationStatus::Completed);
+        assert!(latest.taken_time.is_some());
+
+        let older = medication_logs::Entity::find_by_id(older.id)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(older.status, MedicationStatus::Missed);
+
+        // 次の返信は残っている飲み忘れログに適用される
+        let status = post_signed(&request, &text_message_payload("飲んだ")).await;
+        assert_eq!(status, 200);
+        let older = medication_logs::Entity::find_by_id(older.id)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(older.status, MedicationStatus::Completed);
     })
     .await;
 }
 
+#[tokio::test]
+#[serial]
+async fn applies_skip_missed_and_snooze_replies() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        let user = prepare_data::link_line_user(&ctx, logged_in.user, LINE_USER_ID).await;
+        let medicine = prepare_data::create_medicine(&ctx, user.id, "アスピリン").await;
+        let log = prepare_data::create_medication_log(
+            &ctx,
+            medicine.id,
+            "2025-06-08T21:00:00+09:00",
+            "pending",
+        )
+        .await;
+
+        let find_log = || async {
+            medication_logs::Entity::find_by_id(log.id)
+                .one(&ctx.db)
+                .await
+                .unwrap()
+                .unwrap()
+        };
+
+        let status = post_signed(&request, &text_message_payload("あとで")).await;
+        assert_eq!(status, 200);
+        assert_eq!(find_log().await.status, MedicationStatus::Snoozed);
+
+        let status = post_signed(&request, &text_message_payload("飲み忘れ")).await;
+        assert_eq!(status, 200);
+        let missed = find_log().await;
+        assert_eq!(missed.status, MedicationStatus::Missed);
+        assert!(missed.taken_time.is_none());
+
+        let status = post_signed(&request, &text_message_payload("スキップ")).await;
+        assert_eq!(status, 200);
+        assert_eq!(find_log().await.status, MedicationStatus::Skipped);
+
+        // 記録待ちのログが無くなった後の返信は何も変更しない
+        let status = post_signed(&request, &text_message_payload("服薬完了")).await;
+        assert_eq!(status, 200);
+        assert_eq!(find_log().await.status, MedicationStatus::Skipped);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn snooze_reply_defers_reminder_up_to_the_limit() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        let user = prepare_data::link_line_user(&ctx, logged_in.user, LINE_USER_ID).await;
+        let medicine = prepare_data::create_medicine(&ctx, user.id, "アスピリン").await;
+        let log = prepare_data::create_medication_log(
+            &ctx,
+            medicine.id,
+            "2025-06-08T21:00:00+09:00",
+            "pending",
+        )
+        .await;
+
+        let find_log = || async {
+            medication_logs::Entity::find_by_id(log.id)
+                .one(&ctx.db)
+                .await
+                .unwrap()
+                .unwrap()
+        };
+
+        let before = chrono::Local::now();
+        let status = post_signed(&request, &text_message_payload("後で通知")).await;
+        assert_eq!(status, 200);
+        let snoozed = find_log().await;
+        assert_eq!(snoozed.status, MedicationStatus::Snoozed);
+        assert_eq!(snoozed.snooze_count, 1);
+        let snoozed_until = snoozed.snoozed_until.expect("snoozed_until should be set");
+        assert!(
+            snoozed_until >= before + chrono::Duration::minutes(medication_logs_model::SNOOZE_MINUTES)
+        );
+
+        for _ in 1..medication_logs_model::MAX_SNOOZE_COUNT {
+            post_signed(&request, &text_message_payload("あとで")).await;
+        }
+        let at_limit = find_log().await;
+        assert_eq!(at_limit.snooze_count, medication_logs_model::MAX_SNOOZE_COUNT);
+
+        // 上限に達した後の延期は何も変更しない
+        let status = post_signed(&request, &text_message_payload("あとで")).await;
+        assert_eq!(status, 200);
+        let after_limit = find_log().await;
+        assert_eq!(after_limit.snooze_count, medication_logs_model::MAX_SNOOZE_COUNT);
+        assert_eq!(after_limit.snoozed_until, at_limit.snoozed_until);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn ignores_replies_from_unlinked_line_user() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        let medicine = prepare_data::create_medicine(&ctx, logged_in.user.id, "ロキソニン").await;
+        let log = prepare_data::create_medication_log(
+            &ctx,
+            medicine.id,
+            "2025-06-08T08:00:00+09:00",
+            "pending",
+        )
+        .await;
+
+        let status = post_signed(&request, &text_message_payload("服薬完了")).await;
+        assert_eq!(status, 200);
+
+        let log = medication_logs::Entity::find_by_id(log.id)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(log.status, MedicationStatus::Pending);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn postback_updates_exactly_the_referenced_log() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        let user = prepare_data::link_line_user(&ctx, logged_in.user, LINE_USER_ID).await;
+        let medicine = prepare_data::create_medicine(&ctx, user.id, "ロキソニン").await;
+        let morning = prepare_data::create_medication_log(
+            &ctx,
+            medicine.id,
+            "2025-06-08T08:00:00+09:00",
+            "pending",
+        )
+        .await;
+        let noon = prepare_data::create_medication_log(
+            &ctx,
+            medicine.id,
+            "2025-06-08T12:00:00+09:00",
+            "pending",
+        )
+        .await;
+
+        let data = format!(
+            "action=complete&log_id={}&medicine_id={}",
+            morning.id, medicine.id
+        );
+        let status = post_signed(&request, &postback_payload(&data)).await;
+        assert_eq!(status, 200);
+
+        let morning = medication_logs::Entity::find_by_id(morning.id)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(morning.status, MedicationStatus::Completed);
+        let taken_time = morning.taken_time;
+        assert!(taken_time.is_some());
+
+        let noon = medication_logs::Entity::find_by_id(noon.id)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(noon.status, MedicationStatus::Pending);
+
+        // 同じボタンや別のボタンを再度押しても記録済みのログは変わらない
+        let status = post_signed(&request, &postback_payload(&data)).await;
+        assert_eq!(status, 200);
+        let data = format!("action=skip&log_id={}", morning.id);
+        let status = post_signed(&request, &postback_payload(&data)).await;
+        assert_eq!(status, 200);
+
+        let morning = medication_logs::Entity::find_by_id(morning.id)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(morning.status, MedicationStatus::Completed);
+        assert_eq!(morning.taken_time, taken_time);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn repeated_snooze_postback_snoozes_once() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        let user = prepare_data::link_line_user(&ctx, logged_in.user, LINE_USER_ID).await;
+        let medicine = prepare_data::create_medicine(&ctx, user.id, "ロキソニン").await;
+        let log = prepare_data::create_medication_log(
+            &ctx,
+            medicine.id,
+            "2025-06-08T08:00:00+09:00",
+            "pending",
+        )
+        .await;
+
+        let data = format!("action=snooze&log_id={}", log.id);
+        let status = post_signed(&request, &postback_payload(&data)).await;
+        assert_eq!(status, 200);
+        let snoozed = medication_logs::Entity::find_by_id(log.id)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(snoozed.status, MedicationStatus::Snoozed);
+        assert_eq!(snoozed.snooze_count, 1);
+
+        // 再通知される前の二度押しでは延期し直さない
+        let status = post_signed(&request, &postback_payload(&data)).await;
+        assert_eq!(status, 200);
+        let log = medication_logs::Entity::find_by_id(log.id)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(log.snooze_count, 1);
+        assert_eq!(log.snoozed_until, snoozed.snoozed_until);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn postback_can_complete_a_missed_log() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        let user = prepare_data::link_line_user(&ctx, logged_in.user, LINE_USER_ID).await;
+        let medicine = prepare_data::create_medicine(&ctx, user.id, "ロキソニン").await;
+        let log = prepare_data::create_medication_log(
+            &ctx,
+            medicine.id,
+            "2025-06-08T08:00:00+09:00",
+            "missed",
+        )
+        .await;
+
+        let data = format!("action=missed&log_id={}", log.id);
+        let status = post_signed(&request, &postback_payload(&data)).await;
+        assert_eq!(status, 200);
+
+        let data = format!("action=complete&log_id={}", log.id);
+        let status = post_signed(&request, &postback_payload(&data)).await;
+        assert_eq!(status, 200);
+
+        let log = medication_logs::Entity::find_by_id(log.id)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(log.status, MedicationStatus::Completed);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn postback_ignores_logs_of_other_users() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        prepare_data::link_line_user(&ctx, logged_in.user, LINE_USER_ID).await;
+
+        let other = users::Model::create_with_password(
+            &ctx.db,
+            &users::RegisterParams {
+                email: "other@loco.com".to_string(),
+                password: "1234".to_string(),
+                name: "other".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+        let medicine = prepare_data::create_medicine(&ctx, other.id, "アスピリン").await;
+        let log = prepare_data::create_medication_log(
+            &ctx,
+            medicine.id,
+            "2025-06-08T08:00:00+09:00",
+            "pending",
+        )
+        .await;
+
+        let data = format!("action=complete&log_id={}", log.id);
+        let status = post_signed(&request, &postback_payload(&data)).await;
+        assert_eq!(status, 200);
+
+        let log = medication_logs::Entity::find_by_id(log.id)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(log.status, MedicationStatus::Pending);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn replies_through_line_reply_api() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        let user = prepare_data::link_line_user(&ctx, logged_in.user, LINE_USER_ID).await;
+        let medicine = prepare_data::create_medicine(&ctx, user.id, "ロキソニン").await;
+        prepare_data::create_medication_log(&ctx, medicine.id, "2025-06-08T08:00:00+09:00", "pending")
+            .await;
+
+        let line = MockLineServer::start().await;
+        let payload: WebhookRequest =
+            serde_json::from_str(&text_message_payload("服薬完了")).unwrap();
+        for event in payload.events {
+            handler::dispatch(&line.context(&ctx), event).await.unwrap();
+        }
+
+        let calls = line.calls();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(
+            calls[0].authorization.as_deref(),
+            Some(format!("Bearer {}", mock_server::ACCESS_TOKEN).as_str())
+        );
+        let replies = line.replies();
+        assert_eq!(replies[0]["replyToken"], "38ef843bde154d9b91c21320ffd17a0f");
+        let messages = replies[0]["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0]["type"], "text");
+        assert!(messages[0]["text"].as_str().unwrap().contains("ロキソニン"));
+    })
+    .await;
+}
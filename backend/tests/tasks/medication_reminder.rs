@@ -1,9 +1,24 @@
-use backend::app::App;
-use loco_rs::{task, testing::prelude::*};
+use backend::{
+    app::App,
+    models::{
+        _entities::{medication_logs, medication_schedules, medicines},
+        medication_logs::MedicationStatus,
+        medication_schedules::weekdays_mask,
+        notification_dead_letters, scheduler_states,
+    },
+    tasks::medication_reminder::MedicationReminderTask,
+};
+use sea_orm::{ColumnTrait, QueryFilter};
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::America::New_York;
+use loco_rs::{app::AppContext, task, testing::prelude::*, TestServer};
 
 use loco_rs::boot::run_task;
+use sea_orm::{prelude::Decimal, ActiveModelTrait, ActiveValue, EntityTrait, IntoActiveModel};
 use serial_test::serial;
 
+use crate::{line::mock_server, requests::prepare_data};
+
 #[tokio::test]
 #[serial]
 async fn test_can_run_medication_reminder() {
@@ -15,3 +30,746 @@ async fn test_can_run_medication_reminder() {
             .is_ok()
     );
 }
+
+/// 予定時刻が1時間前の未服薬ログを作成し、スヌーズ時刻を設定する
+async fn create_snoozed_log(
+    ctx: &AppContext,
+    medicine_id: i32,
+    snoozed_minutes_ago: i64,
+) -> medication_logs::Model {
+    let now = Local::now();
+    let log = prepare_data::create_medication_log(
+        ctx,
+        medicine_id,
+        &(now - Duration::hours(1)).to_rfc3339(),
+        "snoozed",
+    )
+    .await;
+
+    let mut log = log.into_active_model();
+    log.snoozed_until = ActiveValue::Set(Some((now - Duration::minutes(snoozed_minutes_ago)).into()));
+    log.snooze_count = ActiveValue::Set(1);
+    log.update(&ctx.db).await.unwrap()
+}
+
+async fn find_log(ctx: &AppContext, id: i32) -> medication_logs::Model {
+    medication_logs::Entity::find_by_id(id)
+        .one(&ctx.db)
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+#[tokio::test]
+#[serial]
+async fn missed_check_waits_for_snoozed_reminder() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        let medicine = prepare_data::create_medicine(&ctx, logged_in.user.id, "アスピリン").await;
+
+        // 予定時刻から30分経過したログ（スヌーズなし）は未服薬になる
+        let unsnoozed = prepare_data::create_medication_log(
+            &ctx,
+            medicine.id,
+            &(Local::now() - Duration::minutes(30)).to_rfc3339(),
+            "pending",
+        )
+        .await;
+        // スヌーズ直後のログはまだ未服薬にしない
+        let recently_snoozed = create_snoozed_log(&ctx, medicine.id, 5).await;
+        // 再通知から30分経過したログは未服薬になる
+        let long_snoozed = create_snoozed_log(&ctx, medicine.id, 31).await;
+
+        MedicationReminderTask
+            .check_missed_medications(&ctx, Utc::now() - Duration::minutes(5), Utc::now())
+            .await
+            .unwrap();
+
+        assert_eq!(find_log(&ctx, unsnoozed.id).await.status, MedicationStatus::Missed);
+        assert_eq!(find_log(&ctx, recently_snoozed.id).await.status, MedicationStatus::Snoozed);
+        assert_eq!(find_log(&ctx, long_snoozed.id).await.status, MedicationStatus::Missed);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn due_snoozes_are_renotified_once() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        let medicine = prepare_data::create_medicine(&ctx, logged_in.user.id, "アスピリン").await;
+
+        let due = create_snoozed_log(&ctx, medicine.id, 1).await;
+        let not_due = create_snoozed_log(&ctx, medicine.id, -10).await;
+
+        // 処理する範囲より後の再通知時刻は、実行時刻を過ぎていても対象外
+        MedicationReminderTask
+            .process_snoozed_medications(&ctx, Utc::now() - Duration::minutes(5))
+            .await
+            .unwrap();
+        assert!(find_log(&ctx, due.id).await.renotified_at.is_none());
+
+        MedicationReminderTask
+            .process_snoozed_medications(&ctx, Utc::now())
+            .await
+            .unwrap();
+
+        let due = find_log(&ctx, due.id).await;
+        let renotified_at = due.renotified_at.expect("due snooze should be renotified");
+        assert_eq!(due.status, MedicationStatus::Snoozed);
+        assert!(find_log(&ctx, not_due.id).await.renotified_at.is_none());
+
+        // 2回目の実行では再通知しない
+        MedicationReminderTask
+            .process_snoozed_medications(&ctx, Utc::now())
+            .await
+            .unwrap();
+        assert_eq!(find_log(&ctx, due.id).await.renotified_at, Some(renotified_at));
+    })
+    .await;
+}
+
+const LINE_USER_ID: &str = "U4af4980629a0a1b2c3d4e5f6a7b8c9d0";
+
+#[tokio::test]
+#[serial]
+async fn reminder_runs_notification_worker() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        let user = prepare_data::link_line_user(&ctx, logged_in.user, LINE_USER_ID).await;
+        let medicine = prepare_data::create_medicine(&ctx, user.id, "アスピリン").await;
+        let schedule =
+            prepare_data::create_medication_schedule(&ctx, medicine.id, "08:00").await;
+
+        // トークン未設定だとワーカーが届かなかった通知を保存するので、送信を試みたことが分かる
+        let no_token = mock_server::without_access_token(&ctx);
+        let current_time = utc("2025-06-08T23:00:00Z").with_timezone(&chrono_tz::Asia::Tokyo);
+        MedicationReminderTask
+            .process_medication_schedule(&no_token, &schedule, current_time)
+            .await
+            .unwrap();
+        assert_eq!(parked_notification_types(&ctx).await, ["medication_reminder"]);
+
+        let logs = medication_logs::Entity::find()
+            .filter(medication_logs::Column::MedicineId.eq(medicine.id))
+            .all(&ctx.db)
+            .await
+            .unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].status, MedicationStatus::Pending);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn missed_medication_runs_notification_worker() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        let user = prepare_data::link_line_user(&ctx, logged_in.user, LINE_USER_ID).await;
+        let medicine = prepare_data::create_medicine(&ctx, user.id, "アスピリン").await;
+        let log = prepare_data::create_medication_log(
+            &ctx,
+            medicine.id,
+            "2025-06-08T08:00:00+09:00",
+            "pending",
+        )
+        .await;
+
+        let no_token = mock_server::without_access_token(&ctx);
+        MedicationReminderTask
+            .process_missed_medication(&no_token, &log)
+            .await
+            .unwrap();
+        assert_eq!(parked_notification_types(&ctx).await, ["missed_medication"]);
+        assert_eq!(find_log(&ctx, log.id).await.status, MedicationStatus::Missed);
+
+        // 送信をスキップする設定なら保存もしない
+        MedicationReminderTask
+            .process_missed_medication(&ctx, &log)
+            .await
+            .unwrap();
+        assert_eq!(parked_notification_types(&ctx).await, ["missed_medication"]);
+    })
+    .await;
+}
+
+fn utc(rfc3339: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(rfc3339).unwrap().with_timezone(&Utc)
+}
+
+fn hm(time: &str) -> NaiveTime {
+    NaiveTime::parse_from_str(time, "%H:%M").unwrap()
+}
+
+#[test]
+fn due_local_times_include_times_skipped_by_dst_start() {
+    // 2025-03-09 02:00 EST に時計が 03:00 EDT へ進む
+    let times = MedicationReminderTask::due_local_times(New_York, utc("2025-03-09T07:00:00Z"));
+    assert_eq!(times.len(), 61);
+    assert_eq!(times.first(), Some(&hm("02:00")));
+    assert_eq!(times.last(), Some(&hm("03:00")));
+
+    let times = MedicationReminderTask::due_local_times(New_York, utc("2025-03-09T07:01:30Z"));
+    assert_eq!(times, vec![hm("03:01")]);
+}
+
+#[test]
+fn due_local_times_on_dst_end_are_the_current_minute() {
+    // 2025-11-02 02:00 EDT に時計が 01:00 EST へ戻る
+    let times = MedicationReminderTask::due_local_times(New_York, utc("2025-11-02T06:00:00Z"));
+    assert_eq!(times, vec![hm("01:00")]);
+
+    let times = MedicationReminderTask::due_local_times(New_York, utc("2025-11-02T06:30:00Z"));
+    assert_eq!(times, vec![hm("01:30")]);
+}
+
+/// LINE連携済みで指定タイムゾーンのユーザーを作成し、薬とスケジュールを登録する
+async fn create_schedule_in_timezone(
+    request: &TestServer,
+    ctx: &AppContext,
+    timezone: &str,
+    time_of_day: &str,
+) -> medication_schedules::Model {
+    let logged_in = prepare_data::init_user_login(request, ctx).await;
+    let user = prepare_data::link_line_user(ctx, logged_in.user, LINE_USER_ID).await;
+    let mut user = user.into_active_model();
+    user.timezone = ActiveValue::Set(Some(timezone.to_string()));
+    let user = user.update(&ctx.db).await.unwrap();
+
+    let medicine = prepare_data::create_medicine(ctx, user.id, "アスピリン").await;
+    prepare_data::create_medication_schedule(ctx, medicine.id, time_of_day).await
+}
+
+async fn find_logs_for(ctx: &AppContext, medicine_id: i32) -> Vec<medication_logs::Model> {
+    medication_logs::Entity::find()
+        .filter(medication_logs::Column::MedicineId.eq(medicine_id))
+        .all(&ctx.db)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+#[serial]
+async fn reminder_logs_use_user_timezone_offset_across_dst() {
+    request::<App, _, _>(|request, ctx| async move {
+        let schedule =
+            create_schedule_in_timezone(&request, &ctx, "America/New_York", "08:00").await;
+
+        for now in ["2025-03-08T13:00:00Z", "2025-03-09T12:00:00Z"] {
+            MedicationReminderTask
+                .process_medication_schedule(&ctx, &schedule, utc(now).with_timezone(&New_York))
+                .await
+                .unwrap();
+        }
+
+        let mut scheduled: Vec<_> = find_logs_for(&ctx, schedule.medicine_id)
+            .await
+            .into_iter()
+            .map(|log| log.scheduled_time)
+            .collect();
+        scheduled.sort();
+        assert_eq!(
+            scheduled,
+            vec![
+                DateTime::parse_from_rfc3339("2025-03-08T08:00:00-05:00").unwrap(),
+                DateTime::parse_from_rfc3339("2025-03-09T08:00:00-04:00").unwrap(),
+            ]
+        );
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn reminder_in_dst_gap_is_logged_when_clock_jumps() {
+    request::<App, _, _>(|request, ctx| async move {
+        // 2025-03-09 の 02:30 はニューヨークには存在しない
+        let schedule =
+            create_schedule_in_timezone(&request, &ctx, "America/New_York", "02:30").await;
+
+        MedicationReminderTask
+            .process_medication_schedule(
+                &ctx,
+                &schedule,
+                utc("2025-03-09T07:00:00Z").with_timezone(&New_York),
+            )
+            .await
+            .unwrap();
+
+        let logs = find_logs_for(&ctx, schedule.medicine_id).await;
+        assert_eq!(logs.len(), 1);
+        assert_eq!(
+            logs[0].scheduled_time,
+            New_York.with_ymd_and_hms(2025, 3, 9, 3, 0, 0).unwrap()
+        );
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn repeated_hour_on_dst_end_is_reminded_once() {
+    request::<App, _, _>(|request, ctx| async move {
+        // 2025-11-02 の 01:30 はニューヨークで2回ある
+        let schedule =
+            create_schedule_in_timezone(&request, &ctx, "America/New_York", "01:30").await;
+
+        for now in ["2025-11-02T05:30:00Z", "2025-11-02T06:30:00Z"] {
+            MedicationReminderTask
+                .process_medication_schedule(&ctx, &schedule, utc(now).with_timezone(&New_York))
+                .await
+                .unwrap();
+        }
+
+        let logs = find_logs_for(&ctx, schedule.medicine_id).await;
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].scheduled_time, utc("2025-11-02T05:30:00Z"));
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn overlapping_ticks_log_and_remind_once() {
+    request::<App, _, _>(|request, ctx| async move {
+        let schedule = create_schedule_in_timezone(&request, &ctx, "Asia/Tokyo", "08:00").await;
+
+        // 重なった2つのティックが同じ予定を同時に処理する
+        let no_token = mock_server::without_access_token(&ctx);
+        let current_time = utc("2025-06-08T23:00:00Z").with_timezone(&chrono_tz::Asia::Tokyo);
+        let (first, second) = tokio::join!(
+            MedicationReminderTask.process_medication_schedule(&no_token, &schedule, current_time),
+            MedicationReminderTask.process_medication_schedule(&no_token, &schedule, current_time),
+        );
+        first.unwrap();
+        second.unwrap();
+
+        assert_eq!(find_logs_for(&ctx, schedule.medicine_id).await.len(), 1);
+        assert_eq!(parked_notification_types(&ctx).await, ["medication_reminder"]);
+    })
+    .await;
+}
+
+/// 曜日指定のスケジュールに変更する
+async fn make_weekly(
+    ctx: &AppContext,
+    schedule: medication_schedules::Model,
+    weekdays: &[u8],
+) -> medication_schedules::Model {
+    let mut schedule = schedule.into_active_model();
+    schedule.frequency = ActiveValue::Set("weekly".to_string());
+    schedule.weekdays = ActiveValue::Set(weekdays_mask(weekdays).unwrap());
+    schedule.update(&ctx.db).await.unwrap()
+}
+
+#[tokio::test]
+#[serial]
+async fn due_schedules_match_time_weekday_and_timezone() {
+    request::<App, _, _>(|request, ctx| async move {
+        let daily =
+            create_schedule_in_timezone(&request, &ctx, "America/New_York", "08:00").await;
+        let user_id = medicines::Entity::find_by_id(daily.medicine_id)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap()
+            .user_id;
+
+        // スケジュールごとに別の薬にして、どれが処理されたかをログで区別する
+        let schedule_for = |name: &'static str, time_of_day: &'static str, weekdays: Option<&'static [u8]>| {
+            let ctx = ctx.clone();
+            async move {
+                let medicine = prepare_data::create_medicine(&ctx, user_id, name).await;
+                let schedule =
+                    prepare_data::create_medication_schedule(&ctx, medicine.id, time_of_day).await;
+                match weekdays {
+                    Some(weekdays) => make_weekly(&ctx, schedule, weekdays).await,
+                    None => schedule,
+                }
+            }
+        };
+        let later = schedule_for("later", "08:01", None).await;
+        let monday = schedule_for("monday", "08:00", Some(&[1, 3])).await;
+        let tuesday = schedule_for("tuesday", "08:00", Some(&[2])).await;
+
+        // ユーザーのタイムゾーンに該当しないグループでは何も処理しない
+        let monday_morning = utc("2025-06-09T12:00:00Z");
+        MedicationReminderTask
+            .process_due_schedules(
+                &ctx,
+                chrono_tz::Asia::Tokyo,
+                &[None],
+                monday_morning - Duration::minutes(1),
+                monday_morning,
+            )
+            .await
+            .unwrap();
+        assert!(find_logs_for(&ctx, daily.medicine_id).await.is_empty());
+
+        // 2025-06-09 は月曜日で、ニューヨークでは 08:00
+        MedicationReminderTask
+            .process_due_schedules(
+                &ctx,
+                New_York,
+                &[Some("America/New_York".to_string())],
+                monday_morning - Duration::minutes(1),
+                monday_morning,
+            )
+            .await
+            .unwrap();
+
+        for (schedule, expected) in [(&daily, 1), (&monday, 1), (&later, 0), (&tuesday, 0)] {
+            let logs = find_logs_for(&ctx, schedule.medicine_id).await;
+            assert_eq!(logs.len(), expected, "schedule at {}", schedule.time_of_day);
+            if let Some(log) = logs.first() {
+                assert_eq!(log.scheduled_time, monday_morning);
+            }
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn due_schedules_in_dst_gap_fire_when_clock_jumps() {
+    request::<App, _, _>(|request, ctx| async move {
+        let schedule =
+            create_schedule_in_timezone(&request, &ctx, "America/New_York", "02:30").await;
+
+        MedicationReminderTask
+            .process_due_schedules(
+                &ctx,
+                New_York,
+                &[Some("America/New_York".to_string())],
+                utc("2025-03-09T06:59:00Z"),
+                utc("2025-03-09T07:00:00Z"),
+            )
+            .await
+            .unwrap();
+
+        let logs = find_logs_for(&ctx, schedule.medicine_id).await;
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].scheduled_time, utc("2025-03-09T07:00:00Z"));
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn interval_schedule_fires_every_n_hours_from_its_start() {
+    request::<App, _, _>(|request, ctx| async move {
+        let schedule =
+            create_schedule_in_timezone(&request, &ctx, "America/New_York", "08:00").await;
+        let mut schedule = schedule.into_active_model();
+        schedule.frequency = ActiveValue::Set("custom".to_string());
+        schedule.interval_count = ActiveValue::Set(Some(8));
+        schedule.interval_unit = ActiveValue::Set(Some("hours".to_string()));
+        schedule.start_date = ActiveValue::Set(NaiveDate::from_ymd_opt(2025, 6, 9));
+        let schedule = schedule.update(&ctx.db).await.unwrap();
+
+        // ニューヨークの 2025-06-09 00:00 〜 2025-06-10 00:00
+        MedicationReminderTask
+            .process_due_schedules(
+                &ctx,
+                New_York,
+                &[Some("America/New_York".to_string())],
+                utc("2025-06-09T04:00:00Z"),
+                utc("2025-06-10T04:00:00Z"),
+            )
+            .await
+            .unwrap();
+
+        // 起点の 08:00 より前の 00:00 は対象外
+        let mut times = find_logs_for(&ctx, schedule.medicine_id)
+            .await
+            .into_iter()
+            .map(|log| log.scheduled_time)
+            .collect::<Vec<_>>();
+        times.sort();
+        assert_eq!(
+            times,
+            vec![
+                utc("2025-06-09T12:00:00Z"),
+                utc("2025-06-09T20:00:00Z"),
+                utc("2025-06-10T04:00:00Z"),
+            ]
+        );
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn rrule_schedule_fires_on_its_occurrences() {
+    request::<App, _, _>(|request, ctx| async move {
+        let schedule =
+            create_schedule_in_timezone(&request, &ctx, "America/New_York", "08:00").await;
+        let mut schedule = schedule.into_active_model();
+        schedule.frequency = ActiveValue::Set("rrule".to_string());
+        schedule.rrule = ActiveValue::Set(Some("FREQ=MONTHLY;BYDAY=1MO".to_string()));
+        schedule.start_date = ActiveValue::Set(NaiveDate::from_ymd_opt(2025, 6, 1));
+        let schedule = schedule.update(&ctx.db).await.unwrap();
+
+        // 2025-07-07 は7月の第1月曜日、2025-07-14 は第2月曜日
+        for morning in [utc("2025-07-07T12:00:00Z"), utc("2025-07-14T12:00:00Z")] {
+            MedicationReminderTask
+                .process_due_schedules(
+                    &ctx,
+                    New_York,
+                    &[Some("America/New_York".to_string())],
+                    morning - Duration::minutes(1),
+                    morning,
+                )
+                .await
+                .unwrap();
+        }
+
+        let logs = find_logs_for(&ctx, schedule.medicine_id).await;
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].scheduled_time, utc("2025-07-07T12:00:00Z"));
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn low_stock_is_alerted_once_until_refilled() {
+    request::<App, _, _>(|request, ctx| async move {
+        let schedule =
+            create_schedule_in_timezone(&request, &ctx, "America/New_York", "08:00").await;
+        let medicine = medicines::Entity::find_by_id(schedule.medicine_id)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        let mut medicine = medicine.into_active_model();
+        medicine.stock_quantity = ActiveValue::Set(Some(Decimal::from(10)));
+        let medicine = medicine.update(&ctx.db).await.unwrap();
+        let now = utc("2025-06-09T12:00:00Z");
+
+        // 10日分あれば通知しない（既定は7日未満で通知）
+        MedicationReminderTask.check_refills(&ctx, now).await.unwrap();
+        let unchanged = medicines::Entity::find_by_id(medicine.id).one(&ctx.db).await.unwrap().unwrap();
+        assert_eq!(unchanged.refill_alerted_at, None);
+
+        // 残り5日分になっても、確認は現地の日付ごとに1度だけ
+        let mut low = unchanged.into_active_model();
+        low.stock_quantity = ActiveValue::Set(Some(Decimal::from(5)));
+        low.update(&ctx.db).await.unwrap();
+        MedicationReminderTask
+            .check_refills(&ctx, utc("2025-06-10T03:59:00Z"))
+            .await
+            .unwrap();
+        let unchanged = medicines::Entity::find_by_id(medicine.id).one(&ctx.db).await.unwrap().unwrap();
+        assert_eq!(unchanged.refill_alerted_at, None);
+
+        // ニューヨークの翌日に通知する（LINEのトークンが無いので届かずに保存される）
+        let next_day = utc("2025-06-10T04:00:00Z");
+        let no_token = mock_server::without_access_token(&ctx);
+        MedicationReminderTask.check_refills(&no_token, next_day).await.unwrap();
+        assert_eq!(parked_notification_types(&ctx).await, ["refill_reminder"]);
+
+        // 通知済みなので補充されるまで再通知しない
+        MedicationReminderTask
+            .check_refills(&ctx, utc("2025-06-11T12:00:00Z"))
+            .await
+            .unwrap();
+        let alerted = medicines::Entity::find_by_id(medicine.id).one(&ctx.db).await.unwrap().unwrap();
+        assert_eq!(alerted.refill_alerted_at, Some(next_day.into()));
+    })
+    .await;
+}
+
+/// 届かずに保存された通知の種類（古い順）
+async fn parked_notification_types(ctx: &AppContext) -> Vec<String> {
+    notification_dead_letters::Entity::find_pending()
+        .all(&ctx.db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|dead_letter| dead_letter.notification_type)
+        .collect()
+}
+
+async fn find_schedule(ctx: &AppContext, id: i32) -> medication_schedules::Model {
+    medication_schedules::Entity::find_by_id(id)
+        .one(&ctx.db)
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+#[tokio::test]
+#[serial]
+async fn course_finishes_after_max_doses() {
+    request::<App, _, _>(|request, ctx| async move {
+        let schedule =
+            create_schedule_in_timezone(&request, &ctx, "America/New_York", "08:00").await;
+        let mut schedule = schedule.into_active_model();
+        schedule.max_doses = ActiveValue::Set(Some(2));
+        let schedule = schedule.update(&ctx.db).await.unwrap();
+
+        // 3日分処理しても通知は2回まで
+        let process_day = |day: &'static str| {
+            let ctx = ctx.clone();
+            async move {
+                let morning = utc(day);
+                MedicationReminderTask
+                    .process_due_schedules(
+                        &ctx,
+                        New_York,
+                        &[Some("America/New_York".to_string())],
+                        morning - Duration::minutes(1),
+                        morning,
+                    )
+                    .await
+            }
+        };
+        process_day("2025-06-09T12:00:00Z").await.unwrap();
+        assert!(find_schedule(&ctx, schedule.id).await.active.unwrap());
+
+        // 2回目の通知でコースを終了する
+        process_day("2025-06-10T12:00:00Z").await.unwrap();
+        let finished = find_schedule(&ctx, schedule.id).await;
+        assert_eq!(finished.dose_count, 2);
+        assert_eq!(finished.active, Some(false));
+
+        process_day("2025-06-11T12:00:00Z").await.unwrap();
+        assert_eq!(find_logs_for(&ctx, schedule.medicine_id).await.len(), 2);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn course_finishes_after_max_doses_even_if_reminder_fails() {
+    request::<App, _, _>(|request, ctx| async move {
+        let schedule =
+            create_schedule_in_timezone(&request, &ctx, "America/New_York", "08:00").await;
+        let mut schedule = schedule.into_active_model();
+        schedule.max_doses = ActiveValue::Set(Some(1));
+        let schedule = schedule.update(&ctx.db).await.unwrap();
+
+        // 最後のリマインダーが届かなくても、コースは終了する
+        let no_token = mock_server::without_access_token(&ctx);
+        let current_time = utc("2025-06-09T12:00:00Z").with_timezone(&New_York);
+        MedicationReminderTask
+            .process_medication_schedule(&no_token, &schedule, current_time)
+            .await
+            .unwrap();
+        assert_eq!(
+            parked_notification_types(&ctx).await,
+            ["medication_reminder", "course_finished"]
+        );
+        let finished = find_schedule(&ctx, schedule.id).await;
+        assert_eq!(finished.dose_count, 1);
+        assert_eq!(finished.active, Some(false));
+
+        // 回数に達したままアクティブなスケジュールは、終了日が無くても見回りで終了する
+        let mut stuck = finished.into_active_model();
+        stuck.active = ActiveValue::Set(Some(true));
+        let stuck = stuck.update(&ctx.db).await.unwrap();
+        MedicationReminderTask
+            .finish_ended_courses(
+                &ctx,
+                &[Some("America/New_York".to_string())],
+                NaiveDate::from_ymd_opt(2025, 6, 9).unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(find_schedule(&ctx, stuck.id).await.active, Some(false));
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn course_finishes_after_end_date() {
+    request::<App, _, _>(|request, ctx| async move {
+        let schedule =
+            create_schedule_in_timezone(&request, &ctx, "America/New_York", "08:00").await;
+        let mut schedule = schedule.into_active_model();
+        schedule.end_date = ActiveValue::Set(NaiveDate::from_ymd_opt(2025, 6, 9));
+        let schedule = schedule.update(&ctx.db).await.unwrap();
+        let timezone_names = [Some("America/New_York".to_string())];
+
+        MedicationReminderTask
+            .finish_ended_courses(&ctx, &timezone_names, NaiveDate::from_ymd_opt(2025, 6, 9).unwrap())
+            .await
+            .unwrap();
+        assert!(find_schedule(&ctx, schedule.id).await.active.unwrap());
+
+        // 終了日の翌日にコース終了を通知する（LINEのトークンが無いので届かずに保存される）
+        let no_token = mock_server::without_access_token(&ctx);
+        MedicationReminderTask
+            .finish_ended_courses(&no_token, &timezone_names, NaiveDate::from_ymd_opt(2025, 6, 10).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(parked_notification_types(&ctx).await, ["course_finished"]);
+        assert_eq!(find_schedule(&ctx, schedule.id).await.active, Some(false));
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn first_tick_processes_only_the_current_minute() {
+    request::<App, _, _>(|request, ctx| async move {
+        let schedule =
+            create_schedule_in_timezone(&request, &ctx, "America/New_York", "08:00").await;
+
+        // 処理済み時刻が無い状態で 08:05 に起動しても 08:00 の分はさかのぼらない
+        MedicationReminderTask
+            .tick(&ctx, utc("2025-06-09T12:05:10Z"))
+            .await
+            .unwrap();
+        assert!(find_logs_for(&ctx, schedule.medicine_id).await.is_empty());
+        assert_eq!(
+            scheduler_states::Model::last_run_at(&ctx.db, "medication_reminder")
+                .await
+                .unwrap(),
+            Some(utc("2025-06-09T12:05:00Z").into())
+        );
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn late_tick_catches_up_since_watermark_without_duplicates() {
+    request::<App, _, _>(|request, ctx| async move {
+        let schedule =
+            create_schedule_in_timezone(&request, &ctx, "America/New_York", "08:00").await;
+
+        // 07:58 に処理した後、次の実行が 08:03 まで遅れた
+        MedicationReminderTask
+            .tick(&ctx, utc("2025-06-09T11:58:00Z"))
+            .await
+            .unwrap();
+        MedicationReminderTask
+            .tick(&ctx, utc("2025-06-09T12:03:27Z"))
+            .await
+            .unwrap();
+
+        let logs = find_logs_for(&ctx, schedule.medicine_id).await;
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].scheduled_time, utc("2025-06-09T12:00:00Z"));
+
+        // 保存された処理済み時刻から再開するので、同じ時刻を繰り返し処理しない
+        for now in ["2025-06-09T12:03:50Z", "2025-06-09T12:10:00Z"] {
+            MedicationReminderTask
+                .tick(&ctx, utc(now))
+                .await
+                .unwrap();
+        }
+        assert_eq!(find_logs_for(&ctx, schedule.medicine_id).await.len(), 1);
+        assert_eq!(
+            scheduler_states::Model::last_run_at(&ctx.db, "medication_reminder")
+                .await
+                .unwrap(),
+            Some(utc("2025-06-09T12:10:00Z").into())
+        );
+    })
+    .await;
+}
@@ -1,3 +1,3 @@
 
 
-pub mod medication_reminder;
\ No newline at end of file
+pub mod medication_reminder;pub mod notification_dead_letters;
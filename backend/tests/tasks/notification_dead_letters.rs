@@ -0,0 +1,135 @@
+use backend::{
+    app::App,
+    models::notification_dead_letters,
+    tasks::notification_dead_letters::NotificationDeadLettersTask,
+    workers::notification_worker::{NotificationWorker, NotificationWorkerArgs},
+};
+use loco_rs::{bgworker::BackgroundWorker, boot::run_task, task, testing::prelude::*};
+use sea_orm::EntityTrait;
+use serial_test::serial;
+
+use crate::line::mock_server::{self, MockLineServer};
+
+fn args(message: &str) -> NotificationWorkerArgs {
+    NotificationWorkerArgs {
+        user_id: None,
+        line_user_id: Some("U-dead-letter".to_string()),
+        message: message.to_string(),
+        notification_type: "general".to_string(),
+        medicine_id: None,
+        log_id: None,
+        report: None,
+        dead_letter_id: None,
+    }
+}
+
+fn replay_vars(target: &str) -> task::Vars {
+    task::Vars::from_cli_args(vec![("replay".to_string(), target.to_string())])
+}
+
+#[tokio::test]
+#[serial]
+async fn dead_letters_can_be_listed_and_replayed() {
+    let boot = boot_test::<App>().await.unwrap();
+    let ctx = &boot.app_context;
+    let task_name = Some("notification_dead_letters".to_string());
+
+    // アクセストークンが無いので届かず、保存される
+    let no_token = mock_server::without_access_token(ctx);
+    for message in ["first", "second"] {
+        NotificationWorker::perform_later(&no_token, args(message)).await.unwrap();
+    }
+    let parked = notification_dead_letters::Entity::find_pending()
+        .all(&ctx.db)
+        .await
+        .unwrap();
+    assert_eq!(parked.len(), 2);
+    assert!(run_task::<App>(ctx, task_name.as_ref(), &task::Vars::default())
+        .await
+        .is_ok());
+
+    // 設定を直してから再送する
+    let line = MockLineServer::start().await;
+    let ctx = &line.context(ctx);
+    run_task::<App>(ctx, task_name.as_ref(), &replay_vars(&parked[0].id.to_string()))
+        .await
+        .unwrap();
+    assert_eq!(line.pushes().len(), 1);
+    assert_eq!(line.pushes()[0]["to"], "U-dead-letter");
+    let pending = notification_dead_letters::Entity::find_pending()
+        .all(&ctx.db)
+        .await
+        .unwrap();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].id, parked[1].id);
+
+    // 再送済みのものは再送しない
+    let replayed = notification_dead_letters::Entity::find_by_id(parked[0].id)
+        .one(&ctx.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(replayed.is_replayed());
+    assert!(NotificationDeadLettersTask::replay(ctx, replayed).await.is_err());
+
+    run_task::<App>(ctx, task_name.as_ref(), &replay_vars("all"))
+        .await
+        .unwrap();
+    let pending = notification_dead_letters::Entity::find_pending()
+        .all(&ctx.db)
+        .await
+        .unwrap();
+    assert!(pending.is_empty());
+    assert_eq!(line.pushes().len(), 2);
+
+    assert!(run_task::<App>(ctx, task_name.as_ref(), &replay_vars("9999"))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+#[serial]
+async fn failed_replay_keeps_a_single_dead_letter() {
+    let boot = boot_test::<App>().await.unwrap();
+    let ctx = &boot.app_context;
+
+    let no_token = mock_server::without_access_token(ctx);
+    NotificationWorker::perform_later(&no_token, args("first")).await.unwrap();
+    let parked = notification_dead_letters::Entity::find_pending()
+        .all(&ctx.db)
+        .await
+        .unwrap();
+    assert_eq!(parked.len(), 1);
+
+    // 再送でも届かなければ、新しく保存せずに元の通知を未再送のまま残す
+    for _ in 0..2 {
+        let dead_letter = notification_dead_letters::Entity::find_by_id(parked[0].id)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(NotificationDeadLettersTask::replay(&no_token, dead_letter)
+            .await
+            .is_err());
+    }
+    let all = notification_dead_letters::Entity::find()
+        .all(&ctx.db)
+        .await
+        .unwrap();
+    assert_eq!(all.len(), 1);
+    assert_eq!(all[0].id, parked[0].id);
+    assert!(!all[0].is_replayed());
+
+    // 直してから再送すれば届く
+    let line = MockLineServer::start().await;
+    let ctx = &line.context(ctx);
+    NotificationDeadLettersTask::replay(ctx, all[0].clone())
+        .await
+        .unwrap();
+    assert_eq!(line.pushes().len(), 1);
+    assert!(notification_dead_letters::Entity::find_pending()
+        .all(&ctx.db)
+        .await
+        .unwrap()
+        .is_empty());
+}
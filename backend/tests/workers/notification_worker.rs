@@ -1,10 +1,40 @@
-use loco_rs::{bgworker::BackgroundWorker, testing::prelude::*};
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    extract::State,
+    http::{
+        header::{LOCATION, RETRY_AFTER},
+        HeaderMap, HeaderValue, StatusCode,
+    },
+    routing::post,
+    Json, Router,
+};
+use loco_rs::{app::AppContext, bgworker::BackgroundWorker, testing::prelude::*};
 use backend::{
     app::App,
+    models::{
+        notification_dead_letters,
+        notification_logs::{self, NotificationStatus},
+        users::{self, RegisterParams},
+    },
+    notifications::{LineChannel, NotificationChannelKind, NotificationStrategy},
     workers::notification_worker::{NotificationWorker, NotificationWorkerArgs},
 };
+use sea_orm::{ActiveModelTrait, ActiveValue, EntityTrait, IntoActiveModel, QueryOrder};
+use insta::assert_snapshot;
 use serial_test::serial;
 
+use crate::line::mock_server::{self, MockLineServer};
+
+macro_rules! configure_insta {
+    ($($expr:expr),*) => {
+        let mut settings = insta::Settings::clone_current();
+        settings.set_prepend_module_to_snapshot(false);
+        settings.set_snapshot_suffix("notification_worker");
+        let _guard = settings.bind_to_scope();
+    };
+}
+
 #[tokio::test]
 #[serial]
 async fn test_run_notification_worker_worker() {
@@ -13,14 +43,566 @@ async fn test_run_notification_worker_worker() {
     // Execute the worker ensuring that it operates in 'ForegroundBlocking' mode, which prevents the addition of your worker to the background
     assert!(
         NotificationWorker::perform_later(&boot.app_context, NotificationWorkerArgs {
-            line_user_id: "test_user".to_string(),
+            user_id: None,
+            line_user_id: Some("test_user".to_string()),
             message: "Test notification".to_string(),
             notification_type: "general".to_string(),
             medicine_id: None,
             log_id: None,
+            report: None,
+            dead_letter_id: None,
         })
             .await
             .is_ok()
     );
     // Include additional assert validations after the execution of the worker
 }
+
+#[test]
+fn reminder_buttons_are_postbacks_carrying_the_log_id() {
+    let line = LineChannel {
+        line_user_id: "test_user".to_string(),
+    };
+
+    let args = NotificationWorkerArgs {
+        user_id: None,
+        line_user_id: Some("test_user".to_string()),
+        message: "Test notification".to_string(),
+        notification_type: "medication_reminder".to_string(),
+        medicine_id: Some(3),
+        log_id: Some(12),
+        report: None,
+        dead_letter_id: None,
+    };
+
+    let reminder = serde_json::to_value(line.medication_reminder_message(&args)).unwrap();
+    let actions: Vec<_> = reminder["contents"]["footer"]["contents"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|button| &button["action"])
+        .collect();
+    assert_eq!(actions[0]["type"], "postback");
+    assert_eq!(actions[0]["data"], "action=complete&log_id=12&medicine_id=3");
+    assert_eq!(actions[1]["data"], "action=snooze&log_id=12&medicine_id=3");
+
+    let missed = serde_json::to_value(line.missed_medication_message(&args)).unwrap();
+    let actions: Vec<_> = missed["contents"]["footer"]["contents"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|button| &button["action"])
+        .collect();
+    assert_eq!(actions[0]["data"], "action=complete&log_id=12&medicine_id=3");
+    assert_eq!(actions[1]["data"], "action=missed&log_id=12&medicine_id=3");
+}
+
+#[tokio::test]
+#[serial]
+async fn immediate_notification_runs_worker() {
+    let boot = boot_test::<App>().await.unwrap();
+
+    // ForegroundBlocking ではワーカーがその場で実行され、届かなければ保存される
+    let no_token = mock_server::without_access_token(&boot.app_context);
+    App::send_immediate_notification(
+        &no_token,
+        "test_user".to_string(),
+        "Test notification".to_string(),
+        "general".to_string(),
+    )
+    .await
+    .unwrap();
+    assert!(parked_errors(&boot.app_context).await[0].contains("LINE notification failed"));
+
+    assert!(App::send_immediate_notification(
+        &boot.app_context,
+        "test_user".to_string(),
+        "Test notification".to_string(),
+        "general".to_string(),
+    )
+    .await
+    .is_ok());
+}
+
+#[tokio::test]
+#[serial]
+async fn every_attempt_is_recorded() {
+    let boot = boot_test::<App>().await.unwrap();
+    let args = NotificationWorkerArgs {
+        user_id: None,
+        line_user_id: Some("unknown_user".to_string()),
+        message: "Test notification".to_string(),
+        notification_type: "medication_reminder".to_string(),
+        medicine_id: Some(3),
+        log_id: Some(12),
+        report: None,
+        dead_letter_id: None,
+    };
+
+    let no_token = mock_server::without_access_token(&boot.app_context);
+    NotificationWorker::perform_later(&no_token, args.clone())
+        .await
+        .unwrap();
+    NotificationWorker::perform_later(&boot.app_context, args)
+        .await
+        .unwrap();
+
+    let logs = notification_logs::Entity::find()
+        .order_by_asc(notification_logs::Column::Id)
+        .all(&boot.app_context.db)
+        .await
+        .unwrap();
+    assert_eq!(logs.len(), 2);
+    let (failed, skipped) = (&logs[0], &logs[1]);
+    assert_eq!(failed.status, NotificationStatus::Failed);
+    assert!(failed.error.as_deref().unwrap().contains("access token is not configured"));
+    assert_eq!(skipped.status, NotificationStatus::Skipped);
+    assert_eq!(skipped.error, None);
+    assert_eq!(skipped.sent_at, None);
+    // 紐づくユーザーがいなくても宛先と対象の記録は残す
+    assert_eq!(skipped.user_id, None);
+    assert_eq!(skipped.recipient, "unknown_user");
+    assert_eq!((skipped.medicine_id, skipped.log_id), (Some(3), Some(12)));
+    // 同じ内容を送ればハッシュも同じになる
+    assert_eq!(failed.payload_hash, skipped.payload_hash);
+    assert_eq!(skipped.payload_hash.len(), 64);
+}
+
+/// 通知の経路を設定したユーザーを作成する
+async fn create_user_with_channels(
+    ctx: &AppContext,
+    channels: &str,
+    strategy: NotificationStrategy,
+    line_user_id: Option<&str>,
+    webhook_url: Option<String>,
+) -> users::Model {
+    let user = users::Model::create_with_password(
+        &ctx.db,
+        &RegisterParams {
+            email: "channels@loco.com".to_string(),
+            password: "1234".to_string(),
+            name: "loco".to_string(),
+        },
+    )
+    .await
+    .unwrap();
+    let mut user = user.into_active_model();
+    user.notification_channels = ActiveValue::Set(channels.to_string());
+    user.notification_strategy = ActiveValue::Set(strategy);
+    user.line_user_id = ActiveValue::Set(line_user_id.map(ToString::to_string));
+    user.webhook_url = ActiveValue::Set(webhook_url);
+    user.update(&ctx.db).await.unwrap()
+}
+
+fn user_args(user: &users::Model) -> NotificationWorkerArgs {
+    NotificationWorkerArgs {
+        user_id: Some(user.id),
+        line_user_id: None,
+        message: "お薬の時間です".to_string(),
+        notification_type: "general".to_string(),
+        medicine_id: None,
+        log_id: None,
+        report: None,
+        dead_letter_id: None,
+    }
+}
+
+async fn find_notification_logs(ctx: &AppContext) -> Vec<(NotificationChannelKind, NotificationStatus)> {
+    notification_logs::Entity::find()
+        .order_by_asc(notification_logs::Column::Id)
+        .all(&ctx.db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|log| (log.channel, log.status))
+        .collect()
+}
+
+/// テスト用の受信サーバー（127.0.0.1）にWebhookを送れるようにしたコンテキスト
+fn allow_private_webhooks(ctx: &AppContext) -> AppContext {
+    let mut settings = ctx.config.settings.clone().unwrap_or_else(|| serde_json::json!({}));
+    settings["webhook"]["allow_private_hosts"] = true.into();
+    let mut ctx = ctx.clone();
+    ctx.config.settings = Some(settings);
+    ctx
+}
+
+type Received = Arc<Mutex<Vec<serde_json::Value>>>;
+
+/// 受け取ったJSONを記録するWebhookの受信サーバーを起動し、URLを返す
+///
+/// `responses` の応答（ステータスと `Retry-After`）を順に返し、最後の応答を繰り返す。
+async fn start_webhook_server(responses: Vec<(StatusCode, Option<&'static str>)>) -> (String, Received) {
+    let received: Received = Arc::new(Mutex::new(Vec::new()));
+    let app = Router::new()
+        .route(
+            "/hook",
+            post(
+                move |State(received): State<Received>, Json(body): Json<serde_json::Value>| async move {
+                    let mut received = received.lock().unwrap();
+                    received.push(body);
+                    let (status, retry_after) =
+                        responses[(received.len() - 1).min(responses.len() - 1)];
+                    let mut headers = HeaderMap::new();
+                    if let Some(retry_after) = retry_after {
+                        headers.insert(RETRY_AFTER, HeaderValue::from_static(retry_after));
+                    }
+                    (status, headers)
+                },
+            ),
+        )
+        .with_state(received.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (format!("http://{addr}/hook"), received)
+}
+
+#[tokio::test]
+#[serial]
+async fn user_without_line_is_notified_by_email() {
+    let boot = boot_test::<App>().await.unwrap();
+    let ctx = &boot.app_context;
+    let user = create_user_with_channels(ctx, "line,email", NotificationStrategy::Fallback, None, None).await;
+
+    // LINE未連携なのでメールだけで送る
+    NotificationWorker::perform_later(ctx, user_args(&user)).await.unwrap();
+
+    assert_eq!(ctx.mailer.as_ref().unwrap().deliveries().count, 1);
+    let logs = notification_logs::Entity::find().all(&ctx.db).await.unwrap();
+    assert_eq!(logs.len(), 1);
+    assert_eq!(logs[0].channel, NotificationChannelKind::Email);
+    assert_eq!(logs[0].status, NotificationStatus::Sent);
+    assert_eq!(logs[0].recipient, "channels@loco.com");
+    assert_eq!(logs[0].user_id, Some(user.id));
+}
+
+#[tokio::test]
+#[serial]
+async fn falls_back_when_preferred_channel_fails() {
+    let boot = boot_test::<App>().await.unwrap();
+    let ctx = &allow_private_webhooks(&boot.app_context);
+    let (url, received) = start_webhook_server(vec![(StatusCode::OK, None)]).await;
+    let user = create_user_with_channels(
+        ctx,
+        "line,webhook,email",
+        NotificationStrategy::Fallback,
+        Some("U-fallback"),
+        Some(url),
+    )
+    .await;
+
+    let no_token = mock_server::without_access_token(ctx);
+    NotificationWorker::perform_later(&no_token, user_args(&user)).await.unwrap();
+
+    // Webhookで届いたのでメールは送らない
+    assert_eq!(
+        find_notification_logs(ctx).await,
+        [
+            (NotificationChannelKind::Line, NotificationStatus::Failed),
+            (NotificationChannelKind::Webhook, NotificationStatus::Sent),
+        ]
+    );
+    assert_eq!(ctx.mailer.as_ref().unwrap().deliveries().count, 0);
+    let received = received.lock().unwrap();
+    assert_eq!(received.len(), 1);
+    assert_eq!(received[0]["type"], "general");
+    assert_eq!(received[0]["message"], "お薬の時間です");
+}
+
+#[tokio::test]
+#[serial]
+async fn fan_out_sends_to_every_channel() {
+    let boot = boot_test::<App>().await.unwrap();
+    let ctx = &allow_private_webhooks(&boot.app_context);
+    let (url, received) = start_webhook_server(vec![(StatusCode::BAD_REQUEST, None)]).await;
+    let user = create_user_with_channels(
+        ctx,
+        "webhook,email",
+        NotificationStrategy::FanOut,
+        None,
+        Some(url),
+    )
+    .await;
+
+    // 一部の経路が失敗しても、どれかで届けば成功とする
+    NotificationWorker::perform_later(ctx, user_args(&user)).await.unwrap();
+    assert_eq!(
+        find_notification_logs(ctx).await,
+        [
+            (NotificationChannelKind::Webhook, NotificationStatus::Failed),
+            (NotificationChannelKind::Email, NotificationStatus::Sent),
+        ]
+    );
+    assert_eq!(received.lock().unwrap().len(), 1);
+
+    // すべての経路で失敗した場合は保存する
+    let mut user = user.into_active_model();
+    user.notification_channels = ActiveValue::Set("webhook".to_string());
+    let user = user.update(&ctx.db).await.unwrap();
+    NotificationWorker::perform_later(ctx, user_args(&user)).await.unwrap();
+    assert!(parked_errors(ctx).await.last().unwrap().contains("webhook notification failed"));
+}
+
+#[tokio::test]
+#[serial]
+async fn skipped_channel_does_not_count_as_delivered() {
+    let boot = boot_test::<App>().await.unwrap();
+    let ctx = &allow_private_webhooks(&boot.app_context);
+    let (url, received) = start_webhook_server(vec![(StatusCode::BAD_REQUEST, None)]).await;
+    let user = create_user_with_channels(
+        ctx,
+        "line,webhook",
+        NotificationStrategy::Fallback,
+        Some("U-skipped"),
+        Some(url),
+    )
+    .await;
+
+    // テストの設定ではLINEは送信されないので、Webhookを試してその失敗を保存する
+    NotificationWorker::perform_later(ctx, user_args(&user)).await.unwrap();
+    assert!(parked_errors(ctx).await.last().unwrap().contains("webhook notification failed"));
+    assert_eq!(
+        find_notification_logs(ctx).await,
+        [
+            (NotificationChannelKind::Line, NotificationStatus::Skipped),
+            (NotificationChannelKind::Webhook, NotificationStatus::Failed),
+        ]
+    );
+    assert_eq!(received.lock().unwrap().len(), 1);
+    let dead_letters = notification_dead_letters::Entity::find_pending()
+        .all(&ctx.db)
+        .await
+        .unwrap();
+    assert_eq!(dead_letters.len(), 1);
+}
+
+/// 届かずに保存された通知のエラー（古い順）
+async fn parked_errors(ctx: &AppContext) -> Vec<String> {
+    notification_dead_letters::Entity::find_pending()
+        .all(&ctx.db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|dead_letter| dead_letter.error)
+        .collect()
+}
+
+async fn find_attempts(ctx: &AppContext) -> Vec<(i32, NotificationStatus)> {
+    notification_logs::Entity::find()
+        .order_by_asc(notification_logs::Column::Id)
+        .all(&ctx.db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|log| (log.attempt, log.status))
+        .collect()
+}
+
+#[tokio::test]
+#[serial]
+async fn retryable_errors_are_retried_until_delivered() {
+    let boot = boot_test::<App>().await.unwrap();
+    let ctx = &allow_private_webhooks(&boot.app_context);
+    let (url, received) = start_webhook_server(vec![
+        (StatusCode::SERVICE_UNAVAILABLE, None),
+        (StatusCode::TOO_MANY_REQUESTS, Some("0")),
+        (StatusCode::OK, None),
+    ])
+    .await;
+    let user =
+        create_user_with_channels(ctx, "webhook", NotificationStrategy::Fallback, None, Some(url)).await;
+
+    NotificationWorker::perform_later(ctx, user_args(&user)).await.unwrap();
+
+    assert_eq!(received.lock().unwrap().len(), 3);
+    assert_eq!(
+        find_attempts(ctx).await,
+        [
+            (1, NotificationStatus::Failed),
+            (2, NotificationStatus::Failed),
+            (3, NotificationStatus::Sent),
+        ]
+    );
+    let dead_letters = notification_dead_letters::Entity::find().all(&ctx.db).await.unwrap();
+    assert!(dead_letters.is_empty());
+}
+
+#[tokio::test]
+#[serial]
+async fn undeliverable_notifications_are_parked() {
+    let boot = boot_test::<App>().await.unwrap();
+    let ctx = &allow_private_webhooks(&boot.app_context);
+
+    // 再送の上限（テストの設定では3回）まで送って諦める
+    let (url, received) = start_webhook_server(vec![(StatusCode::BAD_GATEWAY, None)]).await;
+    let user =
+        create_user_with_channels(ctx, "webhook", NotificationStrategy::Fallback, None, Some(url)).await;
+    NotificationWorker::perform_later(ctx, user_args(&user)).await.unwrap();
+    assert!(parked_errors(ctx).await.last().unwrap().contains("webhook notification failed"));
+    assert_eq!(received.lock().unwrap().len(), 3);
+
+    let dead_letters = notification_dead_letters::Entity::find_pending()
+        .all(&ctx.db)
+        .await
+        .unwrap();
+    assert_eq!(dead_letters.len(), 1);
+    assert_eq!(dead_letters[0].user_id, Some(user.id));
+    assert_eq!(dead_letters[0].attempts, 3);
+    assert!(dead_letters[0].error.contains("502"));
+    let args: NotificationWorkerArgs = serde_json::from_value(dead_letters[0].args.clone()).unwrap();
+    assert_eq!(args.user_id, Some(user.id));
+    assert_eq!(args.message, "お薬の時間です");
+
+    // 再送しても届かない応答と、上限より長い Retry-After は1回で諦める
+    for response in [(StatusCode::NOT_FOUND, None), (StatusCode::TOO_MANY_REQUESTS, Some("3600"))] {
+        let (url, received) = start_webhook_server(vec![response]).await;
+        let mut user = user.clone().into_active_model();
+        user.webhook_url = ActiveValue::Set(Some(url));
+        let user = user.update(&ctx.db).await.unwrap();
+        NotificationWorker::perform_later(ctx, user_args(&user)).await.unwrap();
+        assert_eq!(received.lock().unwrap().len(), 1);
+    }
+    let dead_letters = notification_dead_letters::Entity::find_pending()
+        .all(&ctx.db)
+        .await
+        .unwrap();
+    let attempts: Vec<_> = dead_letters.iter().map(|dead_letter| dead_letter.attempts).collect();
+    assert_eq!(attempts, [3, 1, 1]);
+}
+
+#[tokio::test]
+#[serial]
+async fn failed_job_is_parked_once_and_not_retried_by_the_queue() {
+    let boot = boot_test::<App>().await.unwrap();
+    let ctx = &mock_server::without_access_token(&allow_private_webhooks(&boot.app_context));
+    let (url, received) = start_webhook_server(vec![(StatusCode::BAD_GATEWAY, None)]).await;
+    let user = create_user_with_channels(
+        ctx,
+        "line,webhook",
+        NotificationStrategy::FanOut,
+        Some("U-all-fail"),
+        Some(url),
+    )
+    .await;
+
+    // 再送はワーカーの中で済ませるので、保存したらキューには成功を返す
+    NotificationWorker::build(ctx)
+        .perform(user_args(&user))
+        .await
+        .unwrap();
+    assert_eq!(received.lock().unwrap().len(), 3);
+    let dead_letters = notification_dead_letters::Entity::find()
+        .all(&ctx.db)
+        .await
+        .unwrap();
+    assert_eq!(dead_letters.len(), 1);
+    assert_eq!(dead_letters[0].attempts, 4);
+    assert!(dead_letters[0].error.contains("webhook notification failed"));
+}
+
+#[tokio::test]
+#[serial]
+async fn webhooks_to_internal_hosts_are_refused() {
+    let boot = boot_test::<App>().await.unwrap();
+    let ctx = &boot.app_context;
+    let (url, received) = start_webhook_server(vec![(StatusCode::OK, None)]).await;
+    let user =
+        create_user_with_channels(ctx, "webhook", NotificationStrategy::Fallback, None, Some(url)).await;
+
+    // 保存後に内部のアドレスに変えられても、送る前に拒否して再送もしない
+    NotificationWorker::perform_later(ctx, user_args(&user)).await.unwrap();
+    assert!(parked_errors(ctx).await.last().unwrap().contains("internal host"));
+    assert!(received.lock().unwrap().is_empty());
+    assert_eq!(find_attempts(ctx).await, [(1, NotificationStatus::Failed)]);
+}
+
+#[tokio::test]
+#[serial]
+async fn webhook_redirects_are_not_followed() {
+    let boot = boot_test::<App>().await.unwrap();
+    let ctx = &allow_private_webhooks(&boot.app_context);
+    let (target, received) = start_webhook_server(vec![(StatusCode::OK, None)]).await;
+    let app = Router::new().route(
+        "/hook",
+        post(move || async move { (StatusCode::TEMPORARY_REDIRECT, [(LOCATION, target)]) }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    let user = create_user_with_channels(
+        ctx,
+        "webhook",
+        NotificationStrategy::Fallback,
+        None,
+        Some(format!("http://{addr}/hook")),
+    )
+    .await;
+
+    NotificationWorker::perform_later(ctx, user_args(&user)).await.unwrap();
+    assert!(parked_errors(ctx).await.last().unwrap().contains("307"));
+    assert!(received.lock().unwrap().is_empty());
+}
+
+fn reminder_args() -> NotificationWorkerArgs {
+    NotificationWorkerArgs {
+        user_id: None,
+        line_user_id: Some("U-mock".to_string()),
+        message: "アスピリンを飲む時間です".to_string(),
+        notification_type: "medication_reminder".to_string(),
+        medicine_id: Some(3),
+        log_id: Some(12),
+        report: None,
+        dead_letter_id: None,
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn reminder_is_pushed_to_line_as_flex_message() {
+    configure_insta!();
+
+    let boot = boot_test::<App>().await.unwrap();
+    let line = MockLineServer::start().await;
+    let ctx = &line.context(&boot.app_context);
+
+    NotificationWorker::perform_later(ctx, reminder_args()).await.unwrap();
+
+    let calls = line.calls();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].path, "/v2/bot/message/push");
+    assert_eq!(
+        calls[0].authorization.as_deref(),
+        Some(format!("Bearer {}", mock_server::ACCESS_TOKEN).as_str())
+    );
+    assert_snapshot!(serde_json::to_string_pretty(&calls[0].body).unwrap());
+
+    let logs = notification_logs::Entity::find().all(&ctx.db).await.unwrap();
+    assert_eq!(logs.len(), 1);
+    assert_eq!(logs[0].status, NotificationStatus::Sent);
+    assert_eq!(logs[0].line_request_id.as_deref(), Some("mock-request-1"));
+}
+
+#[tokio::test]
+#[serial]
+async fn line_rate_limit_is_retried() {
+    let boot = boot_test::<App>().await.unwrap();
+    let line = MockLineServer::start().await;
+    line.respond_with(StatusCode::TOO_MANY_REQUESTS, Some("0"));
+    line.respond_with(StatusCode::INTERNAL_SERVER_ERROR, None);
+    let ctx = &line.context(&boot.app_context);
+
+    NotificationWorker::perform_later(ctx, reminder_args()).await.unwrap();
+
+    let pushes = line.pushes();
+    assert_eq!(pushes.len(), 3);
+    assert!(pushes.iter().all(|push| *push == pushes[0]));
+    assert_eq!(
+        find_attempts(ctx).await,
+        [
+            (1, NotificationStatus::Failed),
+            (2, NotificationStatus::Failed),
+            (3, NotificationStatus::Sent),
+        ]
+    );
+}
@@ -1,10 +1,17 @@
 use loco_rs::{bgworker::BackgroundWorker, testing::prelude::*};
 use backend::{
     app::App,
-    workers::report_generator::{ReportGeneratorWorker, ReportGeneratorArgs},
+    models::notification_dead_letters,
+    workers::report_generator::{
+        MedicationReport, ReportGeneratorArgs, ReportGeneratorWorker, ReportSummary,
+    },
 };
+use chrono::Local;
+use sea_orm::EntityTrait;
 use serial_test::serial;
 
+use crate::{line::mock_server, requests::prepare_data};
+
 #[tokio::test]
 #[serial]
 async fn test_run_report_generator_worker() {
@@ -24,3 +31,68 @@ async fn test_run_report_generator_worker() {
     );
     // Include additional assert validations after the execution of the worker
 }
+
+#[tokio::test]
+#[serial]
+async fn generate_report_runs_worker() {
+    let boot = boot_test::<App>().await.unwrap();
+
+    // ForegroundBlocking ではワーカーがその場で実行され、エラーがそのまま返る
+    let err = App::generate_report(&boot.app_context, i32::MAX, "daily".to_string())
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("User not found"));
+}
+
+#[tokio::test]
+#[serial]
+async fn report_notification_runs_notification_worker() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        let user = prepare_data::link_line_user(
+            &ctx,
+            logged_in.user,
+            "U4af4980629a0a1b2c3d4e5f6a7b8c9d0",
+        )
+        .await;
+
+        let report = MedicationReport {
+            user_id: user.id,
+            report_type: "daily".to_string(),
+            period: "2025-06-08".to_string(),
+            summary: ReportSummary {
+                total_scheduled: 2,
+                total_taken: 1,
+                total_missed: 1,
+                adherence_rate: 50.0,
+                most_missed_time: Some("21:00".to_string()),
+                best_adherence_medicine: None,
+                worst_adherence_medicine: None,
+            },
+            medicines: vec![],
+            recommendations: vec![],
+            generated_at: Local::now().naive_local(),
+        };
+        let worker = ReportGeneratorWorker::build(&ctx);
+
+        // 届かなかった通知が保存されるので、送信を試みたことが分かる
+        let no_token = mock_server::without_access_token(&ctx);
+        worker
+            .send_report_notification(&no_token, &user, &report)
+            .await
+            .unwrap();
+        let parked = notification_dead_letters::Entity::find_pending()
+            .all(&ctx.db)
+            .await
+            .unwrap();
+        assert_eq!(parked.len(), 1);
+        assert_eq!(parked[0].notification_type, "medication_report");
+        assert!(parked[0].error.contains("LINE notification failed"));
+
+        assert!(worker
+            .send_report_notification(&ctx, &user, &report)
+            .await
+            .is_ok());
+    })
+    .await;
+}
@@ -44,13 +44,14 @@ export function formatRelativeTime(date: Date | string) {
 }
 
 // 服薬ステータス関連ユーティリティ
-export type MedicationStatus = 'pending' | 'completed' | 'missed' | 'skipped'
+export type MedicationStatus = 'pending' | 'completed' | 'missed' | 'skipped' | 'snoozed'
 
 export function getMedicationStatusColor(status: MedicationStatus) {
   switch (status) {
     case 'completed':
       return 'success'
     case 'pending':
+    case 'snoozed':
       return 'pending'
     case 'missed':
       return 'destructive'
@@ -71,6 +72,8 @@ export function getMedicationStatusText(status: MedicationStatus) {
       return '飲み忘れ'
     case 'skipped':
       return 'スキップ'
+    case 'snoozed':
+      return 'スヌーズ中'
     default:
       return '不明'
   }
@@ -1,7 +1,7 @@
 import type { Medicine } from './medicine'
 
 // Medication Status
-export type MedicationStatus = 'pending' | 'completed' | 'missed' | 'skipped'
+export type MedicationStatus = 'pending' | 'completed' | 'missed' | 'skipped' | 'snoozed'
 
 // Medication Log Types
 export interface MedicationLog {